@@ -0,0 +1,27 @@
+#version 460 core
+// One direction of a gaussian blur. View3DRenderPipeline alternates horizontal
+// and vertical passes over the lighting pass's bright colour for bloom.
+
+in vec2 tex_coord;
+
+out vec4 frag_colour;
+
+// The previous pass's output, or the bright colour for the first pass
+layout (binding = 0) uniform sampler2D image;
+
+uniform bool horizontal;
+
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(image, 0));
+    vec2 direction = horizontal ? vec2(texel.x, 0.0) : vec2(0.0, texel.y);
+
+    vec3 colour = texture(image, tex_coord).rgb * WEIGHTS[0];
+    for (int i = 1; i < 5; i++) {
+        colour += texture(image, tex_coord + direction * i).rgb * WEIGHTS[i];
+        colour += texture(image, tex_coord - direction * i).rgb * WEIGHTS[i];
+    }
+
+    frag_colour = vec4(colour, 1.0);
+}
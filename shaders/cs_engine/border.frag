@@ -0,0 +1,21 @@
+#version 460 core
+// Border widths are fractions of the widget's size: left, right, top, bottom,
+// the same as BorderWidget's border_widths
+
+in vec2 tex_coord;
+
+out vec4 frag_colour;
+
+uniform vec4 colour;
+uniform vec4 border_widths;
+
+void main() {
+    bool inside = tex_coord.x >= border_widths.x && tex_coord.x <= 1.0 - border_widths.y
+        && tex_coord.y >= border_widths.z && tex_coord.y <= 1.0 - border_widths.w;
+
+    if (inside) {
+        discard;
+    }
+
+    frag_colour = colour;
+}
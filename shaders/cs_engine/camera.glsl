@@ -0,0 +1,5 @@
+// Filled in by Camera, the projection is only sent when it changes
+layout (std140) uniform CameraMatrices {
+    mat4 projection;
+    mat4 view;
+};
@@ -0,0 +1,32 @@
+#version 460 core
+// Draws a scene's output onto the window. Scenes with bloom come from
+// View3DRenderPipeline, whose lighting output is in linear HDR, so the bloom blur
+// is added and the result tonemapped and gamma corrected. Other scenes are
+// passed through, keeping their alpha so layers blend over the ones below.
+
+in vec2 tex_coord;
+
+out vec4 frag_colour;
+
+// Textures from the scene's get_link(), bound to units in order
+layout (binding = 0) uniform sampler2D scene_texture;
+layout (binding = 1) uniform sampler2D bloom_texture;
+
+uniform bool has_bloom;
+
+const float EXPOSURE = 1.0;
+const float GAMMA = 2.2;
+
+void main() {
+    vec4 scene = texture(scene_texture, tex_coord);
+    if (!has_bloom) {
+        frag_colour = scene;
+        return;
+    }
+
+    vec3 colour = scene.rgb + texture(bloom_texture, tex_coord).rgb;
+    colour = vec3(1.0) - exp(-colour * EXPOSURE);
+    colour = pow(colour, vec3(1.0 / GAMMA));
+
+    frag_colour = vec4(colour, 1.0);
+}
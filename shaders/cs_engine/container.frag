@@ -0,0 +1,6 @@
+#version 460 core
+// For widgets that are only drawn through their children
+
+void main() {
+    discard;
+}
@@ -0,0 +1,42 @@
+#version 460 core
+// Writes the G-buffer: position relative to the camera, so the lighting pass can
+// find the view direction without it, world normal, then colour with specular
+// strength in alpha.
+// Every mesh has diffuse, specular and normal maps, as the resource manager gives
+// meshes missing any 1x1 maps of their material's values. Specular strength is
+// read from red, which glTF metallic-roughness maps are converted to. Meshes
+// without tangents only have the normal in tbn, so their flat normal map gives
+// the vertex normal. Only opaque meshes are drawn here, and masked glTF textures only hold
+// alpha of 0 or 1, so fragments with an alpha below ALPHA_CUTOFF are cut out.
+#include "camera.glsl"
+
+#ifndef ALPHA_CUTOFF
+#define ALPHA_CUTOFF 0.5
+#endif
+
+in vec3 frag_position;
+in vec2 tex_coord;
+in mat3 tbn;
+
+layout (location = 0) out vec4 g_position;
+layout (location = 1) out vec4 g_normal;
+layout (location = 2) out vec4 g_albedo_specular;
+
+uniform sampler2D diffuse_texture;
+uniform sampler2D specular_texture;
+uniform sampler2D normal_texture;
+
+void main() {
+    vec4 albedo = texture(diffuse_texture, tex_coord);
+    if (albedo.a < ALPHA_CUTOFF) {
+        discard;
+    }
+
+    vec3 normal = normalize(tbn * (texture(normal_texture, tex_coord).rgb * 2.0 - 1.0));
+    // The view matrix only rotates and translates, so this undoes the translation
+    vec3 camera_position = -transpose(mat3(view)) * view[3].xyz;
+
+    g_position = vec4(frag_position - camera_position, 1.0);
+    g_normal = vec4(normal, 1.0);
+    g_albedo_specular = vec4(albedo.rgb, texture(specular_texture, tex_coord).r);
+}
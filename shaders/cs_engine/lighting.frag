@@ -0,0 +1,55 @@
+#version 460 core
+// Lights the G-buffer. Scenes don't have lights yet, so there is a single light
+// shining along LIGHT_DIRECTION plus ambient light, which can be changed with
+// defines. Writes the lit colour in linear HDR, then whatever is brighter than
+// BLOOM_THRESHOLD for the bloom blur. The sky isn't lit.
+
+#ifndef LIGHT_DIRECTION
+#define LIGHT_DIRECTION vec3(-0.4, -1.0, -0.6)
+#endif
+#ifndef LIGHT_COLOUR
+#define LIGHT_COLOUR vec3(1.0)
+#endif
+#ifndef AMBIENT
+#define AMBIENT 0.15
+#endif
+#ifndef SHININESS
+#define SHININESS 32.0
+#endif
+#ifndef BLOOM_THRESHOLD
+#define BLOOM_THRESHOLD 1.0
+#endif
+
+in vec2 tex_coord;
+
+layout (location = 0) out vec4 frag_colour;
+layout (location = 1) out vec4 bright_colour;
+
+// The G-buffer's textures, bound to units in order
+layout (binding = 0) uniform sampler2D g_position;
+layout (binding = 1) uniform sampler2D g_normal;
+layout (binding = 2) uniform sampler2D g_albedo_specular;
+
+void main() {
+    vec4 position = texture(g_position, tex_coord);
+    vec4 albedo_specular = texture(g_albedo_specular, tex_coord);
+    vec3 colour = albedo_specular.rgb;
+
+    // The sky is written with a position w of 0
+    if (position.w > 0.0) {
+        vec3 normal = normalize(texture(g_normal, tex_coord).xyz);
+        vec3 to_light = normalize(-LIGHT_DIRECTION);
+        // Positions are relative to the camera
+        vec3 to_camera = normalize(-position.xyz);
+        vec3 halfway = normalize(to_light + to_camera);
+
+        float diffuse = max(dot(normal, to_light), 0.0);
+        float specular = pow(max(dot(normal, halfway), 0.0), SHININESS) * albedo_specular.a;
+        colour = colour * (AMBIENT + diffuse * LIGHT_COLOUR) + specular * LIGHT_COLOUR;
+    }
+
+    frag_colour = vec4(colour, 1.0);
+
+    float brightness = dot(colour, vec3(0.2126, 0.7152, 0.0722));
+    bright_colour = vec4(brightness > BLOOM_THRESHOLD ? colour : vec3(0.0), 1.0);
+}
@@ -0,0 +1,23 @@
+#version 460 core
+// Shared by the G-buffer and transparent shaders, lighting is done in world space
+#include "vertex_layout.glsl"
+#include "camera.glsl"
+
+out vec3 frag_position;
+out vec2 tex_coord;
+out mat3 tbn;
+
+void main() {
+    vec4 world_position = model * vec4(position, 1.0);
+    mat3 normal_matrix = transpose(inverse(mat3(model)));
+
+    frag_position = world_position.xyz;
+    tex_coord = vertex_tex_coord;
+    tbn = mat3(
+        normalize(normal_matrix * tangent),
+        normalize(normal_matrix * bitangent),
+        normalize(normal_matrix * normal)
+    );
+
+    gl_Position = projection * view * world_position;
+}
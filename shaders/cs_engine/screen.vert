@@ -0,0 +1,10 @@
+#version 460 core
+// Full screen quads drawn by framebuffers, which already cover clip space
+#include "vertex_layout.glsl"
+
+out vec2 tex_coord;
+
+void main() {
+    tex_coord = vertex_tex_coord;
+    gl_Position = vec4(position.xy, 0.0, 1.0);
+}
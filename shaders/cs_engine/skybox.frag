@@ -0,0 +1,17 @@
+#version 460 core
+// Drawn into the G-buffer after the models. A position w of 0 tells the lighting
+// pass not to light it.
+
+in vec3 direction;
+
+layout (location = 0) out vec4 g_position;
+layout (location = 1) out vec4 g_normal;
+layout (location = 2) out vec4 g_albedo_specular;
+
+uniform samplerCube diffuse_texture;
+
+void main() {
+    g_position = vec4(0.0);
+    g_normal = vec4(0.0);
+    g_albedo_specular = vec4(texture(diffuse_texture, direction).rgb, 0.0);
+}
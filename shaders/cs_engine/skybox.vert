@@ -0,0 +1,12 @@
+#version 460 core
+// The view's translation is removed so the skybox moves with the camera, and
+// depth is always 1 so it is behind everything else
+#include "vertex_layout.glsl"
+#include "camera.glsl"
+
+out vec3 direction;
+
+void main() {
+    direction = position;
+    gl_Position = (projection * mat4(mat3(view)) * vec4(position, 1.0)).xyww;
+}
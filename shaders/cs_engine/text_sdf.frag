@@ -0,0 +1,34 @@
+#version 460 core
+// Text shader for fonts using FontRenderMode::Sdf. The glyph atlas stores the
+// distance to the glyph's edge in the red channel, 0.5 on the edge and higher inside.
+// Expects the widget vertex stage to pass the texture coordinates as tex_coord.
+
+in vec2 tex_coord;
+
+out vec4 frag_colour;
+
+uniform sampler2D diffuse_texture;
+uniform vec4 colour;
+uniform vec4 outline_colour;
+uniform vec4 glow_colour;
+uniform vec4 sdf_params; // Edge, outline edge, glow edge, unused
+
+void main() {
+    float distance = texture(diffuse_texture, tex_coord).r;
+    // About half a screen pixel, so edges stay sharp at any scale
+    float smoothing = max(fwidth(distance) * 0.5, 0.0001);
+
+    float fill = smoothstep(sdf_params.x - smoothing, sdf_params.x + smoothing, distance);
+    float outline = smoothstep(sdf_params.y - smoothing, sdf_params.y + smoothing, distance);
+    float glow = sdf_params.z < sdf_params.y ? smoothstep(sdf_params.z, sdf_params.y, distance) : 0.0;
+
+    vec4 result = vec4(glow_colour.rgb, glow_colour.a * glow);
+    result = mix(result, outline_colour, outline);
+    result = mix(result, colour, fill);
+
+    if (result.a <= 0.0) {
+        discard;
+    }
+
+    frag_colour = result;
+}
@@ -0,0 +1,11 @@
+#version 460 core
+
+in vec2 tex_coord;
+
+out vec4 frag_colour;
+
+uniform sampler2D diffuse_texture;
+
+void main() {
+    frag_colour = texture(diffuse_texture, tex_coord);
+}
@@ -0,0 +1,16 @@
+#version 460 core
+// Blended over the lighting pass's output. Scenes don't have lights yet, so
+// colour is drawn as it is. Only meshes split off for being transparent are
+// drawn with this, so opaque fragments of them are drawn here too.
+
+in vec3 frag_position;
+in vec2 tex_coord;
+in mat3 tbn;
+
+out vec4 frag_colour;
+
+uniform sampler2D diffuse_texture;
+
+void main() {
+    frag_colour = texture(diffuse_texture, tex_coord);
+}
@@ -0,0 +1,8 @@
+// Vertex attributes as models upload them. Each instance's transform takes up
+// four locations, one for each column.
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 normal;
+layout (location = 2) in vec2 vertex_tex_coord;
+layout (location = 3) in vec3 tangent;
+layout (location = 4) in vec3 bitangent;
+layout (location = 5) in mat4 model;
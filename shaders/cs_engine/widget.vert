@@ -0,0 +1,10 @@
+#version 460 core
+// Widgets' transforms already include the scene's orthographic projection
+#include "vertex_layout.glsl"
+
+out vec2 tex_coord;
+
+void main() {
+    tex_coord = vertex_tex_coord;
+    gl_Position = model * vec4(position, 1.0);
+}
@@ -0,0 +1,362 @@
+use std::{rc::Rc, io::Cursor};
+use image::{AnimationDecoder, ImageFormat, codecs::{gif::GifDecoder, png::PngDecoder}};
+use serde::Deserialize;
+use silver_gl::{Texture, GlImage, gl};
+use crate::{EngineError, TextureOptions, pixel_type, channel_count};
+
+// Frames shorter than this are shown for DEFAULT_FRAME_DURATION instead, the same
+// as browsers do, since many GIFs are saved with no delay
+const MIN_FRAME_DURATION: f32 = 0.02;
+const DEFAULT_FRAME_DURATION: f32 = 0.1;
+
+// Every frame is its own texture, so playing it only swaps which one is bound
+pub struct AnimatedTexture {
+    pub frames: Vec<Rc<Texture>>,
+    pub durations: Vec<f32> // Seconds each frame is shown for
+}
+
+impl AnimatedTexture {
+    // Seconds to play every frame once
+    pub fn duration(&self) -> f32 {
+        self.durations.iter().sum()
+    }
+}
+
+// Grid of equally sized frames, read left to right then top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpriteSheet {
+    pub columns: u32,
+    pub rows: u32,
+    pub frame_count: Option<u32>, // For when the last row isn't full, every cell otherwise
+    pub frame_duration: f32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopMode {
+    Once, // Stops on the last frame
+    Loop,
+    PingPong // Plays forwards then backwards, without repeating the first and last frames
+}
+
+impl SpriteSheet {
+    // Sheets are cached separately for every grid they are split with
+    pub(crate) fn cache_key(&self, path: &str, options: &TextureOptions) -> String {
+        let frame_count = self.frame_count.map_or(String::from("all"), |frame_count| frame_count.to_string());
+
+        format!("{}#sheet({}x{},frames={},duration={})", options.cache_key(path), self.columns, self.rows, frame_count, self.frame_duration)
+    }
+}
+
+// Playback state of an animated texture, see TextureWidget::update_animation()
+pub struct AnimationPlayer {
+    pub animation: Rc<AnimatedTexture>,
+    pub loop_mode: LoopMode,
+    pub speed: f32,
+    time: f32,
+    playing: bool,
+    finished: bool,
+    on_finished: Option<Box<dyn FnMut()>>
+}
+
+impl AnimationPlayer {
+    pub fn new(animation: Rc<AnimatedTexture>, loop_mode: LoopMode) -> Self {
+        Self {
+            animation,
+            loop_mode,
+            speed: 1.0,
+            time: 0.0,
+            playing: true,
+            finished: false,
+            on_finished: None
+        }
+    }
+
+    pub fn play(&mut self) {
+        if self.finished {
+            self.seek(0.0);
+        }
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) { self.playing = false }
+    pub fn is_playing(&self) -> bool { self.playing }
+
+    // Only animations played Once finish
+    pub fn is_finished(&self) -> bool { self.finished }
+
+    // Called when an animation played Once reaches its end
+    pub fn set_on_finished(&mut self, on_finished: impl FnMut() + 'static) {
+        self.on_finished = Some(Box::new(on_finished));
+    }
+
+    pub fn get_time(&self) -> f32 { self.time }
+
+    // Seconds into the animation, wrapping around for looping animations
+    pub fn seek(&mut self, time: f32) {
+        let length = self.cycle_length();
+
+        self.time = match self.loop_mode {
+            LoopMode::Once => time.clamp(0.0, length),
+            _ if length > 0.0 => time.rem_euclid(length),
+            _ => 0.0
+        };
+        self.finished = self.loop_mode == LoopMode::Once && self.time >= length;
+    }
+
+    pub fn seek_frame(&mut self, frame: usize) {
+        let time = self.animation.durations.iter().take(frame).sum();
+        self.seek(time);
+    }
+
+    // Returns whether the animation finished during this update
+    pub fn advance(&mut self, delta_time: f32) -> bool {
+        if !self.playing || self.finished {
+            return false;
+        }
+
+        let length = self.cycle_length();
+        let time = self.time + delta_time * self.speed;
+
+        if self.loop_mode == LoopMode::Once && time >= length {
+            self.time = length;
+            self.playing = false;
+            self.finished = true;
+
+            if let Some(on_finished) = &mut self.on_finished {
+                on_finished();
+            }
+
+            return true;
+        }
+
+        self.seek(time);
+        false
+    }
+
+    // Index of the frame to show at the current time
+    pub fn get_frame(&self) -> usize {
+        let mut remaining = self.time;
+
+        for frame in self.sequence() {
+            remaining -= self.animation.durations[frame];
+            if remaining < 0.0 {
+                return frame;
+            }
+        }
+
+        // Exactly at the end of an animation played once
+        self.sequence().last().unwrap_or(0)
+    }
+
+    pub fn get_texture(&self) -> Option<&Rc<Texture>> {
+        self.animation.frames.get(self.get_frame())
+    }
+
+    // Frames in the order they are played in one cycle
+    fn sequence(&self) -> impl Iterator<Item = usize> {
+        let count = self.animation.frames.len();
+        let back = match self.loop_mode {
+            LoopMode::PingPong => (1..count.saturating_sub(1)).rev(),
+            _ => (0..0).rev()
+        };
+
+        (0..count).chain(back)
+    }
+
+    fn cycle_length(&self) -> f32 {
+        self.sequence().map(|frame| self.animation.durations[frame]).sum()
+    }
+}
+
+// Every frame of an animated GIF or PNG with how long it is shown for. Frames are
+// already composited onto the previous ones, so are all the full size.
+pub(crate) fn decode_animation(path: &str, bytes: &[u8]) -> Result<Vec<(GlImage, f32)>, EngineError> {
+    let frames = match image::guess_format(bytes)? {
+        ImageFormat::Gif => GifDecoder::new(Cursor::new(bytes))?.into_frames().collect_frames()?,
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(Cursor::new(bytes))?;
+            if !decoder.is_apng() {
+                return Err(EngineError::ResourceManagerError(format!("'{}' is a PNG without any animation!", path)));
+            }
+
+            decoder.apng().into_frames().collect_frames()?
+        },
+        format => return Err(EngineError::ResourceManagerError(format!("'{}' is {:?}, only GIF and PNG can be animated!", path, format)))
+    };
+
+    Ok(
+        frames
+            .into_iter()
+            .map(|frame| {
+                let (numerator, denominator) = frame.delay().numer_denom_ms();
+                let duration = numerator as f32 / denominator.max(1) as f32 / 1000.0;
+                let duration = if duration < MIN_FRAME_DURATION { DEFAULT_FRAME_DURATION } else { duration };
+
+                let buffer = frame.into_buffer();
+                let image = GlImage {
+                    width: buffer.width() as i32,
+                    height: buffer.height() as i32,
+                    bytes: buffer.into_raw(),
+                    internal_format: gl::SRGB8_ALPHA8,
+                    data_format: gl::RGBA
+                };
+
+                (image, duration)
+            })
+            .collect()
+    )
+}
+
+pub(crate) fn split_sprite_sheet(path: &str, image: &GlImage, sheet: &SpriteSheet) -> Result<Vec<(GlImage, f32)>, EngineError> {
+    let cells = sheet.columns
+        .checked_mul(sheet.rows)
+        .ok_or_else(|| EngineError::ResourceManagerError(format!("{:?} has more cells than can be counted!", sheet)))?;
+    let frame_count = sheet.frame_count.unwrap_or(cells);
+
+    if sheet.columns == 0 || sheet.rows == 0 || frame_count == 0 || frame_count > cells {
+        return Err(EngineError::ResourceManagerError(format!("{:?} doesn't describe any frames, or more than fit in its grid!", sheet)));
+    }
+    if sheet.frame_duration <= 0.0 || !sheet.frame_duration.is_finite() {
+        return Err(EngineError::ResourceManagerError(format!("{:?} has to show its frames for a finite time longer than 0 seconds!", sheet)));
+    }
+    if image.width as u32 % sheet.columns != 0 || image.height as u32 % sheet.rows != 0 {
+        return Err(
+            EngineError::ResourceManagerError(
+                format!("'{}' is {}x{}, which can't be split into {} columns and {} rows!", path, image.width, image.height, sheet.columns, sheet.rows)
+            )
+        );
+    }
+
+    let (width, height) = (image.width as usize / sheet.columns as usize, image.height as usize / sheet.rows as usize);
+    let channel_size = match pixel_type(image) {
+        gl::UNSIGNED_SHORT => 2,
+        gl::FLOAT => 4,
+        _ => 1
+    };
+    let pixel_size = channel_count(image) * channel_size;
+    let row_size = image.width as usize * pixel_size;
+
+    Ok(
+        (0..frame_count as usize)
+            .map(|frame| {
+                let (column, row) = (frame % sheet.columns as usize, frame / sheet.columns as usize);
+                let mut bytes = Vec::with_capacity(width * height * pixel_size);
+
+                for y in row * height..(row + 1) * height {
+                    let start = y * row_size + column * width * pixel_size;
+                    bytes.extend_from_slice(&image.bytes[start..start + width * pixel_size]);
+                }
+
+                let frame = GlImage {
+                    bytes,
+                    internal_format: image.internal_format,
+                    data_format: image.data_format,
+                    width: width as i32,
+                    height: height as i32
+                };
+
+                (frame, sheet.frame_duration)
+            })
+            .collect()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+    use crate::{CSEngine, CSEngineConfig, GraphicsLibrary};
+    use super::*;
+
+    fn blank_image(width: i32, height: i32) -> GlImage {
+        GlImage { bytes: vec![0; (width * height * 4) as usize], internal_format: gl::RGBA8, data_format: gl::RGBA, width, height }
+    }
+
+    // Frames are shown for 1, 2, 3 and 4 seconds in turn
+    fn player(engine: &mut CSEngine, loop_mode: LoopMode) -> AnimationPlayer {
+        let frames = (0..4)
+            .map(|frame| engine.resource_manager.upload_texture_2d(&format!("frame{}.png", frame), blank_image(1, 1), TextureOptions::default()).unwrap())
+            .collect();
+        let animation = AnimatedTexture { frames, durations: vec![1.0, 2.0, 3.0, 4.0] };
+
+        AnimationPlayer::new(Rc::new(animation), loop_mode)
+    }
+
+    fn headless_engine() -> CSEngine {
+        CSEngine::new(CSEngineConfig {
+            gl: GraphicsLibrary::None,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn seek_clamps_or_wraps() {
+        let mut engine = headless_engine();
+
+        let mut once = player(&mut engine, LoopMode::Once);
+        once.seek(12.0);
+        assert_eq!(once.get_time(), 10.0);
+        assert!(once.is_finished());
+        assert_eq!(once.get_frame(), 3);
+
+        let mut looped = player(&mut engine, LoopMode::Loop);
+        looped.seek(11.5);
+        assert_eq!(looped.get_time(), 1.5);
+        assert_eq!(looped.get_frame(), 1);
+        looped.seek_frame(2);
+        assert_eq!(looped.get_time(), 3.0);
+        assert_eq!(looped.get_frame(), 2);
+    }
+
+    #[test]
+    fn ping_pong_plays_back_without_repeating_ends() {
+        let mut engine = headless_engine();
+        let mut ping_pong = player(&mut engine, LoopMode::PingPong);
+
+        // 0, 1, 2, 3, 2, 1 then back to 0, with the cycle taking 15 seconds
+        let mut frames = Vec::new();
+        for time in [0.5, 1.5, 3.5, 6.5, 10.5, 13.5, 15.5] {
+            ping_pong.seek(time);
+            frames.push(ping_pong.get_frame());
+        }
+        assert_eq!(frames, vec![0, 1, 2, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn on_finished_is_called_once() {
+        let mut engine = headless_engine();
+        let mut once = player(&mut engine, LoopMode::Once);
+
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        once.set_on_finished(move || counter.set(counter.get() + 1));
+
+        assert!(!once.advance(9.0));
+        assert!(once.advance(2.0));
+        assert!(!once.advance(2.0));
+        assert_eq!(calls.get(), 1);
+        assert!(once.is_finished());
+        assert!(!once.is_playing());
+
+        once.play();
+        assert_eq!(once.get_time(), 0.0);
+        assert!(!once.is_finished());
+    }
+
+    #[test]
+    fn sprite_sheets_are_checked() {
+        let image = blank_image(4, 2);
+        let sheet = SpriteSheet { columns: 2, rows: 2, frame_count: Some(3), frame_duration: 0.1 };
+        assert_eq!(split_sprite_sheet("sheet.png", &image, &sheet).unwrap().len(), 3);
+
+        for sheet in [
+            SpriteSheet { columns: u32::MAX, rows: 2, frame_count: None, frame_duration: 0.1 },
+            SpriteSheet { columns: 2, rows: 2, frame_count: Some(5), frame_duration: 0.1 },
+            SpriteSheet { columns: 2, rows: 2, frame_count: None, frame_duration: 0.0 },
+            SpriteSheet { columns: 2, rows: 2, frame_count: None, frame_duration: f32::NAN },
+            SpriteSheet { columns: 3, rows: 2, frame_count: None, frame_duration: 0.1 }
+        ] {
+            assert!(matches!(split_sprite_sheet("sheet.png", &image, &sheet), Err(EngineError::ResourceManagerError(_))));
+        }
+    }
+}
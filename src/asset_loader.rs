@@ -0,0 +1,346 @@
+use std::{rc::Rc, cell::RefCell, collections::{HashMap, HashSet, hash_map::Entry}, sync::{Arc, atomic::{AtomicBool, Ordering}, mpsc::{self, Sender, Receiver}}, thread::{self, JoinHandle}};
+use silver_gl::{GlImage, Texture};
+use crate::{ResourceManager, EngineError, Model, ObjData, RasterizedGlyph, GlyphMetaDeta, FontRenderMode, Vfs, join_path, TextureOptions};
+
+pub enum LoadState<T> {
+    Loading,
+    Ready(Rc<T>),
+    Failed(String)
+}
+
+// Asset that is being loaded in the background, which is filled in by
+// ResourceManager::update_loading() once it is uploaded to the GPU
+pub struct Pending<T> {
+    state: Rc<RefCell<LoadState<T>>>
+}
+
+impl<T> Pending<T> {
+    pub(crate) fn loading() -> Self {
+        Self { state: Rc::new(RefCell::new(LoadState::Loading)) }
+    }
+
+    pub(crate) fn ready(asset: Rc<T>) -> Self {
+        Self { state: Rc::new(RefCell::new(LoadState::Ready(asset))) }
+    }
+
+    pub(crate) fn set_state(&self, state: LoadState<T>) {
+        *self.state.borrow_mut() = state;
+    }
+
+    pub fn is_loading(&self) -> bool { matches!(*self.state.borrow(), LoadState::Loading) }
+
+    pub fn get(&self) -> Option<Rc<T>> {
+        match &*self.state.borrow() {
+            LoadState::Ready(asset) => Some(Rc::clone(asset)),
+            _ => None
+        }
+    }
+
+    pub fn get_error(&self) -> Option<String> {
+        match &*self.state.borrow() {
+            LoadState::Failed(err) => Some(err.clone()),
+            _ => None
+        }
+    }
+}
+
+impl<T> Clone for Pending<T> {
+    fn clone(&self) -> Self {
+        Self { state: Rc::clone(&self.state) }
+    }
+}
+
+// Work for the loader thread, anything that doesn't need the GL context
+pub(crate) enum AssetRequest {
+    Texture2D(String),
+    TextureCubemap(String),
+    Model(String),
+    Gltf(String),
+    Glyphs {
+        font_family: String,
+        path: String,
+        font_sizes: Vec<u32>,
+        chars: Vec<char>,
+        content_scale: f32,
+        mode: FontRenderMode
+    }
+}
+
+pub(crate) enum AssetData {
+    Image(GlImage),
+    Obj(ObjData, HashMap<String, GlImage>), // Textures by path
+    Gltf(gltf::Document, Vec<gltf::buffer::Data>, Vec<gltf::image::Data>),
+    Glyphs(Vec<(GlyphMetaDeta, RasterizedGlyph)>)
+}
+
+// Where the result of a job goes once it is uploaded
+pub(crate) enum PendingSlot {
+    Texture2D(String, TextureOptions, Pending<Texture>),
+    TextureCubemap(String, TextureOptions, Pending<Texture>),
+    Model(String, Pending<Model>),
+    Glyphs { content_scale: f32 }
+}
+
+pub(crate) struct PendingJob {
+    pub group: String,
+    pub description: String,
+    pub slot: PendingSlot
+}
+
+struct LoadJob {
+    id: u64,
+    request: AssetRequest,
+    vfs: Vfs // The mounts when the job was sent
+}
+
+pub(crate) struct LoadResult {
+    pub id: u64,
+    // Errors are sent as strings since not every error type can be sent between threads
+    pub data: Result<AssetData, String>
+}
+
+// Dedicated loading thread that reads and decodes assets in the order they were
+// sent, leaving only the upload to the GPU for the GL thread
+pub(crate) struct AssetLoader {
+    sender: Option<Sender<LoadJob>>,
+    receiver: Receiver<LoadResult>,
+    thread: Option<JoinHandle<()>>,
+    cancelled: Arc<AtomicBool>, // Set when dropped, so queued jobs are skipped
+    next_id: u64
+}
+
+impl AssetLoader {
+    pub fn new() -> Result<Self, EngineError> {
+        let (job_sender, job_receiver) = mpsc::channel();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let thread_cancelled = Arc::clone(&cancelled);
+        let thread = thread::Builder::new()
+            .name(String::from("asset_loader"))
+            .spawn(move || load_assets(job_receiver, result_sender, thread_cancelled))?;
+
+        Ok(
+            Self {
+                sender: Some(job_sender),
+                receiver: result_receiver,
+                thread: Some(thread),
+                cancelled,
+                next_id: 0
+            }
+        )
+    }
+
+    pub fn send(&mut self, request: AssetRequest, vfs: Vfs) -> Result<u64, EngineError> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.sender
+            .as_ref()
+            .and_then(|sender| sender.send(LoadJob { id, request, vfs }).ok())
+            .ok_or(EngineError::ResourceManagerError(String::from("The asset loading thread has stopped!")))?;
+
+        Ok(id)
+    }
+
+    pub fn try_recv(&self) -> Option<LoadResult> {
+        self.receiver.try_recv().ok()
+    }
+
+    // Blocks until the next job is done, None if the thread has stopped
+    pub fn recv(&self) -> Option<LoadResult> {
+        self.receiver.recv().ok()
+    }
+}
+
+// Lets the thread finish its current job before the engine exits, any jobs still
+// queued behind it are skipped
+impl Drop for AssetLoader {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.sender = None;
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+fn load_assets(jobs: Receiver<LoadJob>, results: Sender<LoadResult>, cancelled: Arc<AtomicBool>) {
+    // Faces can't be shared between threads, so the thread keeps its own
+    let library = freetype::Library::init();
+    let mut faces: HashMap<String, freetype::Face> = HashMap::new();
+
+    for job in jobs {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let vfs = &job.vfs;
+        let data = match job.request {
+            AssetRequest::Texture2D(path) | AssetRequest::TextureCubemap(path) => {
+                ResourceManager::load_image(vfs, &path).map(AssetData::Image).map_err(|err| err.to_string())
+            },
+            AssetRequest::Model(path) => load_obj(vfs, &path).map_err(|err| err.to_string()),
+            AssetRequest::Gltf(path) => {
+                ResourceManager::import_gltf(vfs, &path)
+                    .map(|(document, buffers, images)| AssetData::Gltf(document, buffers, images))
+                    .map_err(|err| err.to_string())
+            },
+            AssetRequest::Glyphs { font_family, path, font_sizes, chars, content_scale, mode } => {
+                let face = match faces.entry(path) {
+                    Entry::Occupied(entry) => Ok(&*entry.into_mut()),
+                    Entry::Vacant(entry) => library
+                        .as_ref()
+                        .map_err(|err| err.to_string())
+                        .and_then(|library| {
+                            vfs.read(entry.key())
+                                .and_then(|bytes| Ok(library.new_memory_face(bytes, 0)?))
+                                .map_err(|err| err.to_string())
+                        })
+                        .map(|face| &*entry.insert(face))
+                };
+
+                face.and_then(|face| {
+                    rasterize_glyphs(face, &font_family, &font_sizes, &chars, content_scale, mode).map_err(|err| err.to_string())
+                })
+            },
+        };
+
+        // The resource manager was dropped, so nothing is waiting for results
+        if results.send(LoadResult { id: job.id, data }).is_err() {
+            break;
+        }
+    }
+}
+
+fn load_obj(vfs: &Vfs, path: &str) -> Result<AssetData, EngineError> {
+    let data = ResourceManager::read_obj(vfs, path)?;
+
+    let mut texture_paths: HashSet<String> = HashSet::new();
+    for material in data.meshes.iter().filter_map(|mesh| mesh.material.as_ref()) {
+        for name in [
+            &material.diffuse_texture,
+            &material.specular_texture,
+            &material.normal_texture,
+            &material.shininess_texture
+        ] {
+            if !name.is_empty() {
+                texture_paths.insert(join_path(&data.directory, name));
+            }
+        }
+    }
+
+    let mut images = HashMap::new();
+    for path in texture_paths {
+        let image = ResourceManager::load_image(vfs, &path)?;
+        images.insert(path, image);
+    }
+
+    Ok(AssetData::Obj(data, images))
+}
+
+fn rasterize_glyphs(
+    face: &freetype::Face,
+    font_family: &str,
+    font_sizes: &[u32],
+    chars: &[char],
+    content_scale: f32,
+    mode: FontRenderMode
+) -> Result<AssetData, EngineError> {
+    let mut glyphs = Vec::new();
+
+    for font_size in font_sizes {
+        face.set_pixel_sizes(0, (*font_size as f32 * content_scale).round() as u32)?;
+
+        // Characters missing from the face are left out, like preload_font() does
+        for glyph in chars.iter().filter(|glyph| face.get_char_index(**glyph as usize) != 0) {
+            let metadata = GlyphMetaDeta {
+                font_family: font_family.to_owned(),
+                font_size: *font_size,
+                glyph: *glyph
+            };
+
+            glyphs.push((metadata, ResourceManager::rasterize_glyph(face, *glyph, mode)?));
+        }
+    }
+
+    Ok(AssetData::Glyphs(glyphs))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+    use crate::{CSEngine, CSEngineConfig, GraphicsLibrary};
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cs_engine_loader_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn pending_shares_state_between_clones() {
+        let pending: Pending<u32> = Pending::loading();
+        let clone = pending.clone();
+        assert!(clone.is_loading());
+        assert!(clone.get().is_none());
+
+        pending.set_state(LoadState::Ready(Rc::new(7)));
+        assert!(!clone.is_loading());
+        assert_eq!(clone.get().as_deref(), Some(&7));
+
+        pending.set_state(LoadState::Failed(String::from("missing")));
+        assert!(clone.get().is_none());
+        assert_eq!(clone.get_error().as_deref(), Some("missing"));
+    }
+
+    #[test]
+    fn groups_finish_in_queued_order() {
+        let dir = temp_dir("groups");
+        image::RgbaImage::new(2, 2).save(dir.join("a.png")).unwrap();
+        image::RgbaImage::new(2, 2).save(dir.join("b.png")).unwrap();
+
+        let mut engine = CSEngine::new(CSEngineConfig {
+            gl: GraphicsLibrary::None,
+            ..Default::default()
+        });
+        let resource_manager = &mut engine.resource_manager;
+        resource_manager.get_vfs_mut().mount_dir(&dir);
+
+        let a = resource_manager.queue_texture_2d("one", "a.png", TextureOptions::default()).unwrap();
+        let missing = resource_manager.queue_texture_2d("one", "missing.png", TextureOptions::default()).unwrap();
+        let b = resource_manager.queue_texture_2d("two", "b.png", TextureOptions::default()).unwrap();
+        assert!(a.is_loading());
+
+        // Only the first group's jobs are finished, so the second's is next
+        assert!(matches!(resource_manager.wait_for_group("one"), Err(EngineError::ResourceManagerError(_))));
+        assert!(a.get().is_some());
+        assert!(missing.get_error().is_some());
+        assert!(b.is_loading());
+        assert!(!resource_manager.is_group_loaded("two"));
+        assert_eq!(resource_manager.get_loading_progress(), (2, 3));
+        assert_eq!(resource_manager.get_loading_feedback(), "Loading b.png");
+
+        resource_manager.wait_for_group("two").unwrap();
+        assert!(b.get().is_some());
+        assert_eq!(resource_manager.get_loading_progress(), (3, 3));
+        assert_eq!(resource_manager.get_loading_feedback(), "Finished loading");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn cancelled_jobs_are_skipped() {
+        let (job_sender, job_receiver) = mpsc::channel();
+        let (result_sender, result_receiver) = mpsc::channel();
+
+        job_sender.send(LoadJob { id: 0, request: AssetRequest::Texture2D(String::from("a.png")), vfs: Vfs::new() }).unwrap();
+        drop(job_sender);
+
+        load_assets(job_receiver, result_sender, Arc::new(AtomicBool::new(true)));
+        assert!(result_receiver.try_recv().is_err());
+    }
+}
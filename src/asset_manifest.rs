@@ -0,0 +1,507 @@
+use std::collections::BTreeMap;
+use serde::Deserialize;
+use crate::{EngineError, ResourceManager, Vfs, TextureOptions, TextureFilter, TextureWrap, SpriteSheet, ShaderPathBundle, FontRenderMode, CharacterSet, ShaderPreprocessor, decode_animation, MAX_SDF_SPREAD};
+
+// Every asset a game uses, under the logical names game code loads them by, see
+// ResourceManager::load_manifest(). Each type of asset has its own table:
+//
+//     [textures.kamui_portrait]
+//     path = "textures/kamui.png"
+//     group = "chapter_1"
+//     options = { preset = "pixel_art" }
+//
+//     [models.classroom]
+//     path = "models/classroom.glb"
+//     group = "chapter_1"
+//
+//     [fonts.dialogue]
+//     path = "fonts/NotoSans-Regular.ttf"
+//     sdf_spread = 8
+//     sizes = [24, 32]
+//     characters = "latin1"
+//
+//     [shaders.crt]
+//     vertex = "cs_engine/widget.vert"
+//     fragment = "shaders/crt.frag"
+//     defines = { CURVATURE = "0.25" }
+//
+// Paths are read through the vfs the same as paths in code. Assets without a
+// group are only loaded when they are first used.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AssetManifest {
+    pub textures: BTreeMap<String, TextureEntry>,
+    pub animations: BTreeMap<String, AnimationEntry>,
+    pub models: BTreeMap<String, ModelEntry>,
+    pub fonts: BTreeMap<String, FontEntry>,
+    pub shaders: BTreeMap<String, ShaderEntry>
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextureEntry {
+    pub path: String,
+    pub group: Option<String>,
+    #[serde(default)]
+    pub cubemap: bool, // Single image in the layout load_texture_cubemap() takes
+    #[serde(default)]
+    pub options: TextureOptionsEntry
+}
+
+// GIFs and APNGs, or a sprite sheet when its grid is given
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnimationEntry {
+    pub path: String,
+    pub group: Option<String>,
+    pub sprite_sheet: Option<SpriteSheet>,
+    #[serde(default)]
+    pub options: TextureOptionsEntry
+}
+
+// OBJ, or glTF for .gltf and .glb files
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelEntry {
+    pub path: String,
+    pub group: Option<String>
+}
+
+// Glyphs for the sizes listed are loaded along with the font's group
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FontEntry {
+    pub path: String,
+    pub group: Option<String>,
+    pub sdf_spread: Option<u32>, // Renders the font as signed distance fields, bitmaps otherwise
+    #[serde(default)]
+    pub sizes: Vec<u32>,
+    pub characters: Option<CharacterSet> // Ascii if not given
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShaderEntry {
+    pub vertex: Option<String>,
+    pub geometry: Option<String>,
+    pub fragment: Option<String>,
+    #[serde(default)]
+    pub defines: BTreeMap<String, String>,
+    pub group: Option<String>
+}
+
+// Starts from a preset, with any option given replacing the preset's
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TextureOptionsEntry {
+    pub preset: TexturePreset,
+    pub filter: Option<TextureFilter>,
+    pub mipmaps: Option<bool>,
+    pub wrap: Option<TextureWrap>,
+    pub anisotropy: Option<u32>,
+    pub srgb: Option<bool>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TexturePreset {
+    #[default]
+    Default,
+    PixelArt,
+    Linear
+}
+
+impl TextureOptionsEntry {
+    pub fn to_options(&self) -> TextureOptions {
+        let preset = match self.preset {
+            TexturePreset::Default => TextureOptions::default(),
+            TexturePreset::PixelArt => TextureOptions::pixel_art(),
+            TexturePreset::Linear => TextureOptions::linear()
+        };
+
+        TextureOptions {
+            filter: self.filter.unwrap_or(preset.filter),
+            mipmaps: self.mipmaps.unwrap_or(preset.mipmaps),
+            wrap: self.wrap.unwrap_or(preset.wrap),
+            anisotropy: self.anisotropy.unwrap_or(preset.anisotropy),
+            srgb: self.srgb.unwrap_or(preset.srgb)
+        }
+    }
+}
+
+impl ModelEntry {
+    pub fn is_gltf(&self) -> bool {
+        let path = self.path.to_lowercase();
+        path.ends_with(".gltf") || path.ends_with(".glb")
+    }
+}
+
+impl FontEntry {
+    pub fn render_mode(&self) -> FontRenderMode {
+        match self.sdf_spread {
+            Some(spread) => FontRenderMode::Sdf { spread },
+            None => FontRenderMode::Bitmap
+        }
+    }
+
+    pub fn get_characters(&self) -> CharacterSet {
+        self.characters.clone().unwrap_or(CharacterSet::Ascii)
+    }
+}
+
+impl ShaderEntry {
+    // Defines are already sorted by name, the same as ShaderPathBundle::define() keeps them
+    pub fn to_paths(&self) -> ShaderPathBundle {
+        ShaderPathBundle {
+            vertex: self.vertex.clone(),
+            geometry: self.geometry.clone(),
+            fragment: self.fragment.clone(),
+            defines: self.defines.iter().map(|(name, value)| (name.clone(), value.clone())).collect()
+        }
+    }
+}
+
+trait Grouped {
+    fn get_group(&self) -> Option<&str>;
+}
+
+impl Grouped for TextureEntry { fn get_group(&self) -> Option<&str> { self.group.as_deref() } }
+impl Grouped for AnimationEntry { fn get_group(&self) -> Option<&str> { self.group.as_deref() } }
+impl Grouped for ModelEntry { fn get_group(&self) -> Option<&str> { self.group.as_deref() } }
+impl Grouped for FontEntry { fn get_group(&self) -> Option<&str> { self.group.as_deref() } }
+impl Grouped for ShaderEntry { fn get_group(&self) -> Option<&str> { self.group.as_deref() } }
+
+fn in_group<T: Grouped + Clone>(entries: &BTreeMap<String, T>, group: &str) -> BTreeMap<String, T> {
+    entries
+        .iter()
+        .filter(|(_, entry)| entry.get_group() == Some(group))
+        .map(|(name, entry)| (name.clone(), entry.clone()))
+        .collect()
+}
+
+fn get_entry<'a, T>(entries: &'a BTreeMap<String, T>, kind: &str, name: &str) -> Result<&'a T, EngineError> {
+    entries
+        .get(name)
+        .ok_or_else(|| EngineError::ManifestError(format!("There is no {} named '{}'!", kind, name)))
+}
+
+impl AssetManifest {
+    pub fn parse(path: &str, source: &str) -> Result<Self, EngineError> {
+        toml::from_str(source).map_err(|err| EngineError::ManifestError(format!("'{}' is malformed: {}", path, err)))
+    }
+
+    // Entries of the other manifest replace any with the same name
+    pub fn merge(&mut self, other: AssetManifest) {
+        self.textures.extend(other.textures);
+        self.animations.extend(other.animations);
+        self.models.extend(other.models);
+        self.fonts.extend(other.fonts);
+        self.shaders.extend(other.shaders);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty() && self.animations.is_empty() && self.models.is_empty() && self.fonts.is_empty() && self.shaders.is_empty()
+    }
+
+    // Only the entries in the group
+    pub fn get_group(&self, group: &str) -> AssetManifest {
+        AssetManifest {
+            textures: in_group(&self.textures, group),
+            animations: in_group(&self.animations, group),
+            models: in_group(&self.models, group),
+            fonts: in_group(&self.fonts, group),
+            shaders: in_group(&self.shaders, group)
+        }
+    }
+
+    pub fn get_texture(&self, name: &str) -> Result<&TextureEntry, EngineError> { get_entry(&self.textures, "texture", name) }
+    pub fn get_animation(&self, name: &str) -> Result<&AnimationEntry, EngineError> { get_entry(&self.animations, "animation", name) }
+    pub fn get_model(&self, name: &str) -> Result<&ModelEntry, EngineError> { get_entry(&self.models, "model", name) }
+    pub fn get_font(&self, name: &str) -> Result<&FontEntry, EngineError> { get_entry(&self.fonts, "font", name) }
+    pub fn get_shader(&self, name: &str) -> Result<&ShaderEntry, EngineError> { get_entry(&self.shaders, "shader", name) }
+
+    // Reads every file without touching the GPU, so it can run before the window
+    // is open. Only the size of images is read, while models, animations, fonts
+    // and shaders are parsed in full, along with everything they reference.
+    pub(crate) fn validate(&self, vfs: &Vfs, face_library: &freetype::Library) -> Vec<EngineError> {
+        let mut errors = Vec::new();
+        // The entry's own checks are already manifest errors, so only add which entry
+        let mut check = |kind: &str, name: &str, result: Result<(), EngineError>| {
+            let message = match result {
+                Ok(()) => return,
+                Err(EngineError::ManifestError(message)) => message,
+                Err(err) => err.to_string()
+            };
+            errors.push(EngineError::ManifestError(format!("{} '{}': {}", kind, name, message)));
+        };
+
+        for (name, entry) in &self.textures {
+            check("Texture", name, validate_texture(vfs, entry));
+        }
+        for (name, entry) in &self.animations {
+            check("Animation", name, validate_animation(vfs, entry));
+        }
+        for (name, entry) in &self.models {
+            let result = if entry.is_gltf() {
+                ResourceManager::import_gltf(vfs, &entry.path).map(|_| ())
+            } else {
+                ResourceManager::read_obj(vfs, &entry.path).map(|_| ())
+            };
+            check("Model", name, result);
+        }
+        for (name, entry) in &self.fonts {
+            check("Font", name, validate_font(vfs, face_library, entry));
+        }
+        for (name, entry) in &self.shaders {
+            check("Shader", name, validate_shader(vfs, entry));
+        }
+
+        errors
+    }
+}
+
+fn image_size(vfs: &Vfs, path: &str) -> Result<(u32, u32), EngineError> {
+    Ok(image::io::Reader::new(std::io::Cursor::new(vfs.read(path)?)).with_guessed_format()?.into_dimensions()?)
+}
+
+fn validate_options(options: &TextureOptionsEntry) -> Result<(), EngineError> {
+    if options.anisotropy == Some(0) {
+        return Err(EngineError::ManifestError(String::from("Anisotropy has to be at least 1!")));
+    }
+
+    Ok(())
+}
+
+fn validate_texture(vfs: &Vfs, entry: &TextureEntry) -> Result<(), EngineError> {
+    validate_options(&entry.options)?;
+    let (width, height) = image_size(vfs, &entry.path)?;
+
+    // Cubemaps are a horizontal cross of square faces
+    if entry.cubemap && width * 3 != height * 4 {
+        return Err(
+            EngineError::ManifestError(
+                format!("'{}' is {}x{}, but cubemaps have to be a horizontal cross 4 faces wide and 3 high!", entry.path, width, height)
+            )
+        );
+    }
+
+    Ok(())
+}
+
+fn validate_animation(vfs: &Vfs, entry: &AnimationEntry) -> Result<(), EngineError> {
+    validate_options(&entry.options)?;
+
+    let sheet = match entry.sprite_sheet {
+        Some(sheet) => sheet,
+        None => return decode_animation(&entry.path, &vfs.read(&entry.path)?).map(|_| ())
+    };
+
+    let cells = sheet.columns
+        .checked_mul(sheet.rows)
+        .ok_or_else(|| EngineError::ManifestError(format!("{:?} has more cells than can be counted!", sheet)))?;
+    if sheet.columns == 0 || sheet.rows == 0 || sheet.frame_count.is_some_and(|count| count == 0 || count > cells) {
+        return Err(EngineError::ManifestError(format!("{:?} doesn't describe any frames, or more than fit in its grid!", sheet)));
+    }
+    if sheet.frame_duration <= 0.0 || !sheet.frame_duration.is_finite() {
+        return Err(EngineError::ManifestError(String::from("Sprite sheet frames have to be shown for a finite time longer than 0 seconds!")));
+    }
+
+    let (width, height) = image_size(vfs, &entry.path)?;
+    if width % sheet.columns != 0 || height % sheet.rows != 0 {
+        return Err(
+            EngineError::ManifestError(
+                format!("'{}' is {}x{}, which can't be split into {} columns and {} rows!", entry.path, width, height, sheet.columns, sheet.rows)
+            )
+        );
+    }
+
+    Ok(())
+}
+
+fn validate_font(vfs: &Vfs, face_library: &freetype::Library, entry: &FontEntry) -> Result<(), EngineError> {
+    if entry.sdf_spread.is_some_and(|spread| spread == 0 || spread > MAX_SDF_SPREAD) {
+        return Err(EngineError::ManifestError(format!("The SDF spread has to be between 1 and {} pixels!", MAX_SDF_SPREAD)));
+    }
+    if entry.sizes.contains(&0) {
+        return Err(EngineError::ManifestError(String::from("Font sizes have to be at least 1!")));
+    }
+
+    let face = face_library.new_memory_face(vfs.read(&entry.path)?, 0)?;
+    if face.family_name().is_none() {
+        return Err(EngineError::ManifestError(format!("'{}' does not have a family name!", entry.path)));
+    }
+
+    Ok(())
+}
+
+fn validate_shader(vfs: &Vfs, entry: &ShaderEntry) -> Result<(), EngineError> {
+    let paths = entry.to_paths();
+
+    if paths.vertex.is_none() || paths.fragment.is_none() {
+        return Err(EngineError::ManifestError(String::from("Shaders need both a vertex and a fragment stage!")));
+    }
+
+    // Resolves every include, which is all that can be checked without compiling
+    for stage in [&paths.vertex, &paths.geometry, &paths.fragment].into_iter().flatten() {
+        ShaderPreprocessor::new(vfs, &paths.defines).process(stage)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+    use crate::DirectoryMount;
+    use super::*;
+
+    // A fresh directory under the system's temp directory for each test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cs_engine_manifest_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn validate(name: &str, source: &str, images: &[(&str, u32, u32)]) -> Vec<String> {
+        let dir = temp_dir(name);
+        for (path, width, height) in images {
+            image::RgbImage::new(*width, *height).save(dir.join(path)).unwrap();
+        }
+
+        let mut vfs = Vfs::new();
+        vfs.mount(std::sync::Arc::new(DirectoryMount::new(&dir)));
+        let face_library = freetype::Library::init().unwrap();
+
+        let errors = AssetManifest::parse("test.toml", source).unwrap().validate(&vfs, &face_library);
+        fs::remove_dir_all(&dir).ok();
+
+        errors.iter().map(|err| err.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_reads_every_table() {
+        let manifest = AssetManifest::parse(
+            "test.toml",
+            r#"
+                [textures.portrait]
+                path = "textures/portrait.png"
+                group = "chapter_1"
+                options = { preset = "pixel_art", srgb = false }
+
+                [animations.flame]
+                path = "textures/flame.png"
+                sprite_sheet = { columns = 4, rows = 2, frame_duration = 0.1 }
+
+                [models.classroom]
+                path = "models/classroom.glb"
+
+                [fonts.dialogue]
+                path = "fonts/dialogue.ttf"
+                sdf_spread = 8
+                sizes = [24, 32]
+
+                [shaders.crt]
+                vertex = "cs_engine/widget.vert"
+                fragment = "shaders/crt.frag"
+                defines = { CURVATURE = "0.25" }
+            "#
+        ).unwrap();
+
+        assert_eq!(manifest.get_texture("portrait").unwrap().group.as_deref(), Some("chapter_1"));
+        assert_eq!(manifest.get_animation("flame").unwrap().sprite_sheet.unwrap().columns, 4);
+        assert!(manifest.get_model("classroom").unwrap().is_gltf());
+        assert_eq!(manifest.get_font("dialogue").unwrap().render_mode(), FontRenderMode::Sdf { spread: 8 });
+        assert_eq!(manifest.get_shader("crt").unwrap().to_paths().defines, vec![(String::from("CURVATURE"), String::from("0.25"))]);
+        assert_eq!(manifest.get_group("chapter_1").textures.len(), 1);
+        assert!(manifest.get_model("missing").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_malformed_manifests() {
+        assert!(matches!(AssetManifest::parse("test.toml", "[textures.portrait]"), Err(EngineError::ManifestError(_))));
+        assert!(matches!(AssetManifest::parse("test.toml", "[sounds.bell]\npath = \"bell.ogg\""), Err(EngineError::ManifestError(_))));
+        assert!(
+            matches!(
+                AssetManifest::parse("test.toml", "[textures.portrait]\npath = \"portrait.png\"\nsize = 2"),
+                Err(EngineError::ManifestError(_))
+            )
+        );
+    }
+
+    #[test]
+    fn to_options_overrides_preset() {
+        let entry = TextureOptionsEntry { preset: TexturePreset::PixelArt, mipmaps: Some(true), ..Default::default() };
+
+        assert_eq!(entry.to_options(), TextureOptions { mipmaps: true, ..TextureOptions::pixel_art() });
+        assert_eq!(TextureOptionsEntry::default().to_options(), TextureOptions::default());
+        assert_eq!(TextureOptionsEntry { preset: TexturePreset::Linear, ..Default::default() }.to_options(), TextureOptions::linear());
+    }
+
+    #[test]
+    fn validate_accepts_valid_entries() {
+        let errors = validate(
+            "valid",
+            r#"
+                [textures.sky]
+                path = "sky.png"
+                cubemap = true
+
+                [animations.flame]
+                path = "flame.png"
+                sprite_sheet = { columns = 4, rows = 2, frame_count = 7, frame_duration = 0.1 }
+            "#,
+            &[("sky.png", 8, 6), ("flame.png", 8, 4)]
+        );
+
+        assert_eq!(errors, Vec::<String>::new());
+    }
+
+    #[test]
+    fn validate_reports_each_entry_once() {
+        let errors = validate(
+            "invalid",
+            r#"
+                [textures.blurry]
+                path = "blurry.png"
+                options = { anisotropy = 0 }
+
+                [textures.missing]
+                path = "missing.png"
+
+                [textures.sky]
+                path = "blurry.png"
+                cubemap = true
+
+                [animations.flame]
+                path = "blurry.png"
+                sprite_sheet = { columns = 3, rows = 1, frame_duration = 0.1 }
+
+                [fonts.dialogue]
+                path = "dialogue.ttf"
+                sdf_spread = 0
+
+                [shaders.crt]
+                vertex = "cs_engine/widget.vert"
+            "#,
+            &[("blurry.png", 4, 4)]
+        );
+
+        assert_eq!(errors.len(), 6);
+        assert_eq!(errors[0], "Asset manifest had an error: Texture 'blurry': Anisotropy has to be at least 1!");
+        assert!(errors[1].starts_with("Asset manifest had an error: Texture 'missing': Virtual file system had an error:"));
+        assert_eq!(
+            errors[2],
+            "Asset manifest had an error: Texture 'sky': 'blurry.png' is 4x4, but cubemaps have to be a horizontal cross 4 faces wide and 3 high!"
+        );
+        assert_eq!(
+            errors[3],
+            "Asset manifest had an error: Animation 'flame': 'blurry.png' is 4x4, which can't be split into 3 columns and 1 rows!"
+        );
+        assert_eq!(
+            errors[4],
+            format!("Asset manifest had an error: Font 'dialogue': The SDF spread has to be between 1 and {} pixels!", MAX_SDF_SPREAD)
+        );
+        assert_eq!(errors[5], "Asset manifest had an error: Shader 'crt': Shaders need both a vertex and a fragment stage!");
+    }
+}
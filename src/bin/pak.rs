@@ -0,0 +1,33 @@
+// Builds a pak file from a directory of assets, which can then be mounted with
+// Vfs::mount_pak()
+//   cargo run --bin pak -- <asset directory> <output.pak>
+//   cargo run --bin pak -- --list <file.pak>
+use std::{env, process};
+use cinema_skylight_engine::{build_pak, PakMount};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        ["--list", pak] => PakMount::open(pak).map(|pak| {
+            let mut files: Vec<&String> = pak.files().collect();
+            files.sort();
+
+            for file in files {
+                println!("{}", file);
+            }
+        }),
+        [directory, output] => build_pak(directory, output).map(|count| {
+            println!("Packed {} files into {}", count, output);
+        }),
+        _ => {
+            eprintln!("Usage: pak <asset directory> <output.pak>\n       pak --list <file.pak>");
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
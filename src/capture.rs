@@ -0,0 +1,101 @@
+use std::ffi::c_void;
+use image::RgbaImage;
+use silver_gl::{Texture, gl};
+use crate::{EngineError, Scene, Compositor, is_null_gl_loaded};
+
+// Reads a texture back from the GPU into an image. GL stores rows bottom to top,
+// so the result is flipped to match image files.
+pub fn capture_texture(texture: &Texture, width: i32, height: i32) -> Result<RgbaImage, EngineError> {
+    check_capturable()?;
+
+    read_texture(texture.get_id(), width, height)
+}
+
+// Captures a scene as it is presented, so it should have been drawn beforehand.
+// Its render pipeline's output is run through the compositor's shader on its own
+// into an offscreen framebuffer at the scene's size, which is read back. For
+// View3DRenderPipeline that includes the bloom and tonemapping.
+// Use CSEngine::screenshot after drawing a frame for every layer together.
+pub fn capture_scene(compositor: &Compositor, scene: &dyn Scene) -> Result<RgbaImage, EngineError> {
+    check_capturable()?;
+    let (width, height) = scene.get_size();
+
+    let mut texture = 0;
+    let mut framebuffer = 0;
+    unsafe {
+        gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture);
+        gl::TextureStorage2D(texture, 1, gl::RGBA8, width, height);
+        gl::CreateFramebuffers(1, &mut framebuffer);
+        gl::NamedFramebufferTexture(framebuffer, gl::COLOR_ATTACHMENT0, texture, 0);
+    }
+
+    let result = compositor
+        .composite_into(scene, framebuffer, width, height)
+        .and_then(|_| read_texture(texture, width, height));
+
+    unsafe {
+        gl::DeleteFramebuffers(1, &framebuffer);
+        gl::DeleteTextures(1, &texture);
+    }
+
+    result
+}
+
+// Captures whatever is currently in the default framebuffer, meant to be called
+// after everything is drawn but before swapping buffers
+pub fn capture_framebuffer(width: i32, height: i32) -> Result<RgbaImage, EngineError> {
+    check_capturable()?;
+    let mut bytes = vec![0u8; width as usize * height as usize * 4];
+
+    unsafe {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width,
+            height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            bytes.as_mut_ptr() as *mut c_void
+        );
+    }
+
+    to_image(bytes, width, height)
+}
+
+// Headless runs draw nothing, so there would only be blank pixels to read back.
+// Use the DrawRecorder to check what would have been drawn instead.
+fn check_capturable() -> Result<(), EngineError> {
+    if is_null_gl_loaded() {
+        return Err(EngineError::CaptureError(String::from("Nothing is drawn when headless, so there is nothing to capture!")));
+    }
+
+    Ok(())
+}
+
+fn read_texture(id: u32, width: i32, height: i32) -> Result<RgbaImage, EngineError> {
+    let mut bytes = vec![0u8; width as usize * height as usize * 4];
+
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::GetTextureImage(
+            id,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            bytes.len() as i32,
+            bytes.as_mut_ptr() as *mut c_void
+        );
+    }
+
+    to_image(bytes, width, height)
+}
+
+fn to_image(bytes: Vec<u8>, width: i32, height: i32) -> Result<RgbaImage, EngineError> {
+    let mut image = RgbaImage::from_raw(width as u32, height as u32, bytes)
+        .ok_or(EngineError::CaptureError(format!("Captured pixels do not fit a {}x{} image", width, height)))?;
+    image::imageops::flip_vertical_in_place(&mut image);
+
+    Ok(image)
+}
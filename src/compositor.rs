@@ -0,0 +1,220 @@
+use std::rc::Rc;
+use silver_gl::{ShaderProgram, MultiBindModel, ModelTrait, gl};
+use crate::{Scene, ResourceManager, ShaderPathBundle, EngineError, DrawRecorder, create_wquad};
+
+// Area of the window a layer is composited onto, in pixels from the bottom left
+#[derive(Debug, Clone, Copy)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32
+}
+
+pub struct CompositorLayer {
+    pub scene: Box<dyn Scene>,
+    pub viewport: Option<Viewport>, // Covers the whole window if None
+    pub visible: bool
+}
+
+impl CompositorLayer {
+    pub fn new(scene: Box<dyn Scene>) -> Self {
+        Self {
+            scene,
+            viewport: None,
+            visible: true
+        }
+    }
+}
+
+// Holds the stack of scenes that make up a frame, drawing each scene to its own
+// render pipeline then compositing the outputs onto the default framebuffer,
+// first layer at the bottom.
+// The composite shader receives every texture from the scene's get_link() as
+// diffuse textures, so for View3DRenderPipeline that is the lighting output
+// followed by the bloom blur, which it is expected to combine, then the depth.
+// The has_bloom uniform is set when there is more than one texture.
+// CSEngine loads ShaderPathBundle::composite() to start with, which tonemaps
+// scenes with bloom. Until a shader program is loaded scenes are still drawn,
+// but not presented.
+pub struct Compositor {
+    pub layers: Vec<CompositorLayer>,
+    shader_program: Option<Rc<ShaderProgram>>,
+    quad: Option<MultiBindModel>,
+    width: i32,
+    height: i32
+}
+
+impl Compositor {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            layers: Vec::new(),
+            shader_program: None,
+            quad: None,
+            width,
+            height
+        }
+    }
+
+    pub fn load_shader_program(
+        &mut self,
+        resource_manager: &mut ResourceManager,
+        shader_paths: ShaderPathBundle
+    ) -> Result<(), EngineError> {
+        self.shader_program = Some(resource_manager.load_shader_program(shader_paths)?);
+
+        if self.quad.is_none() {
+            self.quad = Some(create_composite_quad());
+        }
+
+        Ok(())
+    }
+
+    pub fn push(&mut self, scene: Box<dyn Scene>) {
+        self.layers.push(CompositorLayer::new(scene));
+    }
+
+    pub fn push_with_viewport(&mut self, scene: Box<dyn Scene>, viewport: Viewport) -> Result<(), EngineError> {
+        let mut layer = CompositorLayer::new(scene);
+        layer.scene.set_size(viewport.width, viewport.height)?;
+        layer.viewport = Some(viewport);
+        self.layers.push(layer);
+
+        Ok(())
+    }
+
+    pub fn get_size(&self) -> (i32, i32) { (self.width, self.height) }
+
+    // Resizes every layer that covers the whole window along with it
+    pub fn set_size(&mut self, width: i32, height: i32) -> Result<(), EngineError> {
+        self.width = width;
+        self.height = height;
+
+        for layer in &mut self.layers {
+            if layer.viewport.is_none() {
+                layer.scene.set_size(width, height)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        for layer in &mut self.layers {
+            if layer.visible {
+                layer.scene.update(delta_time);
+            }
+        }
+    }
+
+    // Every layer is refreshed, so hidden layers are ready when they are shown
+    pub fn refresh_layout(&mut self, resource_manager: &mut ResourceManager) -> Result<(), EngineError> {
+        for layer in &mut self.layers {
+            layer.scene.refresh_layout(resource_manager)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn draw(&mut self) -> Result<(), EngineError> {
+        for layer in &mut self.layers {
+            if layer.visible {
+                layer.scene.draw()?;
+            }
+        }
+
+        self.composite()
+    }
+
+    fn composite(&mut self) -> Result<(), EngineError> {
+        let (shader_program, quad) = match (&self.shader_program, &mut self.quad) {
+            (Some(shader_program), Some(quad)) => (shader_program, quad),
+            _ => return Ok(())
+        };
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, self.width, self.height);
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+            // Layers are ordered by the stack, and the flipped quad would be culled
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+        }
+
+        shader_program.use_program();
+
+        for layer in &self.layers {
+            if !layer.visible {
+                continue;
+            }
+
+            let viewport = layer.viewport.unwrap_or(Viewport { x: 0, y: 0, width: self.width, height: self.height });
+            unsafe { gl::Viewport(viewport.x, viewport.y, viewport.width, viewport.height) };
+
+            composite_scene(shader_program, quad, layer.scene.as_ref())?;
+        }
+
+        unsafe { gl::Enable(gl::CULL_FACE) };
+
+        Ok(())
+    }
+
+    // Composites the scene on its own over the whole of the framebuffer, the same
+    // as it would be presented. Used for captures, so a quad is made for each call
+    // to leave the compositor's untouched.
+    pub(crate) fn composite_into(&self, scene: &dyn Scene, framebuffer: u32, width: i32, height: i32) -> Result<(), EngineError> {
+        let shader_program = self.shader_program
+            .as_ref()
+            .ok_or(EngineError::CaptureError(String::from("Compositor has no shader program loaded to composite with!")))?;
+        let mut quad = create_composite_quad();
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+            gl::Viewport(0, 0, width, height);
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+        }
+
+        shader_program.use_program();
+        let result = composite_scene(shader_program, &mut quad, scene);
+
+        unsafe {
+            gl::Enable(gl::CULL_FACE);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        result
+    }
+
+    pub fn record(&mut self, recorder: &mut DrawRecorder) {
+        for layer in &mut self.layers {
+            if layer.visible {
+                layer.scene.record(recorder);
+            }
+        }
+    }
+}
+
+fn create_composite_quad() -> MultiBindModel {
+    let mut quad = create_wquad();
+    // Flipped compared to widgets since framebuffer textures start at the bottom left
+    quad.get_transform_array_mut().set_data_mut(vec![cgmath::ortho(0.0, 1.0, 0.0, 1.0, -1.0, 1.0)]);
+
+    quad
+}
+
+// Draws the scene's output with the shader program over the current viewport
+fn composite_scene(shader_program: &ShaderProgram, quad: &mut MultiBindModel, scene: &dyn Scene) -> Result<(), EngineError> {
+    let link = scene.get_render_pipeline().get_link()?;
+    shader_program.set_bool("has_bloom", link.len() > 1)?;
+
+    quad.meshes[0].diffuse_textures = link;
+    quad.draw(shader_program)?;
+
+    Ok(())
+}
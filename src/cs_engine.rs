@@ -1,31 +1,58 @@
-use std::{sync::mpsc::Receiver, ffi::{c_void, CString}, slice};
+use std::{sync::mpsc::Receiver, ffi::{c_void, CString}, slice, time::Instant};
 use glfw::{Window, Glfw, WindowEvent, Context};
 use silver_gl::gl;
-use crate::ResourceManager;
+use image::RgbaImage;
+use crate::{ResourceManager, DrawRecorder, EngineError, capture_framebuffer, Compositor, ShaderPathBundle, load_null_gl};
 
 pub struct CSEngine {
-    pub glfw: Glfw,
-    pub events: Receiver<(f64, WindowEvent)>,
-    pub window: Window,
+    // Window handles are only present when a graphics library is selected,
+    // with GraphicsLibrary::None the engine runs headless
+    pub glfw: Option<Glfw>,
+    pub events: Option<Receiver<(f64, WindowEvent)>>,
+    pub window: Option<Window>,
     pub resource_manager: ResourceManager,
+    // Stack of scenes drawn and presented every frame of the render loop
+    pub compositor: Compositor,
+    // Scenes are recorded here instead of drawn when running headless
+    pub draw_recorder: DrawRecorder,
+    frame_time: FrameTime,
+    should_close: bool,
     config: CSEngineConfig,
 }
 
 impl CSEngine {
     pub fn new(config: CSEngineConfig) -> Self {
-        let (glfw, window, events) = Self::create_window(
-            config.width as u32,
-            config.height as u32,
-            &config.title,
-            config.capture_mouse,
-            config.gl
-        );
+        let (glfw, window, events) = match config.gl {
+            GraphicsLibrary::None => (None, None, None),
+            _ => {
+                let (glfw, window, events) = Self::create_window(
+                    config.width as u32,
+                    config.height as u32,
+                    &config.title,
+                    config.capture_mouse,
+                    config.visible,
+                    config.gl
+                );
+
+                (Some(glfw), Some(window), Some(events))
+            },
+        };
+
+        // Framebuffer can be larger than the requested size on high DPI displays
+        let (fb_width, fb_height) = match &window {
+            Some(window) => window.get_framebuffer_size(),
+            None => (config.width, config.height)
+        };
 
         let mut engine = CSEngine {
             glfw,
             events,
             window,
             resource_manager: ResourceManager::new(),
+            compositor: Compositor::new(fb_width, fb_height),
+            draw_recorder: DrawRecorder::new(),
+            frame_time: FrameTime::default(),
+            should_close: false,
             config
         };
 
@@ -40,6 +67,23 @@ impl CSEngine {
 
         engine.configure_gl();
         engine.resource_manager.gl = engine.config.gl; // Set here so RM can react to changes in GL settings
+        engine.resource_manager.headless = engine.is_headless();
+        engine.resource_manager.set_hot_reload(engine.config.hot_reload);
+        engine.compositor
+            .load_shader_program(&mut engine.resource_manager, ShaderPathBundle::composite())
+            .expect("Built-in composite shader should compile");
+
+        if let Some(window) = &engine.window {
+            engine.resource_manager.set_content_scale(window.get_content_scale().0);
+        }
+
+        if let Some(glfw) = &mut engine.glfw {
+            glfw.set_swap_interval(if engine.config.vsync {
+                glfw::SwapInterval::Sync(1)
+            } else {
+                glfw::SwapInterval::None
+            });
+        }
 
         engine
     }
@@ -49,6 +93,7 @@ impl CSEngine {
         height: u32,
         title: &str,
         capture_mouse: bool,
+        visible: bool,
         gl: GraphicsLibrary
     ) -> (Glfw, Window, Receiver<(f64, WindowEvent)>) {
         // Create window
@@ -61,6 +106,8 @@ impl CSEngine {
             },
             GraphicsLibrary::None => {},
         }
+        // Invisible windows still have a full GL context, allowing for offscreen rendering
+        glfw.window_hint(glfw::WindowHint::Visible(visible));
 
         let (mut window, events) = glfw.create_window(
             width,
@@ -75,6 +122,7 @@ impl CSEngine {
         window.set_framebuffer_size_polling(true);
         window.set_cursor_pos_polling(true);
         window.set_scroll_polling(true);
+        window.set_content_scale_polling(true);
         
         if capture_mouse {
             window.set_cursor_mode(glfw::CursorMode::Disabled);
@@ -88,7 +136,8 @@ impl CSEngine {
         match self.config.gl {
             GraphicsLibrary::OpenGL4_6(_, _) => unsafe {
                 // Create GL context
-                gl::load_with(|symbol| self.window.get_proc_address(symbol) as *const _);
+                let window = self.window.as_mut().expect("OpenGL should always have a window");
+                gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
         
                 // Depth testing
                 gl::Enable(gl::DEPTH_TEST);
@@ -113,12 +162,157 @@ impl CSEngine {
                     },
                 }
             },
-            GraphicsLibrary::None => {},
+            // GL calls made by widgets, scenes and the resource manager go to the null backend
+            GraphicsLibrary::None => load_null_gl(),
         }
     }
 
     pub fn extension_supported(&self, extension: &str) -> bool {
-        self.glfw.extension_supported(extension)
+        match &self.glfw {
+            Some(glfw) => glfw.extension_supported(extension),
+            None => false,
+        }
+    }
+
+    pub fn is_headless(&self) -> bool {
+        matches!(self.config.gl, GraphicsLibrary::None)
+    }
+
+    // Owns the render loop until the window is closed or set_should_close() is called.
+    // Each frame, window events are passed to event_handler, then update is called
+    // according to the config's UpdateMode, then the compositor's scenes are updated
+    // with the time since the last frame (e.g. for animated textures) and drawn.
+    // Window resizes and DPI changes are handled before the event handler.
+    // If you need a different loop, call poll_events() and draw_frame() yourself.
+    pub fn run<E, U>(&mut self, mut event_handler: E, mut update: U) -> Result<(), EngineError>
+    where
+        E: FnMut(&mut CSEngine, WindowEvent) -> Result<(), EngineError>,
+        U: FnMut(&mut CSEngine, FrameTime) -> Result<(), EngineError>
+    {
+        self.config.update_mode.validate()?;
+
+        let start = Instant::now();
+        let mut last_frame = start;
+        let mut accumulator = 0.0;
+
+        self.frame_time = FrameTime::default();
+
+        while !self.should_close() {
+            let now = Instant::now();
+            let delta_time = now.duration_since(last_frame).as_secs_f32();
+            last_frame = now;
+
+            self.frame_time.elapsed = now.duration_since(start).as_secs_f64();
+
+            for event in self.poll_events() {
+                match event {
+                    WindowEvent::FramebufferSize(width, height) => self.compositor.set_size(width, height)?,
+                    WindowEvent::ContentScale(x_scale, _) => self.resource_manager.set_content_scale(x_scale),
+                    _ => {}
+                }
+
+                event_handler(self, event)?;
+            }
+
+            self.resource_manager.update_loading();
+            // Errors are kept for the game to show, see take_reload_errors()
+            self.resource_manager.update_hot_reload();
+
+            let update_mode = self.config.update_mode;
+            match update_mode {
+                UpdateMode::Variable => {
+                    self.frame_time.delta_time = delta_time;
+
+                    let frame_time = self.frame_time;
+                    update(self, frame_time)?;
+                },
+                UpdateMode::Fixed(step) => {
+                    // Capped so a long frame (e.g. a loading hitch) doesn't cause a
+                    // spiral of updates trying to catch up
+                    accumulator += delta_time.min(MAX_FRAME_TIME);
+                    self.frame_time.delta_time = step;
+
+                    while accumulator >= step {
+                        let frame_time = self.frame_time;
+                        update(self, frame_time)?;
+
+                        accumulator -= step;
+                    }
+                },
+            }
+
+            self.compositor.update(delta_time);
+            self.draw_frame()?;
+            self.frame_time.frame += 1;
+        }
+
+        Ok(())
+    }
+
+    pub fn poll_events(&mut self) -> Vec<WindowEvent> {
+        match (&mut self.glfw, &self.events) {
+            (Some(glfw), Some(events)) => {
+                glfw.poll_events();
+                glfw::flush_messages(events).map(|(_, event)| event).collect()
+            },
+            _ => Vec::new()
+        }
+    }
+
+    // Draws the compositor's scene stack, then presents it. When headless, the stack
+    // is recorded instead, and the recorder only holds the latest frame.
+    // Text whose glyphs were dropped since the last frame is laid out again first.
+    pub fn draw_frame(&mut self) -> Result<(), EngineError> {
+        self.compositor.refresh_layout(&mut self.resource_manager)?;
+
+        if self.is_headless() {
+            self.draw_recorder.clear();
+            self.compositor.record(&mut self.draw_recorder);
+
+            return Ok(());
+        }
+
+        self.compositor.draw()?;
+
+        if let Some(window) = &mut self.window {
+            window.swap_buffers();
+        }
+
+        Ok(())
+    }
+
+    pub fn should_close(&self) -> bool {
+        match &self.window {
+            Some(window) => self.should_close || window.should_close(),
+            None => self.should_close
+        }
+    }
+
+    pub fn set_should_close(&mut self, value: bool) {
+        self.should_close = value;
+
+        if let Some(window) = &mut self.window {
+            window.set_should_close(value);
+        }
+    }
+
+    pub fn get_frame_time(&self) -> FrameTime { self.frame_time }
+
+    // Captures the window's default framebuffer, call after drawing but before swapping buffers
+    pub fn screenshot(&self) -> Result<RgbaImage, EngineError> {
+        match &self.window {
+            Some(window) => {
+                let (width, height) = window.get_framebuffer_size();
+                capture_framebuffer(width, height)
+            },
+            None => Err(EngineError::CaptureError(String::from("Cannot take a screenshot without a window!"))),
+        }
+    }
+
+    pub fn save_screenshot(&self, path: &str) -> Result<(), EngineError> {
+        self.screenshot()?.save(path)?;
+
+        Ok(())
     }
 
     // Callback function intended to be called from C
@@ -176,6 +370,10 @@ pub struct CSEngineConfig {
     pub title: String,
     pub gl: GraphicsLibrary,
     pub capture_mouse: bool,
+    pub visible: bool, // Set to false to render offscreen
+    pub vsync: bool,
+    pub update_mode: UpdateMode,
+    pub hot_reload: bool, // Reload shaders and textures when their files change, see ResourceManager::take_reload_errors()
     pub debug_level: DebugLevel
 }
 
@@ -188,11 +386,47 @@ impl Default for CSEngineConfig {
             title: String::from("My Game"),
             gl: GraphicsLibrary::OpenGL4_6(Default::default(), Default::default()), // TODO: default should be 3_3
             capture_mouse: true,
+            visible: true,
+            vsync: true,
+            update_mode: UpdateMode::Variable,
+            hot_reload: false,
             debug_level: DebugLevel::High // TODO: change to Medium
         }
     }
 }
 
+// Longest frame in seconds that fixed updates will try to catch up on
+const MAX_FRAME_TIME: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy)]
+pub enum UpdateMode {
+    // Update is called once per frame with the time since the last frame
+    Variable,
+    // Update is called with a fixed step in seconds, as many times as is needed
+    // to keep up with real time. Step must be greater than 0,
+    // otherwise run() returns a ConfigError.
+    Fixed(f32)
+}
+
+impl UpdateMode {
+    // A step that isn't positive would never drain the accumulator
+    pub fn validate(&self) -> Result<(), EngineError> {
+        match *self {
+            UpdateMode::Fixed(step) if !(step.is_finite() && step > 0.0) => Err(EngineError::ConfigError(
+                format!("Fixed update step must be a positive number of seconds, got {}", step)
+            )),
+            _ => Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameTime {
+    pub delta_time: f32, // Seconds since last update, use this for Camera::process_movement etc.
+    pub elapsed: f64, // Seconds since the render loop started
+    pub frame: u64
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum GraphicsLibrary {
     OpenGL4_6(OpenGL4_6Config, OpenGLExtSupport),
@@ -226,4 +460,31 @@ impl Default for OpenGLExtSupport {
 #[derive(Debug, Clone, Copy)]
 pub enum DebugLevel {
     High
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_step_must_be_positive() {
+        assert!(UpdateMode::Variable.validate().is_ok());
+        assert!(UpdateMode::Fixed(1.0 / 60.0).validate().is_ok());
+
+        for step in [0.0, -0.5, f32::NAN, f32::INFINITY] {
+            assert!(matches!(UpdateMode::Fixed(step).validate(), Err(EngineError::ConfigError(_))));
+        }
+    }
+
+    #[test]
+    fn run_rejects_invalid_step() {
+        let mut engine = CSEngine::new(CSEngineConfig {
+            gl: GraphicsLibrary::None,
+            update_mode: UpdateMode::Fixed(0.0),
+            ..Default::default()
+        });
+
+        let result = engine.run(|_, _| Ok(()), |_, _| Ok(()));
+        assert!(matches!(result, Err(EngineError::ConfigError(_))));
+    }
+}
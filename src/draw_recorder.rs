@@ -0,0 +1,146 @@
+use cgmath::Matrix4;
+
+// A draw that would have been issued to the graphics library
+#[derive(Debug, Clone)]
+pub enum DrawRecord {
+    Scene {
+        name: &'static str,
+        width: i32,
+        height: i32
+    },
+    Widget {
+        name: &'static str,
+        depth: usize, // How far down the widget tree this widget is, top-level widgets are 0
+        transform: Matrix4<f32>,
+        texture: Option<u32>
+    },
+    Model {
+        instances: usize
+    },
+    TransparentModel {
+        instances: usize
+    },
+    Skybox
+}
+
+// Used in place of drawing when running with GraphicsLibrary::None, so that
+// whole scripts can be run and asserted on without a window or GPU.
+// Assets are still loaded as normal, with GPU objects made by the null backend.
+// Records are kept until cleared, so clear between frames if only the
+// latest frame is wanted.
+#[derive(Debug, Default)]
+pub struct DrawRecorder {
+    pub records: Vec<DrawRecord>,
+    depth: usize
+}
+
+impl DrawRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, record: DrawRecord) {
+        self.records.push(record);
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.depth = 0;
+    }
+
+    pub fn get_depth(&self) -> usize { self.depth }
+
+    // Called around recording a widget's children so their depth is correct
+    pub fn push_depth(&mut self) { self.depth += 1 }
+    pub fn pop_depth(&mut self) { self.depth = self.depth.saturating_sub(1) }
+
+    pub fn widgets(&self) -> impl Iterator<Item = &DrawRecord> {
+        self.records.iter().filter(|record| matches!(record, DrawRecord::Widget { .. }))
+    }
+
+    // Name is matched against the end of the type name, so "TextureWidget" will
+    // match "cinema_skylight_engine::widgets::primitives::TextureWidget"
+    pub fn count_widgets(&self, name: &str) -> usize {
+        self.widgets()
+            .filter(|record| match record {
+                DrawRecord::Widget { name: widget_name, .. } => widget_name.ends_with(name),
+                _ => false
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use silver_gl::{GlImage, gl};
+    use crate::{CSEngine, CSEngineConfig, GraphicsLibrary, TextureOptions, Widget2dScene, primitives::TextureWidget};
+    use super::*;
+
+    fn headless_engine() -> CSEngine {
+        CSEngine::new(CSEngineConfig {
+            width: 640,
+            height: 480,
+            gl: GraphicsLibrary::None,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn depth_never_underflows() {
+        let mut recorder = DrawRecorder::new();
+        recorder.push_depth();
+        recorder.pop_depth();
+        recorder.pop_depth();
+        assert_eq!(recorder.get_depth(), 0);
+    }
+
+    #[test]
+    fn count_widgets_matches_end_of_type_name() {
+        let mut recorder = DrawRecorder::new();
+        recorder.push(DrawRecord::Skybox);
+        recorder.push(DrawRecord::Widget {
+            name: "cinema_skylight_engine::widgets::primitives::TextureWidget",
+            depth: 0,
+            transform: Matrix4::from_scale(1.0),
+            texture: None
+        });
+
+        assert_eq!(recorder.widgets().count(), 1);
+        assert_eq!(recorder.count_widgets("TextureWidget"), 1);
+        assert_eq!(recorder.count_widgets("TextWidget"), 0);
+    }
+
+    #[test]
+    fn headless_frame_records_loaded_assets() {
+        let mut engine = headless_engine();
+
+        let image = GlImage {
+            width: 2,
+            height: 2,
+            bytes: vec![255; 16],
+            internal_format: gl::SRGB8_ALPHA8,
+            data_format: gl::RGBA
+        };
+        let texture = engine.resource_manager
+            .upload_texture_2d("test.png", image, TextureOptions::default())
+            .unwrap();
+        let widget = TextureWidget::with_default_shader(&mut engine.resource_manager, Rc::clone(&texture)).unwrap();
+
+        let mut scene = Widget2dScene::new(640, 480).unwrap();
+        scene.children.push(Box::new(widget));
+        engine.compositor.push(Box::new(scene));
+
+        engine.draw_frame().unwrap();
+        assert!(matches!(engine.draw_recorder.records[0], DrawRecord::Scene { width: 640, height: 480, .. }));
+        assert_eq!(engine.draw_recorder.count_widgets("TextureWidget"), 1);
+        assert!(engine.draw_recorder.widgets().any(|record| matches!(
+            record,
+            DrawRecord::Widget { texture: Some(id), .. } if *id == texture.get_id()
+        )));
+
+        // Only the latest frame is kept
+        engine.draw_frame().unwrap();
+        assert_eq!(engine.draw_recorder.count_widgets("TextureWidget"), 1);
+    }
+}
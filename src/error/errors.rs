@@ -5,6 +5,7 @@ use silver_gl::GlError;
 #[derive(Debug)]
 pub enum EngineError {
     ObjLoadError(tobj::LoadError),
+    GltfLoadError(gltf::Error),
     ImageError(image::ImageError),
     IoError(std::io::Error),
     GlError(GlError),
@@ -13,7 +14,11 @@ pub enum EngineError {
     WidgetNotPrimitive(),
     FontError(freetype::Error),
     FontFamilyNotFound(String),
-    ResourceManagerError(String)
+    ResourceManagerError(String),
+    CaptureError(String),
+    VfsError(String),
+    ManifestError(String),
+    ConfigError(String)
 }
 
 // TODO: Write errors that suggest a solution as well
@@ -21,6 +26,7 @@ impl Display for EngineError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             EngineError::ObjLoadError(obj_err) => write!(f, "{}", obj_err),
+            EngineError::GltfLoadError(gltf_err) => write!(f, "{}", gltf_err),
             EngineError::ImageError(img_err) => write!(f, "{}", img_err),
             EngineError::IoError(io_err) => write!(f, "{}", io_err),
             EngineError::GlError(gl_err) => write!(f, "{}", gl_err),
@@ -30,6 +36,10 @@ impl Display for EngineError {
             EngineError::FontError(font_err) => write!(f, "{}", font_err),
             EngineError::FontFamilyNotFound(family) => write!(f, "Font family '{}' not found. This occurs when you haven't loaded a matching font via the resource manager.", family),
             EngineError::ResourceManagerError(rm_err) => write!(f, "Resource manager had an error: {}", rm_err),
+            EngineError::CaptureError(capture_err) => write!(f, "Failed to capture frame: {}", capture_err),
+            EngineError::VfsError(vfs_err) => write!(f, "Virtual file system had an error: {}", vfs_err),
+            EngineError::ManifestError(manifest_err) => write!(f, "Asset manifest had an error: {}", manifest_err),
+            EngineError::ConfigError(config_err) => write!(f, "Engine config is invalid: {}", config_err),
         }
     }
 }
@@ -42,6 +52,12 @@ impl From<tobj::LoadError> for EngineError {
     }
 }
 
+impl From<gltf::Error> for EngineError {
+    fn from(err: gltf::Error) -> Self {
+        EngineError::GltfLoadError(err)
+    }
+}
+
 impl From<image::ImageError> for EngineError {
     fn from(err: image::ImageError) -> Self {
         EngineError::ImageError(err)
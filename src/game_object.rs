@@ -55,6 +55,20 @@ impl GameObject {
         }
     }
 
+    // Every drawable in the tree with its index in the model's transforms and its
+    // transform in vec_space, matching what set_transform_to_drawable() sends
+    pub(crate) fn collect_drawables(&self, vec_space: Matrix4<f32>, drawables: &mut Vec<(Rc<Model>, usize, Matrix4<f32>)>) {
+        let matrix = vec_space * self.transform_matrix();
+
+        if let Some(drawable) = &self.drawable {
+            drawables.push((Rc::clone(drawable), self.model_index, matrix));
+        }
+
+        for child in &self.children {
+            child.collect_drawables(matrix, drawables);
+        }
+    }
+
     pub fn get_drawable(&self) -> &Option<Rc<Model>> {
         &self.drawable
     }
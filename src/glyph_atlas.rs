@@ -0,0 +1,271 @@
+use std::{rc::Rc, ffi::c_void};
+use cgmath::{Vector4, vec4};
+use silver_gl::{Texture, GlImage, gl};
+use crate::EngineError;
+
+const PAGE_SIZE: i32 = 1024;
+// Empty space around each glyph so linear filtering doesn't bleed in neighbours
+const PADDING: i32 = 1;
+
+// Row of glyphs, new glyphs are placed at the end of the first row they fit in
+struct Shelf {
+    y: i32,
+    height: i32,
+    x: i32
+}
+
+struct AtlasPage {
+    texture: Rc<Texture>,
+    shelves: Vec<Shelf>,
+    last_used: u64
+}
+
+impl AtlasPage {
+    fn new() -> Self {
+        let image = GlImage {
+            bytes: vec![0; (PAGE_SIZE * PAGE_SIZE) as usize],
+            internal_format: gl::RED,
+            data_format: gl::RED,
+            width: PAGE_SIZE,
+            height: PAGE_SIZE
+        };
+
+        Self {
+            texture: Rc::new(Texture::from_2d_ui(image)),
+            shelves: Vec::new(),
+            last_used: 0
+        }
+    }
+
+    // Picks the shortest shelf that fits to waste as little space as possible
+    fn allocate(&mut self, width: i32, height: i32) -> Option<(i32, i32)> {
+        let (width, height) = (width + PADDING, height + PADDING);
+
+        let shelf = self.shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= height && PAGE_SIZE - shelf.x >= width)
+            .min_by_key(|shelf| shelf.height);
+
+        if let Some(shelf) = shelf {
+            let position = (shelf.x, shelf.y);
+            shelf.x += width;
+
+            return Some(position);
+        }
+
+        let y = self.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+        if y + height > PAGE_SIZE || width > PAGE_SIZE {
+            return None;
+        }
+
+        self.shelves.push(Shelf { y, height, x: width });
+
+        Some((0, y))
+    }
+
+    fn upload(&self, x: i32, y: i32, width: i32, height: i32, bytes: &[u8]) {
+        unsafe {
+            // Rows of one byte textures aren't aligned to 4 bytes
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TextureSubImage2D(
+                self.texture.get_id(),
+                0,
+                x,
+                y,
+                width,
+                height,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                bytes.as_ptr() as *const c_void
+            );
+        }
+    }
+
+    fn clear(&mut self) {
+        self.shelves.clear();
+        self.upload(0, 0, PAGE_SIZE, PAGE_SIZE, &vec![0; (PAGE_SIZE * PAGE_SIZE) as usize]);
+    }
+}
+
+// Where a glyph is stored in the atlas, uv is (left, top, right, bottom)
+pub struct AtlasRegion {
+    pub page: usize,
+    pub texture: Rc<Texture>,
+    pub uv: Vector4<f32>
+}
+
+// Packs single channel glyph bitmaps into a few large textures so text can be drawn
+// with one texture bind per page. Pages are added as they fill up, and once
+// max_pages is reached the least recently used page is cleared to make room.
+// Clearing a page invalidates any glyphs on it, which is tracked by the generation.
+// While pinned, pages used since pinning started are never evicted, so text being
+// laid out can't lose its earlier glyphs. If every page is pinned, a page is added
+// past max_pages instead.
+pub struct GlyphAtlas {
+    pages: Vec<AtlasPage>,
+    max_pages: usize,
+    clock: u64,
+    generation: u64,
+    pinned_since: Option<u64>
+}
+
+impl GlyphAtlas {
+    pub fn new(max_pages: usize) -> Self {
+        Self {
+            pages: Vec::new(),
+            max_pages: max_pages.max(1),
+            clock: 0,
+            generation: 0,
+            pinned_since: None
+        }
+    }
+
+    pub fn pin(&mut self) { self.pinned_since = Some(self.clock) }
+    pub fn unpin(&mut self) { self.pinned_since = None }
+
+    fn is_pinned(&self, page: &AtlasPage) -> bool {
+        self.pinned_since.map_or(false, |pinned_since| page.last_used > pinned_since)
+    }
+
+    // Increases every time glyphs are removed from the atlas
+    pub fn get_generation(&self) -> u64 { self.generation }
+
+    pub fn page_count(&self) -> usize { self.pages.len() }
+
+    pub fn get_page_texture(&self, page: usize) -> Option<&Rc<Texture>> {
+        self.pages.get(page).map(|page| &page.texture)
+    }
+
+    // Marks a page as used so it is less likely to be evicted
+    pub fn touch(&mut self, page: usize) {
+        self.clock += 1;
+
+        if let Some(page) = self.pages.get_mut(page) {
+            page.last_used = self.clock;
+        }
+    }
+
+    // Bytes are tightly packed rows of width bytes. If a page had to be cleared to
+    // make room its index is returned alongside the region.
+    pub fn insert(&mut self, bytes: &[u8], width: i32, height: i32) -> Result<(AtlasRegion, Option<usize>), EngineError> {
+        if width + PADDING > PAGE_SIZE || height + PADDING > PAGE_SIZE {
+            return Err(EngineError::ResourceManagerError(format!("A {}x{} glyph is too large for the glyph atlas!", width, height)));
+        }
+
+        if self.pages.is_empty() {
+            self.pages.push(AtlasPage::new());
+        }
+
+        // Glyphs like spaces have no bitmap, so don't take up any space
+        if width == 0 || height == 0 {
+            return Ok((self.region(0, 0, 0, 0, 0), None));
+        }
+
+        for i in 0..self.pages.len() {
+            if let Some((x, y)) = self.pages[i].allocate(width, height) {
+                self.pages[i].upload(x, y, width, height, bytes);
+                self.touch(i);
+
+                return Ok((self.region(i, x, y, width, height), None));
+            }
+        }
+
+        let least_recent = self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| !self.is_pinned(page))
+            .min_by_key(|(_, page)| page.last_used)
+            .map(|(i, _)| i);
+
+        let (page, evicted) = match least_recent {
+            Some(page) if self.pages.len() >= self.max_pages => {
+                self.pages[page].clear();
+                self.generation += 1;
+
+                (page, Some(page))
+            },
+            _ => {
+                self.pages.push(AtlasPage::new());
+
+                (self.pages.len() - 1, None)
+            }
+        };
+
+        let (x, y) = self.pages[page]
+            .allocate(width, height)
+            .expect("Glyph should always fit in an empty page");
+        self.pages[page].upload(x, y, width, height, bytes);
+        self.touch(page);
+
+        Ok((self.region(page, x, y, width, height), evicted))
+    }
+
+    // Removes every glyph, keeping the pages' textures
+    pub fn clear(&mut self) {
+        for page in &mut self.pages {
+            page.clear();
+        }
+
+        self.generation += 1;
+    }
+
+    fn region(&self, page: usize, x: i32, y: i32, width: i32, height: i32) -> AtlasRegion {
+        let size = PAGE_SIZE as f32;
+
+        AtlasRegion {
+            page,
+            texture: Rc::clone(&self.pages[page].texture),
+            uv: vec4(
+                x as f32 / size,
+                y as f32 / size,
+                (x + width) as f32 / size,
+                (y + height) as f32 / size
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::load_null_gl;
+    use super::*;
+
+    // Fills a whole page, so every insert needs a page of its own
+    fn insert_full_page(atlas: &mut GlyphAtlas) -> (AtlasRegion, Option<usize>) {
+        let size = PAGE_SIZE - PADDING;
+        atlas.insert(&vec![255; (size * size) as usize], size, size).unwrap()
+    }
+
+    #[test]
+    fn least_recently_used_page_is_evicted() {
+        load_null_gl();
+        let mut atlas = GlyphAtlas::new(2);
+
+        assert_eq!(insert_full_page(&mut atlas).1, None);
+        assert_eq!(insert_full_page(&mut atlas).1, None);
+        atlas.touch(0);
+
+        let (region, evicted) = insert_full_page(&mut atlas);
+        assert_eq!((region.page, evicted), (1, Some(1)));
+        assert_eq!(atlas.get_generation(), 1);
+    }
+
+    #[test]
+    fn pinned_pages_are_not_evicted() {
+        load_null_gl();
+        let mut atlas = GlyphAtlas::new(1);
+        insert_full_page(&mut atlas);
+
+        atlas.pin();
+        atlas.touch(0);
+        let (region, evicted) = insert_full_page(&mut atlas);
+        assert_eq!((region.page, evicted), (1, None));
+        assert_eq!(atlas.page_count(), 2);
+        atlas.unpin();
+
+        // Back over the limit, so pages are reused again
+        let (region, evicted) = insert_full_page(&mut atlas);
+        assert_eq!((region.page, evicted), (0, Some(0)));
+        assert_eq!(atlas.get_generation(), 1);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{rc::Rc, collections::HashMap, hash::{Hash, Hasher}, marker::PhantomData, fmt};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use crate::{ResourceManager, EngineError};
+
+// Lightweight id for an asset in the resource manager, resolved with
+// ResourceManager::get(). Handles are only valid while their asset is loaded,
+// unloading it bumps its slot's generation so old handles resolve to None even
+// once the slot is reused. To save a reference, store the AssetRef from
+// ResourceManager::save_handle() and get the handle back with load_handle().
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    marker: PhantomData<fn() -> T>
+}
+
+impl<T> Handle<T> {
+    pub fn get_index(&self) -> u32 { self.index }
+    pub fn get_generation(&self) -> u32 { self.generation }
+}
+
+// Implemented by hand, as deriving would require T to implement them too
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle<{}>({}v{})", std::any::type_name::<T>(), self.index, self.generation)
+    }
+}
+
+// What a handle is saved as, since handles only mean something while the game is
+// running. Assets loaded from the manifest are saved by their logical name, so
+// saves still work if the manifest moves their file, and anything else by the
+// key it is cached under.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetRef<K> {
+    Name(String),
+    Key(K)
+}
+
+// Fonts are used by their family name, e.g. in TextStyle, so this is what a
+// Handle<Font> resolves to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Font {
+    pub family: String,
+    pub path: String
+}
+
+// Asset types the resource manager hands out handles for. Keys are what the
+// asset is cached under, e.g. a texture's path along with its options.
+pub trait HandleResource: Sized {
+    type Key: Clone + Eq + Hash + fmt::Debug + Serialize + DeserializeOwned;
+
+    fn handles(resource_manager: &ResourceManager) -> &HandleTable<Self>;
+    fn handles_mut(resource_manager: &mut ResourceManager) -> &mut HandleTable<Self>;
+    fn resolve(resource_manager: &ResourceManager, key: &Self::Key) -> Option<Rc<Self>>;
+    // How many references there are outside the resource manager, None if they
+    // aren't counted
+    fn usage(resource_manager: &ResourceManager, key: &Self::Key) -> Option<usize>;
+    fn swap(resource_manager: &mut ResourceManager, key: &Self::Key, asset: Rc<Self>) -> Result<(), EngineError>;
+    // Loads the manifest entry with the name, returning the key it is cached under
+    fn load_named(resource_manager: &mut ResourceManager, name: &str) -> Result<Self::Key, EngineError>;
+    // Name of the manifest entry that is cached under the key, if there is one
+    fn find_name(resource_manager: &ResourceManager, key: &Self::Key) -> Option<String>;
+}
+
+// Each slot holds the key of the asset it points to, and how many times it has
+// been reused. Freed slots are reused before new ones are added.
+pub struct HandleTable<T: HandleResource> {
+    slots: Vec<(u32, Option<T::Key>)>,
+    free: Vec<u32>,
+    indices: HashMap<T::Key, u32>,
+    marker: PhantomData<fn() -> T>
+}
+
+impl<T: HandleResource> Default for HandleTable<T> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            indices: HashMap::new(),
+            marker: PhantomData
+        }
+    }
+}
+
+impl<T: HandleResource> HandleTable<T> {
+    // Assets always get the same handle while they are loaded
+    pub(crate) fn get_or_insert(&mut self, key: &T::Key) -> Handle<T> {
+        let index = match self.indices.get(key) {
+            Some(index) => *index,
+            None => {
+                let index = match self.free.pop() {
+                    Some(index) => {
+                        self.slots[index as usize].1 = Some(key.clone());
+                        index
+                    },
+                    None => {
+                        self.slots.push((0, Some(key.clone())));
+                        self.slots.len() as u32 - 1
+                    }
+                };
+
+                self.indices.insert(key.clone(), index);
+                index
+            }
+        };
+
+        Handle { index, generation: self.slots[index as usize].0, marker: PhantomData }
+    }
+
+    pub(crate) fn get_key(&self, handle: Handle<T>) -> Option<&T::Key> {
+        match self.slots.get(handle.index as usize) {
+            Some((generation, key)) if *generation == handle.generation => key.as_ref(),
+            _ => None
+        }
+    }
+
+    // Invalidates every handle to the asset
+    pub(crate) fn remove(&mut self, key: &T::Key) {
+        if let Some(index) = self.indices.remove(key) {
+            let slot = &mut self.slots[index as usize];
+            slot.0 = slot.0.wrapping_add(1);
+            slot.1 = None;
+
+            self.free.push(index);
+        }
+    }
+
+    pub(crate) fn get_handles(&self) -> Vec<Handle<T>> {
+        self.indices
+            .values()
+            .map(|index| Handle { index: *index, generation: self.slots[*index as usize].0, marker: PhantomData })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use silver_gl::Texture;
+    use super::*;
+
+    fn key(path: &str) -> String {
+        String::from(path)
+    }
+
+    #[test]
+    fn get_or_insert_reuses_handles() {
+        let mut table: HandleTable<Texture> = HandleTable::default();
+        let a = table.get_or_insert(&key("a.png"));
+        let b = table.get_or_insert(&key("b.png"));
+
+        assert_eq!(table.get_or_insert(&key("a.png")), a);
+        assert_ne!(a, b);
+        assert_eq!(table.get_key(a), Some(&key("a.png")));
+        assert_eq!(table.get_handles().len(), 2);
+    }
+
+    #[test]
+    fn removed_handles_stay_invalid() {
+        let mut table: HandleTable<Texture> = HandleTable::default();
+        let old = table.get_or_insert(&key("a.png"));
+
+        table.remove(&key("a.png"));
+        assert_eq!(table.get_key(old), None);
+
+        // The slot is reused with the next generation
+        let new = table.get_or_insert(&key("b.png"));
+        assert_eq!(new.get_index(), old.get_index());
+        assert_eq!(new.get_generation(), old.get_generation() + 1);
+        assert_eq!(table.get_key(old), None);
+        assert_eq!(table.get_key(new), Some(&key("b.png")));
+    }
+}
@@ -0,0 +1,422 @@
+use std::{collections::HashMap, time::{SystemTime, Instant, Duration}, ffi::CString, path::{Path, PathBuf}, ptr};
+use silver_gl::{Texture, GlImage, ShaderProgram, ShaderCodeBundle, gl};
+use crate::{EngineError, Vfs, upload_pixels};
+
+// How often files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Polls the modification time of files, which is plenty for development and
+// doesn't depend on platform specific file events. Files are watched where the
+// virtual file system finds them on disk, files in archives can't change.
+pub(crate) struct FileWatcher {
+    files: HashMap<String, (PathBuf, Option<SystemTime>)>,
+    last_poll: Instant
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        Self {
+            files: HashMap::new(),
+            last_poll: Instant::now()
+        }
+    }
+
+    pub fn watch(&mut self, vfs: &Vfs, path: &str) {
+        if self.files.contains_key(path) {
+            return;
+        }
+
+        if let Some(real_path) = vfs.real_path(path) {
+            let modified = modified_time(&real_path);
+            self.files.insert(path.to_owned(), (real_path, modified));
+        }
+    }
+
+    pub fn unwatch(&mut self, path: &str) {
+        self.files.remove(path);
+    }
+
+    // Paths that changed since the last poll
+    pub fn poll(&mut self) -> Vec<String> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = Vec::new();
+        for (path, (real_path, last_modified)) in self.files.iter_mut() {
+            let modified = modified_time(real_path);
+
+            // Files that are missing part way through a save are checked again next time
+            if modified.is_some() && modified != *last_modified {
+                *last_modified = modified;
+                changed.push(path.clone());
+            }
+        }
+
+        changed
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// Uploads the image into the existing texture, so everything holding it sees the
+// new version. The image has to be the same size, since texture storage can't be resized.
+pub(crate) fn reupload_texture(texture: &Texture, image: &GlImage) -> Result<(), EngineError> {
+    let id = texture.get_id();
+    let (mut target, mut width, mut height) = (0, 0, 0);
+
+    unsafe {
+        gl::GetTextureParameteriv(id, gl::TEXTURE_TARGET, &mut target);
+        gl::GetTextureLevelParameteriv(id, 0, gl::TEXTURE_WIDTH, &mut width);
+        gl::GetTextureLevelParameteriv(id, 0, gl::TEXTURE_HEIGHT, &mut height);
+    }
+
+    if target as u32 != gl::TEXTURE_2D {
+        return Err(EngineError::ResourceManagerError(String::from("Only 2D textures can be hot reloaded!")));
+    }
+    if (width, height) != (image.width, image.height) {
+        return Err(
+            EngineError::ResourceManagerError(
+                format!("Texture changed size from {}x{} to {}x{}, which needs a restart!", width, height, image.width, image.height)
+            )
+        );
+    }
+
+    upload_pixels(texture, image);
+
+    Ok(())
+}
+
+// Compiles the code and links it into the existing program, so everything holding
+// it sees the new version. The code is linked into a temporary program first, so
+// if anything fails the program is left as it was. Linking resets uniforms and
+// uniform block bindings, e.g. sampler units and CameraMatrices, so any that
+// still exist by the same name are restored afterwards.
+pub(crate) fn relink_shader_program(shader_program: &ShaderProgram, code: &ShaderCodeBundle) -> Result<(), EngineError> {
+    let mut shaders = Vec::new();
+
+    for (kind, source) in [
+        (gl::VERTEX_SHADER, &code.vertex),
+        (gl::GEOMETRY_SHADER, &code.geometry),
+        (gl::FRAGMENT_SHADER, &code.fragment)
+    ] {
+        if let Some(source) = source {
+            match compile_shader(kind, source) {
+                Ok(shader) => shaders.push(shader),
+                Err(err) => {
+                    delete_shaders(&shaders);
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    let result = unsafe {
+        let test_program = gl::CreateProgram();
+        let result = link_program(test_program, &shaders);
+        gl::DeleteProgram(test_program);
+
+        result.and_then(|_| {
+            let id = shader_program.get_id();
+            let state = ProgramState::capture(id);
+
+            let mut attached = [0; 3];
+            let mut count = 0;
+            gl::GetAttachedShaders(id, attached.len() as i32, &mut count, attached.as_mut_ptr());
+            for shader in &attached[..count as usize] {
+                gl::DetachShader(id, *shader);
+            }
+
+            link_program(id, &shaders)?;
+            state.restore(id);
+
+            Ok(())
+        })
+    };
+
+    delete_shaders(&shaders);
+
+    result
+}
+
+enum UniformValue {
+    Float(Vec<f32>),
+    Int(Vec<i32>)
+}
+
+// Uniform values and uniform block bindings of a program, by name
+struct ProgramState {
+    uniforms: Vec<(CString, u32, UniformValue)>, // Name, type and value
+    blocks: Vec<(CString, u32)> // Name and binding
+}
+
+impl ProgramState {
+    unsafe fn capture(program: u32) -> Self {
+        let mut uniforms = Vec::new();
+        let mut blocks = Vec::new();
+
+        let mut count = 0;
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+        for index in 0..count.max(0) as u32 {
+            let mut name = [0u8; 256];
+            let (mut length, mut size, mut kind) = (0, 0, 0);
+            gl::GetActiveUniform(program, index, name.len() as i32, &mut length, &mut size, &mut kind, name.as_mut_ptr() as *mut _);
+            let name = String::from_utf8_lossy(&name[..length.max(0) as usize]).into_owned();
+
+            // Arrays are listed once as "name[0]", so each element is found by name
+            let elements: Vec<String> = match name.strip_suffix("[0]") {
+                Some(base) => (0..size).map(|i| format!("{}[{}]", base, i)).collect(),
+                None => vec![name]
+            };
+
+            for element in elements {
+                let Ok(element) = CString::new(element) else { continue };
+                // Uniforms in blocks don't have a location
+                let location = gl::GetUniformLocation(program, element.as_ptr());
+                if location < 0 {
+                    continue;
+                }
+
+                let value = match uniform_layout(kind) {
+                    Some((false, components)) => {
+                        let mut value = vec![0.0; components];
+                        gl::GetUniformfv(program, location, value.as_mut_ptr());
+                        UniformValue::Float(value)
+                    },
+                    Some((true, components)) => {
+                        let mut value = vec![0; components];
+                        gl::GetUniformiv(program, location, value.as_mut_ptr());
+                        UniformValue::Int(value)
+                    },
+                    None => continue
+                };
+
+                uniforms.push((element, kind, value));
+            }
+        }
+
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCKS, &mut count);
+        for index in 0..count.max(0) as u32 {
+            let mut name = [0u8; 256];
+            let mut length = 0;
+            gl::GetActiveUniformBlockName(program, index, name.len() as i32, &mut length, name.as_mut_ptr() as *mut _);
+            let mut binding = 0;
+            gl::GetActiveUniformBlockiv(program, index, gl::UNIFORM_BLOCK_BINDING, &mut binding);
+
+            if let Ok(name) = CString::new(&name[..length.max(0) as usize]) {
+                blocks.push((name, binding as u32));
+            }
+        }
+
+        Self { uniforms, blocks }
+    }
+
+    unsafe fn restore(&self, program: u32) {
+        for (name, kind, value) in &self.uniforms {
+            let location = gl::GetUniformLocation(program, name.as_ptr());
+            if location < 0 {
+                continue;
+            }
+
+            match value {
+                UniformValue::Float(value) => match *kind {
+                    gl::FLOAT => gl::ProgramUniform1fv(program, location, 1, value.as_ptr()),
+                    gl::FLOAT_VEC2 => gl::ProgramUniform2fv(program, location, 1, value.as_ptr()),
+                    gl::FLOAT_VEC3 => gl::ProgramUniform3fv(program, location, 1, value.as_ptr()),
+                    gl::FLOAT_VEC4 => gl::ProgramUniform4fv(program, location, 1, value.as_ptr()),
+                    gl::FLOAT_MAT2 => gl::ProgramUniformMatrix2fv(program, location, 1, gl::FALSE, value.as_ptr()),
+                    gl::FLOAT_MAT3 => gl::ProgramUniformMatrix3fv(program, location, 1, gl::FALSE, value.as_ptr()),
+                    _ => gl::ProgramUniformMatrix4fv(program, location, 1, gl::FALSE, value.as_ptr())
+                },
+                UniformValue::Int(value) => match value.len() {
+                    1 => gl::ProgramUniform1iv(program, location, 1, value.as_ptr()),
+                    2 => gl::ProgramUniform2iv(program, location, 1, value.as_ptr()),
+                    3 => gl::ProgramUniform3iv(program, location, 1, value.as_ptr()),
+                    _ => gl::ProgramUniform4iv(program, location, 1, value.as_ptr())
+                }
+            }
+        }
+
+        for (name, binding) in &self.blocks {
+            let index = gl::GetUniformBlockIndex(program, name.as_ptr());
+            if index != gl::INVALID_INDEX {
+                gl::UniformBlockBinding(program, index, *binding);
+            }
+        }
+    }
+}
+
+// Whether a uniform type is read as integers, and how many components it has.
+// Samplers are integers holding their texture unit. Types not listed, like
+// doubles and unsigned integers, aren't restored.
+fn uniform_layout(kind: u32) -> Option<(bool, usize)> {
+    match kind {
+        gl::FLOAT => Some((false, 1)),
+        gl::FLOAT_VEC2 => Some((false, 2)),
+        gl::FLOAT_VEC3 => Some((false, 3)),
+        gl::FLOAT_VEC4 => Some((false, 4)),
+        gl::FLOAT_MAT2 => Some((false, 4)),
+        gl::FLOAT_MAT3 => Some((false, 9)),
+        gl::FLOAT_MAT4 => Some((false, 16)),
+        gl::INT | gl::BOOL => Some((true, 1)),
+        gl::INT_VEC2 | gl::BOOL_VEC2 => Some((true, 2)),
+        gl::INT_VEC3 | gl::BOOL_VEC3 => Some((true, 3)),
+        gl::INT_VEC4 | gl::BOOL_VEC4 => Some((true, 4)),
+        gl::SAMPLER_1D | gl::SAMPLER_2D | gl::SAMPLER_3D | gl::SAMPLER_CUBE
+            | gl::SAMPLER_2D_SHADOW | gl::SAMPLER_CUBE_SHADOW | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_2D_MULTISAMPLE | gl::INT_SAMPLER_2D | gl::UNSIGNED_INT_SAMPLER_2D => Some((true, 1)),
+        _ => None
+    }
+}
+
+fn compile_shader(kind: u32, source: &str) -> Result<u32, EngineError> {
+    let source = CString::new(source)
+        .map_err(|_| EngineError::ResourceManagerError(String::from("Shader source contains a null character!")))?;
+
+    unsafe {
+        let shader = gl::CreateShader(kind);
+        gl::ShaderSource(shader, 1, &source.as_ptr(), ptr::null());
+        gl::CompileShader(shader);
+
+        let mut success = 0;
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
+        if success == 0 {
+            let mut length = 0;
+            gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut length);
+            let mut log = vec![0u8; length.max(1) as usize];
+            gl::GetShaderInfoLog(shader, length, ptr::null_mut(), log.as_mut_ptr() as *mut _);
+            gl::DeleteShader(shader);
+
+            return Err(EngineError::ResourceManagerError(format!("Shader failed to compile:\n{}", log_to_string(&log))));
+        }
+
+        Ok(shader)
+    }
+}
+
+// Shaders are detached again afterwards, leaving them free to be deleted
+unsafe fn link_program(program: u32, shaders: &[u32]) -> Result<(), EngineError> {
+    for shader in shaders {
+        gl::AttachShader(program, *shader);
+    }
+    gl::LinkProgram(program);
+    for shader in shaders {
+        gl::DetachShader(program, *shader);
+    }
+
+    let mut success = 0;
+    gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
+    if success == 0 {
+        let mut length = 0;
+        gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut length);
+        let mut log = vec![0u8; length.max(1) as usize];
+        gl::GetProgramInfoLog(program, length, ptr::null_mut(), log.as_mut_ptr() as *mut _);
+
+        return Err(EngineError::ResourceManagerError(format!("Shader program failed to link:\n{}", log_to_string(&log))));
+    }
+
+    Ok(())
+}
+
+fn delete_shaders(shaders: &[u32]) {
+    for shader in shaders {
+        unsafe { gl::DeleteShader(*shader) };
+    }
+}
+
+fn log_to_string(log: &[u8]) -> String {
+    String::from_utf8_lossy(log).trim_end_matches('\0').to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::{self, File}, rc::Rc};
+    use crate::{CSEngine, CSEngineConfig, GraphicsLibrary, ShaderPathBundle};
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cs_engine_hot_reload_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Saves the file with a modification time that is different from the last save
+    fn save(path: &Path, contents: &str, seconds: u64) {
+        fs::write(path, contents).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    #[test]
+    fn poll_finds_changed_files() {
+        let dir = temp_dir("poll");
+        save(&dir.join("a.png"), "a", 1);
+        save(&dir.join("b.png"), "b", 1);
+
+        let mut vfs = Vfs::new();
+        vfs.mount_dir(&dir);
+        let mut file_watcher = FileWatcher::new();
+        file_watcher.watch(&vfs, "a.png");
+        file_watcher.watch(&vfs, "b.png");
+        file_watcher.watch(&vfs, "missing.png");
+
+        save(&dir.join("a.png"), "a", 2);
+        assert!(file_watcher.poll().is_empty(), "Files are only checked every poll interval");
+
+        file_watcher.last_poll -= POLL_INTERVAL;
+        assert_eq!(file_watcher.poll(), vec![String::from("a.png")]);
+
+        file_watcher.last_poll -= POLL_INTERVAL;
+        assert!(file_watcher.poll().is_empty());
+
+        file_watcher.unwatch("b.png");
+        save(&dir.join("b.png"), "b", 2);
+        file_watcher.last_poll -= POLL_INTERVAL;
+        assert!(file_watcher.poll().is_empty());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn failed_relink_keeps_program() {
+        let dir = temp_dir("relink");
+        save(&dir.join("test.vert"), "#version 460 core\n#include \"common.glsl\"\nvoid main() {}\n", 1);
+        save(&dir.join("common.glsl"), "const float SCALE = 1.0;\n", 1);
+        save(&dir.join("test.frag"), "#version 460 core\nvoid main() {}\n", 1);
+
+        let mut engine = CSEngine::new(CSEngineConfig {
+            gl: GraphicsLibrary::None,
+            ..Default::default()
+        });
+        let resource_manager = &mut engine.resource_manager;
+        resource_manager.get_vfs_mut().mount_dir(&dir);
+
+        let paths = ShaderPathBundle {
+            vertex: Some(String::from("test.vert")),
+            fragment: Some(String::from("test.frag")),
+            ..Default::default()
+        };
+        let shader_program = resource_manager.load_shader_program(paths.clone()).unwrap();
+        resource_manager.set_hot_reload(true);
+
+        // The include can't be found, so the program is never relinked
+        save(&dir.join("test.vert"), "#version 460 core\n#include \"missing.glsl\"\nvoid main() {}\n", 2);
+        std::thread::sleep(POLL_INTERVAL);
+        resource_manager.update_hot_reload();
+
+        let errors = resource_manager.take_reload_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("test.vert"));
+        assert!(Rc::ptr_eq(&shader_program, &resource_manager.load_shader_program(paths).unwrap()));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
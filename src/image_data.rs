@@ -0,0 +1,348 @@
+use std::ffi::c_void;
+use silver_gl::{Texture, GlImage, gl};
+
+// GlImage doesn't say what type its pixels are, so it is worked out from the
+// internal format. 16 bit formats hold u16s and half float formats hold f32s,
+// as that is what images are decoded to.
+pub(crate) fn pixel_type(image: &GlImage) -> u32 {
+    match image.internal_format {
+        gl::R16 | gl::RG16 | gl::RGB16 | gl::RGBA16 => gl::UNSIGNED_SHORT,
+        gl::R16F | gl::RG16F | gl::RGB16F | gl::RGBA16F
+        | gl::R32F | gl::RG32F | gl::RGB32F | gl::RGBA32F => gl::FLOAT,
+        _ => gl::UNSIGNED_BYTE
+    }
+}
+
+pub(crate) fn channel_count(image: &GlImage) -> usize {
+    match image.data_format {
+        gl::RED => 1,
+        gl::RG => 2,
+        gl::RGB => 3,
+        _ => 4
+    }
+}
+
+// Texture::from_2d() only uploads bytes, so other types are uploaded into the
+// storage it creates for a blank image of the same format
+pub(crate) fn create_texture_2d(image: GlImage) -> Texture {
+    let data_type = pixel_type(&image);
+    if data_type == gl::UNSIGNED_BYTE {
+        return Texture::from_2d(image);
+    }
+
+    let blank = GlImage {
+        bytes: vec![0; image.width as usize * image.height as usize * channel_count(&image)],
+        internal_format: image.internal_format,
+        data_format: image.data_format,
+        width: image.width,
+        height: image.height
+    };
+    let texture = Texture::from_2d(blank);
+    upload_pixels(&texture, &image);
+
+    texture
+}
+
+// Every channel of every pixel, with integer formats scaled to 0 to 1
+pub(crate) fn to_f32_channels(image: &GlImage) -> Vec<f32> {
+    match pixel_type(image) {
+        gl::UNSIGNED_SHORT => image.bytes
+            .chunks_exact(2)
+            .map(|channel| u16::from_ne_bytes([channel[0], channel[1]]) as f32 / u16::MAX as f32)
+            .collect(),
+        gl::FLOAT => image.bytes
+            .chunks_exact(4)
+            .map(|channel| f32::from_ne_bytes(channel.try_into().unwrap()))
+            .collect(),
+        _ => image.bytes.iter().map(|channel| *channel as f32 / 255.0).collect()
+    }
+}
+
+// Reverse of to_f32_channels(), the values are stored the same way as in format
+pub(crate) fn from_f32_channels(values: &[f32], width: i32, height: i32, format: &GlImage) -> GlImage {
+    let bytes = match pixel_type(format) {
+        gl::UNSIGNED_SHORT => values
+            .iter()
+            .flat_map(|value| ((value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16).to_ne_bytes())
+            .collect(),
+        gl::FLOAT => values.iter().flat_map(|value| value.to_ne_bytes()).collect(),
+        _ => values.iter().map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8).collect()
+    };
+
+    GlImage {
+        bytes,
+        internal_format: format.internal_format,
+        data_format: format.data_format,
+        width,
+        height
+    }
+}
+
+// Replaces the pixels of a 2D texture with an image of the same size
+pub(crate) fn upload_pixels(texture: &Texture, image: &GlImage) {
+    upload_pixels_without_mipmaps(texture, image);
+    unsafe { gl::GenerateTextureMipmap(texture.get_id()) };
+}
+
+// For textures without mipmaps that are replaced often, such as video frames
+pub(crate) fn upload_pixels_without_mipmaps(texture: &Texture, image: &GlImage) {
+    unsafe {
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TextureSubImage2D(
+            texture.get_id(),
+            0,
+            0,
+            0,
+            image.width,
+            image.height,
+            image.data_format,
+            pixel_type(image),
+            image.bytes.as_ptr() as *const c_void
+        );
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+    }
+}
+
+// Multiplies every pixel by a linear RGBA factor. Colour stored as sRGB is converted
+// to linear to be multiplied, then back again.
+pub(crate) fn tint_image(image: &GlImage, factor: [f32; 4], srgb: bool) -> GlImage {
+    let channels = channel_count(image);
+    let has_alpha = matches!(image.data_format, gl::RGBA | gl::RG);
+    let colour_channels = if has_alpha { channels - 1 } else { channels };
+
+    let values: Vec<f32> = to_f32_channels(image)
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let channel = i % channels;
+            if channel >= colour_channels {
+                return value * factor[3];
+            }
+
+            // Luma is tinted by red, as the closest there is to a brightness
+            let factor = if colour_channels >= 3 { factor[channel] } else { factor[0] };
+            if srgb {
+                linear_to_srgb(srgb_to_linear(*value) * factor)
+            } else {
+                value * factor
+            }
+        })
+        .collect();
+
+    from_f32_channels(&values, image.width, image.height, image)
+}
+
+// Single channel specular strength from a glTF metallic-roughness map, which has
+// roughness in green and metalness in blue, each multiplied by their factor.
+// Metals reflect most light and everything else about 4%, and rougher surfaces
+// spread their highlight out, so it is dimmed by roughness.
+pub(crate) fn metallic_roughness_to_specular(image: &GlImage, metallic_factor: f32, roughness_factor: f32) -> GlImage {
+    let channels = channel_count(image);
+    let values: Vec<f32> = to_f32_channels(image)
+        .chunks_exact(channels)
+        .map(|pixel| {
+            let roughness = pixel.get(1).copied().unwrap_or(1.0) * roughness_factor;
+            let metallic = pixel.get(2).copied().unwrap_or(0.0) * metallic_factor;
+
+            (0.04 + 0.96 * metallic) * (1.0 - roughness)
+        })
+        .collect();
+
+    let format = GlImage {
+        bytes: Vec::new(),
+        internal_format: gl::R8,
+        data_format: gl::RED,
+        width: image.width,
+        height: image.height
+    };
+
+    from_f32_channels(&values, image.width, image.height, &format)
+}
+
+// Alpha for meshes drawn without blending. With no cutoff every pixel is made
+// opaque, otherwise alpha below the cutoff becomes 0 and the rest 1, so the
+// G-buffer shader only has to discard fully transparent pixels.
+pub(crate) fn cut_alpha(image: GlImage, cutoff: Option<f32>) -> GlImage {
+    if !matches!(image.data_format, gl::RGBA | gl::RG) {
+        return image;
+    }
+    let channels = channel_count(&image);
+
+    let values: Vec<f32> = to_f32_channels(&image)
+        .iter()
+        .enumerate()
+        .map(|(i, value)| match cutoff {
+            _ if i % channels != channels - 1 => *value,
+            Some(cutoff) if *value < cutoff => 0.0,
+            _ => 1.0
+        })
+        .collect();
+
+    from_f32_channels(&values, image.width, image.height, &image)
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 }
+}
+
+// Whether any pixel has an alpha below 1, alpha is the last of each pixel's channels
+pub(crate) fn image_has_transparency(image: &GlImage) -> bool {
+    let channels = match image.data_format {
+        gl::RGBA => 4,
+        gl::RG => 2, // Luma with alpha
+        _ => return false
+    };
+
+    match pixel_type(image) {
+        gl::UNSIGNED_SHORT => image.bytes
+            .chunks_exact(channels * 2)
+            .any(|pixel| u16::from_ne_bytes([pixel[channels * 2 - 2], pixel[channels * 2 - 1]]) < u16::MAX),
+        gl::FLOAT => image.bytes
+            .chunks_exact(channels * 4)
+            .any(|pixel| f32::from_ne_bytes(pixel[channels * 4 - 4..].try_into().unwrap()) < 1.0),
+        _ => image.bytes.chunks_exact(channels).any(|pixel| pixel[channels - 1] < 255)
+    }
+}
+
+// There are no 16 bit sRGB formats, so sRGB colour is converted to linear before
+// it is uploaded instead. Alpha is always linear.
+pub(crate) fn decode_srgb_16(image: &mut GlImage) {
+    if !matches!(image.data_format, gl::RGB | gl::RGBA) {
+        return;
+    }
+    let channels = channel_count(image);
+
+    for (i, channel) in image.bytes.chunks_exact_mut(2).enumerate() {
+        if i % channels >= 3 {
+            continue;
+        }
+
+        let value = u16::from_ne_bytes([channel[0], channel[1]]) as f32 / u16::MAX as f32;
+        let linear = if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        };
+
+        channel.copy_from_slice(&((linear * u16::MAX as f32).round() as u16).to_ne_bytes());
+    }
+}
+
+// Cubemaps can only be created from bytes, so other types are converted to
+// 8 bits, clamping anything outside of 0 to 1. The image's colour has to be
+// linear, and is stored as sRGB if srgb is set to keep the dark shades' precision.
+pub(crate) fn to_8_bit(image: &GlImage, srgb: bool) -> GlImage {
+    let channels = channel_count(image);
+    let srgb = srgb && channels >= 3;
+
+    let bytes = to_f32_channels(image)
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let value = value.clamp(0.0, 1.0);
+            let value = if srgb && i % channels < 3 {
+                if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 }
+            } else {
+                value
+            };
+
+            (value * 255.0).round() as u8
+        })
+        .collect();
+
+    let internal_format = match (image.data_format, srgb) {
+        (gl::RED, _) => gl::R8,
+        (gl::RG, _) => gl::RG8,
+        (gl::RGB, true) => gl::SRGB8,
+        (gl::RGB, false) => gl::RGB8,
+        (_, true) => gl::SRGB8_ALPHA8,
+        (_, false) => gl::RGBA8
+    };
+
+    GlImage {
+        bytes,
+        internal_format,
+        data_format: image.data_format,
+        width: image.width,
+        height: image.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(bytes: Vec<u8>) -> GlImage {
+        GlImage {
+            width: (bytes.len() / 4) as i32,
+            height: 1,
+            bytes,
+            internal_format: gl::RGBA8,
+            data_format: gl::RGBA
+        }
+    }
+
+    #[test]
+    fn tint_multiplies_linear_colour_and_alpha() {
+        let image = rgba(vec![255, 255, 255, 255, 255, 0, 128, 200]);
+        let tinted = tint_image(&image, [0.5, 1.0, 0.0, 0.5], false);
+
+        assert_eq!(tinted.bytes, vec![128, 255, 0, 128, 128, 0, 0, 100]);
+        assert_eq!(tinted.internal_format, gl::RGBA8);
+    }
+
+    #[test]
+    fn tint_converts_srgb_to_linear() {
+        let image = rgba(vec![255, 255, 255, 255]);
+        let tinted = tint_image(&image, [0.5, 0.5, 0.5, 1.0], true);
+
+        // Half of linear white is about 188 in sRGB
+        assert_eq!(tinted.bytes, vec![188, 188, 188, 255]);
+    }
+
+    #[test]
+    fn specular_from_metallic_roughness() {
+        // Smooth metal, rough metal, smooth dielectric
+        let image = GlImage {
+            width: 3,
+            height: 1,
+            bytes: vec![0, 0, 255, 0, 255, 255, 0, 0, 0],
+            internal_format: gl::RGB8,
+            data_format: gl::RGB
+        };
+        let specular = metallic_roughness_to_specular(&image, 1.0, 1.0);
+
+        assert_eq!((specular.internal_format, specular.data_format), (gl::R8, gl::RED));
+        assert_eq!(specular.bytes, vec![255, 0, 10]);
+
+        // Factors scale the map
+        let specular = metallic_roughness_to_specular(&image, 0.0, 1.0);
+        assert_eq!(specular.bytes, vec![10, 0, 10]);
+    }
+
+    #[test]
+    fn cut_alpha_makes_opaque_or_masks() {
+        let pixels = vec![10, 20, 30, 0, 40, 50, 60, 127, 70, 80, 90, 128];
+
+        assert_eq!(cut_alpha(rgba(pixels.clone()), None).bytes, vec![10, 20, 30, 255, 40, 50, 60, 255, 70, 80, 90, 255]);
+        assert_eq!(cut_alpha(rgba(pixels), Some(0.5)).bytes, vec![10, 20, 30, 0, 40, 50, 60, 0, 70, 80, 90, 255]);
+    }
+
+    #[test]
+    fn cut_alpha_ignores_images_without_alpha() {
+        let image = GlImage {
+            width: 1,
+            height: 1,
+            bytes: vec![10, 20, 30],
+            internal_format: gl::RGB8,
+            data_format: gl::RGB
+        };
+
+        assert_eq!(cut_alpha(image, Some(0.5)).bytes, vec![10, 20, 30]);
+    }
+}
@@ -6,6 +6,25 @@ pub mod game_object;
 pub mod camera;
 pub mod render_pipelines;
 pub mod scenes;
+pub mod draw_recorder;
+pub mod capture;
+pub mod compositor;
+pub mod glyph_atlas;
+pub mod sdf;
+pub mod asset_loader;
+pub mod resource_cache;
+pub mod hot_reload;
+pub mod vfs;
+pub mod texture_options;
+pub mod image_data;
+pub mod skybox;
+pub mod mesh_processing;
+pub mod shader_preprocessor;
+pub mod animated_texture;
+pub mod video;
+pub mod handles;
+pub mod asset_manifest;
+pub mod null_gl;
 
 // TODO: remember to tighten these restrictions up in a way that makes sense
 pub use widgets::*;
@@ -16,6 +35,25 @@ pub use game_object::*;
 pub use camera::*;
 pub use render_pipelines::*;
 pub use scenes::*;
+pub use draw_recorder::*;
+pub use capture::*;
+pub use compositor::*;
+pub use glyph_atlas::*;
+pub use sdf::*;
+pub use asset_loader::*;
+pub use resource_cache::*;
+pub use hot_reload::*;
+pub use vfs::*;
+pub use texture_options::*;
+pub use image_data::*;
+pub use skybox::*;
+pub use mesh_processing::*;
+pub use shader_preprocessor::*;
+pub use animated_texture::*;
+pub use video::*;
+pub use handles::*;
+pub use asset_manifest::*;
+pub use null_gl::*;
 
 // Lib level uses
 use std::cell::RefCell;
@@ -30,8 +68,7 @@ use silver_gl::ModelTrait;
 // Type aliases to make the multi-GL usage easier
 type Model = RefCell<Box<dyn ModelTrait>>;
 
-// TODO: add default shaders for all widgets that are available anywhere,
-// TODO: and use them to create default for every widget (and remove their new funcs)
+// TODO: use the built-in shaders to create defaults for every widget (and remove their new funcs)
 
 // TODO: remember to set all properties to sensible pubs!
 
@@ -60,7 +97,6 @@ type Model = RefCell<Box<dyn ModelTrait>>;
 
 // TODO: Text animation
 // TODO: Use animated for intro/outro of individual text, allow for each font to have its own as well
-// TODO: To that end, support animated textures
 
 // TODO: transform_anchor
 // TODO: Select from an enum of the four cardinal directions plus corners and centre, with an offset of relative
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use cgmath::{Vector3, vec3, InnerSpace, Zero};
+use silver_gl::Vertex;
+
+// Smooth normals for meshes that don't have any, each face adds its normal
+// weighted by its area to its corners. Vertices at the same position share a
+// normal, since vertices are split wherever their UVs differ.
+pub(crate) fn generate_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let position_key = |position: Vector3<f32>| (position.x.to_bits(), position.y.to_bits(), position.z.to_bits());
+    let mut normals: HashMap<(u32, u32, u32), Vector3<f32>> = HashMap::new();
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| vertices[index as usize].position);
+        // Not normalized, so the length is twice the area
+        let normal = (b - a).cross(c - a);
+
+        for position in [a, b, c] {
+            *normals.entry(position_key(position)).or_insert(Vector3::zero()) += normal;
+        }
+    }
+
+    for vertex in vertices.iter_mut() {
+        let normal = normals.get(&position_key(vertex.position)).copied().unwrap_or(Vector3::zero());
+
+        // Vertices not used by any face, or only by degenerate ones, point up
+        vertex.normal = if normal.magnitude2() > 0.0 { normal.normalize() } else { vec3(0.0, 1.0, 0.0) };
+    }
+}
+
+// Tangents and bitangents for normal mapping, following the same conventions as
+// MikkTSpace: per face tangents are accumulated per vertex, made orthogonal to
+// the normal, and the bitangent is rebuilt from the normal and tangent with the
+// handedness of the UVs, so mirrored UVs still work.
+pub(crate) fn generate_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut tangents = vec![Vector3::zero(); vertices.len()];
+    let mut bitangents = vec![Vector3::zero(); vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|index| &vertices[index as usize]);
+
+        let (edge_1, edge_2) = (b.position - a.position, c.position - a.position);
+        let (delta_uv_1, delta_uv_2) = (b.tex_coord - a.tex_coord, c.tex_coord - a.tex_coord);
+
+        let determinant = delta_uv_1.x * delta_uv_2.y - delta_uv_2.x * delta_uv_1.y;
+        if determinant.abs() < f32::EPSILON {
+            continue; // UVs don't cover any area, so there is no direction to use
+        }
+
+        let tangent = (edge_1 * delta_uv_2.y - edge_2 * delta_uv_1.y) / determinant;
+        let bitangent = (edge_2 * delta_uv_1.x - edge_1 * delta_uv_2.x) / determinant;
+
+        for index in triangle {
+            tangents[*index as usize] += tangent;
+            bitangents[*index as usize] += bitangent;
+        }
+    }
+
+    for (i, vertex) in vertices.iter_mut().enumerate() {
+        let normal = vertex.normal;
+
+        // Gram-Schmidt, falling back to any direction perpendicular to the normal
+        let mut tangent = tangents[i] - normal * normal.dot(tangents[i]);
+        if tangent.magnitude2() < f32::EPSILON {
+            let axis = if normal.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+            tangent = axis - normal * normal.dot(axis);
+        }
+        let tangent = tangent.normalize();
+
+        let handedness = if normal.cross(tangent).dot(bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+
+        vertex.tangent = tangent;
+        vertex.bitangent = normal.cross(tangent) * handedness;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Vector2, vec2};
+    use super::*;
+
+    fn vertex(position: Vector3<f32>, tex_coord: Vector2<f32>) -> Vertex {
+        Vertex { position, tex_coord, ..Vertex::default() }
+    }
+
+    // Quad facing +Z, with the UVs given for each corner
+    fn quad(tex_coords: [Vector2<f32>; 4]) -> (Vec<Vertex>, Vec<u32>) {
+        let positions = [vec3(0.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0), vec3(1.0, 1.0, 0.0), vec3(0.0, 1.0, 0.0)];
+        let vertices = positions.iter().zip(tex_coords).map(|(position, tex_coord)| vertex(*position, tex_coord)).collect();
+
+        (vertices, vec![0, 1, 2, 0, 2, 3])
+    }
+
+    fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!((a - b).magnitude() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn flat_quad_normals_face_out() {
+        let (mut vertices, indices) = quad([vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(1.0, 1.0), vec2(0.0, 1.0)]);
+        generate_normals(&mut vertices, &indices);
+
+        for vertex in vertices.iter() {
+            assert_close(vertex.normal, vec3(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn mirrored_uvs_flip_bitangent() {
+        let (mut vertices, indices) = quad([vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(1.0, 1.0), vec2(0.0, 1.0)]);
+        generate_normals(&mut vertices, &indices);
+        generate_tangents(&mut vertices, &indices);
+        assert_close(vertices[0].tangent, vec3(1.0, 0.0, 0.0));
+        assert_close(vertices[0].bitangent, vec3(0.0, 1.0, 0.0));
+
+        // U runs the other way, so the tangent follows it and the bitangent stays up
+        let (mut vertices, indices) = quad([vec2(1.0, 0.0), vec2(0.0, 0.0), vec2(0.0, 1.0), vec2(1.0, 1.0)]);
+        generate_normals(&mut vertices, &indices);
+        generate_tangents(&mut vertices, &indices);
+        assert_close(vertices[0].tangent, vec3(-1.0, 0.0, 0.0));
+        assert_close(vertices[0].bitangent, vec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn degenerate_uvs_still_give_a_basis() {
+        let (mut vertices, indices) = quad([vec2(0.0, 0.0); 4]);
+        generate_normals(&mut vertices, &indices);
+        generate_tangents(&mut vertices, &indices);
+
+        for vertex in vertices.iter() {
+            assert!((vertex.tangent.magnitude() - 1.0).abs() < 1e-5);
+            assert!(vertex.tangent.dot(vertex.normal).abs() < 1e-5);
+            assert!((vertex.bitangent.magnitude() - 1.0).abs() < 1e-5);
+        }
+    }
+}
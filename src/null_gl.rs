@@ -0,0 +1,532 @@
+use std::{ffi::c_void, collections::BTreeMap, sync::{Mutex, Once, atomic::{AtomicBool, Ordering}}};
+use silver_gl::gl;
+
+// Stands in for a GL context when running with GraphicsLibrary::None, so textures,
+// models, shaders and framebuffers can all be created without a window or GPU.
+// Objects are only ids with what is needed to answer queries about them kept on
+// the CPU, e.g. a texture's size or a buffer's contents. Compiling and linking
+// always succeeds, and drawing does nothing, see DrawRecorder for what would
+// have been drawn.
+struct NullGl {
+    next_id: u32,
+    textures: BTreeMap<u32, NullTexture>,
+    bound_textures: BTreeMap<u32, u32>, // By target
+    buffers: BTreeMap<u32, Vec<u8>>,
+    bound_buffers: BTreeMap<u32, u32> // By target
+}
+
+#[derive(Default)]
+struct NullTexture {
+    target: u32,
+    width: i32,
+    height: i32
+}
+
+static STATE: Mutex<NullGl> = Mutex::new(NullGl {
+    next_id: 1,
+    textures: BTreeMap::new(),
+    bound_textures: BTreeMap::new(),
+    buffers: BTreeMap::new(),
+    bound_buffers: BTreeMap::new()
+});
+
+// Loads the null backend in place of GL functions for the whole process, unless
+// a real context has already been loaded. Called by CSEngine when headless, but
+// needs calling before creating widgets or scenes without one, e.g. in tests.
+pub fn load_null_gl() {
+    static LOAD: Once = Once::new();
+
+    LOAD.call_once(|| {
+        if !gl::CreateProgram::is_loaded() {
+            gl::load_with(null_proc_address);
+            LOADED.store(true, Ordering::Release);
+        }
+    });
+}
+
+static LOADED: AtomicBool = AtomicBool::new(false);
+
+// Whether GL calls go to the null backend, where nothing is ever drawn
+pub fn is_null_gl_loaded() -> bool {
+    LOADED.load(Ordering::Acquire)
+}
+
+fn state() -> std::sync::MutexGuard<'static, NullGl> {
+    // Nothing here can leave the state half changed, so a panic elsewhere doesn't matter
+    STATE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn null_proc_address(name: &str) -> *const c_void {
+    match name {
+        "glGetError" => get_error as *const c_void,
+        "glCreateShader" => create_shader as *const c_void,
+        "glCreateProgram" => create_program as *const c_void,
+        "glGenTextures" | "glGenBuffers" | "glGenVertexArrays" | "glGenFramebuffers" | "glGenRenderbuffers"
+            | "glGenSamplers" | "glGenQueries" | "glCreateBuffers" | "glCreateVertexArrays"
+            | "glCreateFramebuffers" | "glCreateRenderbuffers" | "glCreateSamplers" => gen_objects as *const c_void,
+        "glCreateTextures" => create_textures as *const c_void,
+        "glCreateQueries" => create_queries as *const c_void,
+        "glBindTexture" => bind_texture as *const c_void,
+        "glTexImage2D" => tex_image_2d as *const c_void,
+        "glTexStorage2D" => tex_storage_2d as *const c_void,
+        "glTextureStorage2D" => texture_storage_2d as *const c_void,
+        "glGetTextureParameteriv" => get_texture_parameter as *const c_void,
+        "glGetTextureLevelParameteriv" => get_texture_level_parameter as *const c_void,
+        "glBindBuffer" => bind_buffer as *const c_void,
+        "glBindBufferBase" => bind_buffer_base as *const c_void,
+        "glBufferData" | "glBufferStorage" => buffer_data as *const c_void,
+        "glNamedBufferData" | "glNamedBufferStorage" => named_buffer_data as *const c_void,
+        "glBufferSubData" => buffer_sub_data as *const c_void,
+        "glNamedBufferSubData" => named_buffer_sub_data as *const c_void,
+        "glMapBuffer" => map_buffer as *const c_void,
+        "glMapNamedBuffer" => map_named_buffer as *const c_void,
+        "glMapBufferRange" => map_buffer_range as *const c_void,
+        "glMapNamedBufferRange" => map_named_buffer_range as *const c_void,
+        "glUnmapBuffer" | "glUnmapNamedBuffer" => unmap_buffer as *const c_void,
+        "glGetShaderiv" | "glGetProgramiv" => get_object_parameter as *const c_void,
+        "glGetShaderInfoLog" | "glGetProgramInfoLog" => get_info_log as *const c_void,
+        "glGetAttachedShaders" => get_attached_shaders as *const c_void,
+        "glGetIntegerv" => get_integer as *const c_void,
+        "glGetFloatv" => get_float as *const c_void,
+        "glGetNamedFramebufferAttachmentParameteriv" => get_framebuffer_attachment_parameter as *const c_void,
+        "glCheckFramebufferStatus" => check_framebuffer_status as *const c_void,
+        "glCheckNamedFramebufferStatus" => check_named_framebuffer_status as *const c_void,
+        "glGetUniformLocation" | "glGetAttribLocation" => get_location as *const c_void,
+        "glGetUniformBlockIndex" => get_uniform_block_index as *const c_void,
+        "glGetString" => get_string as *const c_void,
+        "glGetStringi" => get_string_i as *const c_void,
+        "glGetTextureHandleARB" => get_texture_handle as *const c_void,
+        "glFenceSync" => fence_sync as *const c_void,
+        "glClientWaitSync" => client_wait_sync as *const c_void,
+        "glIsTexture" | "glIsBuffer" | "glIsVertexArray" | "glIsFramebuffer" | "glIsRenderbuffer" | "glIsSampler"
+            | "glIsQuery" | "glIsShader" | "glIsProgram" | "glIsEnabled" => is_object as *const c_void,
+        // Anything else that isn't a no-op below is null, so calling it panics rather
+        // than quietly doing the wrong thing, e.g. reading pixels back
+        _ => no_op_address(name)
+    }
+}
+
+// Entry points that only take input, e.g. binding, uploading and drawing. Each
+// has its own stub with GL's signature, as calling a function through a
+// different signature is undefined behaviour.
+macro_rules! no_ops {
+    ($($gl_name:literal => fn $name:ident($($arg:ty),*);)*) => {
+        $(
+            #[allow(clippy::too_many_arguments)]
+            extern "system" fn $name($(_: $arg),*) {}
+        )*
+
+        fn no_op_address(name: &str) -> *const c_void {
+            match name {
+                $($gl_name => $name as *const c_void,)*
+                _ => std::ptr::null()
+            }
+        }
+    };
+}
+
+no_ops! {
+    // State
+    "glEnable" => fn enable(u32);
+    "glDisable" => fn disable(u32);
+    "glViewport" => fn viewport(i32, i32, i32, i32);
+    "glScissor" => fn scissor(i32, i32, i32, i32);
+    "glClear" => fn clear(u32);
+    "glClearColor" => fn clear_color(f32, f32, f32, f32);
+    "glClearDepth" => fn clear_depth(f64);
+    "glDepthFunc" => fn depth_func(u32);
+    "glDepthMask" => fn depth_mask(u8);
+    "glBlendFunc" => fn blend_func(u32, u32);
+    "glBlendFuncSeparate" => fn blend_func_separate(u32, u32, u32, u32);
+    "glBlendEquation" => fn blend_equation(u32);
+    "glCullFace" => fn cull_face(u32);
+    "glFrontFace" => fn front_face(u32);
+    "glPolygonMode" => fn polygon_mode(u32, u32);
+    "glLineWidth" => fn line_width(f32);
+    "glPixelStorei" => fn pixel_store(u32, i32);
+    "glFinish" => fn finish();
+    "glFlush" => fn flush();
+    "glDebugMessageCallback" => fn debug_message_callback(gl::types::GLDEBUGPROC, *const c_void);
+    "glDeleteSync" => fn delete_sync(gl::types::GLsync);
+
+    // Shaders
+    "glShaderSource" => fn shader_source(u32, i32, *const *const i8, *const i32);
+    "glCompileShader" => fn compile_shader(u32);
+    "glAttachShader" => fn attach_shader(u32, u32);
+    "glDetachShader" => fn detach_shader(u32, u32);
+    "glLinkProgram" => fn link_program(u32);
+    "glValidateProgram" => fn validate_program(u32);
+    "glUseProgram" => fn use_program(u32);
+    "glDeleteShader" => fn delete_shader(u32);
+    "glDeleteProgram" => fn delete_program(u32);
+    "glUniformBlockBinding" => fn uniform_block_binding(u32, u32, u32);
+
+    // Uniforms
+    "glUniform1i" => fn uniform_1i(i32, i32);
+    "glUniform1ui" => fn uniform_1ui(i32, u32);
+    "glUniform1f" => fn uniform_1f(i32, f32);
+    "glUniform2f" => fn uniform_2f(i32, f32, f32);
+    "glUniform3f" => fn uniform_3f(i32, f32, f32, f32);
+    "glUniform4f" => fn uniform_4f(i32, f32, f32, f32, f32);
+    "glUniform1iv" => fn uniform_1iv(i32, i32, *const i32);
+    "glUniform2iv" => fn uniform_2iv(i32, i32, *const i32);
+    "glUniform3iv" => fn uniform_3iv(i32, i32, *const i32);
+    "glUniform4iv" => fn uniform_4iv(i32, i32, *const i32);
+    "glUniform1fv" => fn uniform_1fv(i32, i32, *const f32);
+    "glUniform2fv" => fn uniform_2fv(i32, i32, *const f32);
+    "glUniform3fv" => fn uniform_3fv(i32, i32, *const f32);
+    "glUniform4fv" => fn uniform_4fv(i32, i32, *const f32);
+    "glUniformMatrix2fv" => fn uniform_matrix_2fv(i32, i32, u8, *const f32);
+    "glUniformMatrix3fv" => fn uniform_matrix_3fv(i32, i32, u8, *const f32);
+    "glUniformMatrix4fv" => fn uniform_matrix_4fv(i32, i32, u8, *const f32);
+    "glProgramUniform1i" => fn program_uniform_1i(u32, i32, i32);
+    "glProgramUniform1f" => fn program_uniform_1f(u32, i32, f32);
+    "glProgramUniform1iv" => fn program_uniform_1iv(u32, i32, i32, *const i32);
+    "glProgramUniform2iv" => fn program_uniform_2iv(u32, i32, i32, *const i32);
+    "glProgramUniform3iv" => fn program_uniform_3iv(u32, i32, i32, *const i32);
+    "glProgramUniform4iv" => fn program_uniform_4iv(u32, i32, i32, *const i32);
+    "glProgramUniform1fv" => fn program_uniform_1fv(u32, i32, i32, *const f32);
+    "glProgramUniform2fv" => fn program_uniform_2fv(u32, i32, i32, *const f32);
+    "glProgramUniform3fv" => fn program_uniform_3fv(u32, i32, i32, *const f32);
+    "glProgramUniform4fv" => fn program_uniform_4fv(u32, i32, i32, *const f32);
+    "glProgramUniformMatrix2fv" => fn program_uniform_matrix_2fv(u32, i32, i32, u8, *const f32);
+    "glProgramUniformMatrix3fv" => fn program_uniform_matrix_3fv(u32, i32, i32, u8, *const f32);
+    "glProgramUniformMatrix4fv" => fn program_uniform_matrix_4fv(u32, i32, i32, u8, *const f32);
+    "glUniformHandleui64ARB" => fn uniform_handle(i32, u64);
+    "glProgramUniformHandleui64ARB" => fn program_uniform_handle(u32, i32, u64);
+    "glMakeTextureHandleResidentARB" => fn make_texture_handle_resident(u64);
+    "glMakeTextureHandleNonResidentARB" => fn make_texture_handle_non_resident(u64);
+
+    // Textures
+    "glActiveTexture" => fn active_texture(u32);
+    "glBindTextureUnit" => fn bind_texture_unit(u32, u32);
+    "glTexSubImage2D" => fn tex_sub_image_2d(u32, i32, i32, i32, i32, i32, u32, u32, *const c_void);
+    "glTextureSubImage2D" => fn texture_sub_image_2d(u32, i32, i32, i32, i32, i32, u32, u32, *const c_void);
+    "glTextureSubImage3D" => fn texture_sub_image_3d(u32, i32, i32, i32, i32, i32, i32, i32, u32, u32, *const c_void);
+    "glTexParameteri" => fn tex_parameter_i(u32, u32, i32);
+    "glTexParameterf" => fn tex_parameter_f(u32, u32, f32);
+    "glTextureParameteri" => fn texture_parameter_i(u32, u32, i32);
+    "glTextureParameterf" => fn texture_parameter_f(u32, u32, f32);
+    "glTextureParameterfv" => fn texture_parameter_fv(u32, u32, *const f32);
+    "glGenerateMipmap" => fn generate_mipmap(u32);
+    "glGenerateTextureMipmap" => fn generate_texture_mipmap(u32);
+    "glDeleteTextures" => fn delete_textures(i32, *const u32);
+
+    // Buffers and vertex arrays
+    "glDeleteBuffers" => fn delete_buffers(i32, *const u32);
+    "glBindBufferRange" => fn bind_buffer_range(u32, u32, u32, isize, isize);
+    "glBindVertexArray" => fn bind_vertex_array(u32);
+    "glDeleteVertexArrays" => fn delete_vertex_arrays(i32, *const u32);
+    "glVertexAttribPointer" => fn vertex_attrib_pointer(u32, i32, u32, u8, i32, *const c_void);
+    "glVertexAttribIPointer" => fn vertex_attrib_i_pointer(u32, i32, u32, i32, *const c_void);
+    "glEnableVertexAttribArray" => fn enable_vertex_attrib_array(u32);
+    "glVertexAttribDivisor" => fn vertex_attrib_divisor(u32, u32);
+    "glEnableVertexArrayAttrib" => fn enable_vertex_array_attrib(u32, u32);
+    "glVertexArrayVertexBuffer" => fn vertex_array_vertex_buffer(u32, u32, u32, isize, i32);
+    "glVertexArrayElementBuffer" => fn vertex_array_element_buffer(u32, u32);
+    "glVertexArrayAttribFormat" => fn vertex_array_attrib_format(u32, u32, i32, u32, u8, u32);
+    "glVertexArrayAttribIFormat" => fn vertex_array_attrib_i_format(u32, u32, i32, u32, u32);
+    "glVertexArrayAttribBinding" => fn vertex_array_attrib_binding(u32, u32, u32);
+    "glVertexArrayBindingDivisor" => fn vertex_array_binding_divisor(u32, u32, u32);
+
+    // Drawing
+    "glDrawArrays" => fn draw_arrays(u32, i32, i32);
+    "glDrawArraysInstanced" => fn draw_arrays_instanced(u32, i32, i32, i32);
+    "glDrawElements" => fn draw_elements(u32, i32, u32, *const c_void);
+    "glDrawElementsInstanced" => fn draw_elements_instanced(u32, i32, u32, *const c_void, i32);
+    "glMultiDrawElementsIndirect" => fn multi_draw_elements_indirect(u32, u32, *const c_void, i32, i32);
+
+    // Framebuffers
+    "glBindFramebuffer" => fn bind_framebuffer(u32, u32);
+    "glDeleteFramebuffers" => fn delete_framebuffers(i32, *const u32);
+    "glFramebufferTexture" => fn framebuffer_texture(u32, u32, u32, i32);
+    "glFramebufferTexture2D" => fn framebuffer_texture_2d(u32, u32, u32, u32, i32);
+    "glNamedFramebufferTexture" => fn named_framebuffer_texture(u32, u32, u32, i32);
+    "glDrawBuffers" => fn draw_buffers(i32, *const u32);
+    "glNamedFramebufferDrawBuffers" => fn named_framebuffer_draw_buffers(u32, i32, *const u32);
+    "glNamedFramebufferDrawBuffer" => fn named_framebuffer_draw_buffer(u32, u32);
+    "glNamedFramebufferReadBuffer" => fn named_framebuffer_read_buffer(u32, u32);
+    "glBindRenderbuffer" => fn bind_renderbuffer(u32, u32);
+    "glRenderbufferStorage" => fn renderbuffer_storage(u32, u32, i32, i32);
+    "glNamedRenderbufferStorage" => fn named_renderbuffer_storage(u32, u32, i32, i32);
+    "glFramebufferRenderbuffer" => fn framebuffer_renderbuffer(u32, u32, u32, u32);
+    "glNamedFramebufferRenderbuffer" => fn named_framebuffer_renderbuffer(u32, u32, u32, u32);
+    "glDeleteRenderbuffers" => fn delete_renderbuffers(i32, *const u32);
+    "glBlitFramebuffer" => fn blit_framebuffer(i32, i32, i32, i32, i32, i32, i32, i32, u32, u32);
+    "glBlitNamedFramebuffer" => fn blit_named_framebuffer(u32, u32, i32, i32, i32, i32, i32, i32, i32, i32, u32, u32);
+    "glClearNamedFramebufferfv" => fn clear_named_framebuffer_fv(u32, u32, i32, *const f32);
+}
+
+fn new_id() -> u32 {
+    let mut state = state();
+    let id = state.next_id;
+    state.next_id += 1;
+
+    id
+}
+
+unsafe fn write_ids(count: i32, ids: *mut u32, mut create: impl FnMut(u32)) {
+    for i in 0..count.max(0) as usize {
+        let id = new_id();
+        create(id);
+        *ids.add(i) = id;
+    }
+}
+
+fn bound_buffer(target: u32) -> u32 {
+    state().bound_buffers.get(&target).copied().unwrap_or(0)
+}
+
+fn bound_texture(target: u32) -> u32 {
+    // Faces are uploaded to the cubemap bound to TEXTURE_CUBE_MAP
+    let target = if (gl::TEXTURE_CUBE_MAP_POSITIVE_X..=gl::TEXTURE_CUBE_MAP_NEGATIVE_Z).contains(&target) {
+        gl::TEXTURE_CUBE_MAP
+    } else {
+        target
+    };
+
+    state().bound_textures.get(&target).copied().unwrap_or(0)
+}
+
+fn set_texture_size(texture: u32, width: i32, height: i32) {
+    let mut state = state();
+    let texture = state.textures.entry(texture).or_default();
+    texture.width = width;
+    texture.height = height;
+}
+
+fn set_buffer_size(buffer: u32, size: isize, data: *const c_void) {
+    let mut bytes = vec![0; size.max(0) as usize];
+    if !data.is_null() {
+        unsafe { std::ptr::copy_nonoverlapping(data as *const u8, bytes.as_mut_ptr(), bytes.len()) };
+    }
+
+    state().buffers.insert(buffer, bytes);
+}
+
+fn write_buffer(buffer: u32, offset: isize, size: isize, data: *const c_void) {
+    let mut state = state();
+
+    if let Some(bytes) = state.buffers.get_mut(&buffer) {
+        let start = (offset.max(0) as usize).min(bytes.len());
+        let end = (start + size.max(0) as usize).min(bytes.len());
+
+        if !data.is_null() {
+            unsafe { std::ptr::copy_nonoverlapping(data as *const u8, bytes[start..end].as_mut_ptr(), end - start) };
+        }
+    }
+}
+
+// Pointers stay valid until the buffer is given new storage
+fn map_range(buffer: u32, offset: isize) -> *mut c_void {
+    let mut state = state();
+
+    match state.buffers.get_mut(&buffer) {
+        Some(bytes) if (offset.max(0) as usize) < bytes.len() => unsafe { bytes.as_mut_ptr().add(offset.max(0) as usize) as *mut c_void },
+        _ => std::ptr::null_mut()
+    }
+}
+
+extern "system" fn is_object(_object: u32) -> u8 { gl::TRUE }
+extern "system" fn get_error() -> u32 { gl::NO_ERROR }
+extern "system" fn create_shader(_kind: u32) -> u32 { new_id() }
+extern "system" fn create_program() -> u32 { new_id() }
+
+extern "system" fn gen_objects(count: i32, ids: *mut u32) {
+    unsafe { write_ids(count, ids, |_| ()) };
+}
+
+extern "system" fn create_queries(_target: u32, count: i32, ids: *mut u32) {
+    unsafe { write_ids(count, ids, |_| ()) };
+}
+
+extern "system" fn create_textures(target: u32, count: i32, ids: *mut u32) {
+    unsafe { write_ids(count, ids, |id| { state().textures.insert(id, NullTexture { target, ..Default::default() }); }) };
+}
+
+extern "system" fn bind_texture(target: u32, texture: u32) {
+    let mut state = state();
+    state.bound_textures.insert(target, texture);
+
+    // Textures from glGenTextures get their target when first bound
+    let entry = state.textures.entry(texture).or_default();
+    if entry.target == 0 {
+        entry.target = target;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+extern "system" fn tex_image_2d(
+    target: u32,
+    level: i32,
+    _internal_format: i32,
+    width: i32,
+    height: i32,
+    _border: i32,
+    _format: u32,
+    _kind: u32,
+    _pixels: *const c_void
+) {
+    if level == 0 {
+        set_texture_size(bound_texture(target), width, height);
+    }
+}
+
+extern "system" fn tex_storage_2d(target: u32, _levels: i32, _internal_format: u32, width: i32, height: i32) {
+    set_texture_size(bound_texture(target), width, height);
+}
+
+extern "system" fn texture_storage_2d(texture: u32, _levels: i32, _internal_format: u32, width: i32, height: i32) {
+    set_texture_size(texture, width, height);
+}
+
+extern "system" fn get_texture_parameter(texture: u32, name: u32, value: *mut i32) {
+    let state = state();
+    let target = state.textures.get(&texture).map_or(0, |texture| texture.target);
+
+    unsafe { *value = if name == gl::TEXTURE_TARGET { target as i32 } else { 0 } };
+}
+
+extern "system" fn get_texture_level_parameter(texture: u32, level: i32, name: u32, value: *mut i32) {
+    let state = state();
+    let (width, height) = state.textures.get(&texture).map_or((0, 0), |texture| (texture.width, texture.height));
+    let shift = level.clamp(0, 31);
+
+    unsafe {
+        *value = match name {
+            gl::TEXTURE_WIDTH => (width >> shift).max(1),
+            gl::TEXTURE_HEIGHT => (height >> shift).max(1),
+            _ => 0
+        };
+    }
+}
+
+extern "system" fn bind_buffer(target: u32, buffer: u32) {
+    state().bound_buffers.insert(target, buffer);
+}
+
+extern "system" fn bind_buffer_base(target: u32, _index: u32, buffer: u32) {
+    state().bound_buffers.insert(target, buffer);
+}
+
+extern "system" fn buffer_data(target: u32, size: isize, data: *const c_void, _usage: u32) {
+    set_buffer_size(bound_buffer(target), size, data);
+}
+
+extern "system" fn named_buffer_data(buffer: u32, size: isize, data: *const c_void, _usage: u32) {
+    set_buffer_size(buffer, size, data);
+}
+
+extern "system" fn buffer_sub_data(target: u32, offset: isize, size: isize, data: *const c_void) {
+    write_buffer(bound_buffer(target), offset, size, data);
+}
+
+extern "system" fn named_buffer_sub_data(buffer: u32, offset: isize, size: isize, data: *const c_void) {
+    write_buffer(buffer, offset, size, data);
+}
+
+extern "system" fn map_buffer(target: u32, _access: u32) -> *mut c_void { map_range(bound_buffer(target), 0) }
+extern "system" fn map_named_buffer(buffer: u32, _access: u32) -> *mut c_void { map_range(buffer, 0) }
+
+extern "system" fn map_buffer_range(target: u32, offset: isize, _length: isize, _access: u32) -> *mut c_void {
+    map_range(bound_buffer(target), offset)
+}
+
+extern "system" fn map_named_buffer_range(buffer: u32, offset: isize, _length: isize, _access: u32) -> *mut c_void {
+    map_range(buffer, offset)
+}
+
+extern "system" fn unmap_buffer(_buffer: u32) -> u8 { gl::TRUE }
+
+// Shaders always compile and programs always link, with empty logs
+extern "system" fn get_object_parameter(_object: u32, name: u32, value: *mut i32) {
+    unsafe {
+        *value = match name {
+            gl::COMPILE_STATUS | gl::LINK_STATUS | gl::VALIDATE_STATUS => gl::TRUE as i32,
+            _ => 0
+        };
+    }
+}
+
+extern "system" fn get_info_log(_object: u32, size: i32, length: *mut i32, log: *mut i8) {
+    unsafe {
+        if !length.is_null() {
+            *length = 0;
+        }
+        if size > 0 && !log.is_null() {
+            *log = 0;
+        }
+    }
+}
+
+extern "system" fn get_attached_shaders(_program: u32, _max_count: i32, count: *mut i32, _shaders: *mut u32) {
+    if !count.is_null() {
+        unsafe { *count = 0 };
+    }
+}
+
+// Limits are those of a typical desktop GPU, everything else is 0
+extern "system" fn get_integer(name: u32, value: *mut i32) {
+    unsafe {
+        *value = match name {
+            gl::MAX_TEXTURE_SIZE | gl::MAX_RENDERBUFFER_SIZE => 16384,
+            gl::MAX_TEXTURE_IMAGE_UNITS | gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS => 32,
+            gl::MAX_UNIFORM_BUFFER_BINDINGS => 36,
+            gl::MAX_COLOR_ATTACHMENTS | gl::MAX_DRAW_BUFFERS => 8,
+            gl::MAX_SAMPLES => 4,
+            _ => 0
+        };
+    }
+}
+
+extern "system" fn get_float(name: u32, value: *mut f32) {
+    unsafe { *value = if name == gl::MAX_TEXTURE_MAX_ANISOTROPY { 16.0 } else { 0.0 } };
+}
+
+extern "system" fn get_framebuffer_attachment_parameter(_framebuffer: u32, _attachment: u32, _name: u32, value: *mut i32) {
+    unsafe { *value = 0 };
+}
+
+extern "system" fn check_framebuffer_status(_target: u32) -> u32 { gl::FRAMEBUFFER_COMPLETE }
+extern "system" fn check_named_framebuffer_status(_framebuffer: u32, _target: u32) -> u32 { gl::FRAMEBUFFER_COMPLETE }
+extern "system" fn get_location(_program: u32, _name: *const i8) -> i32 { 0 }
+extern "system" fn get_uniform_block_index(_program: u32, _name: *const i8) -> u32 { 0 }
+
+const NULL_STRING: &[u8] = b"CSEngine null GL\0";
+
+extern "system" fn get_string(_name: u32) -> *const u8 { NULL_STRING.as_ptr() }
+extern "system" fn get_string_i(_name: u32, _index: u32) -> *const u8 { NULL_STRING.as_ptr() }
+extern "system" fn get_texture_handle(texture: u32) -> u64 { texture as u64 }
+
+// Nothing runs on a GPU, so every fence has already been reached
+extern "system" fn fence_sync(_condition: u32, _flags: u32) -> gl::types::GLsync { NULL_STRING.as_ptr() as gl::types::GLsync }
+extern "system" fn client_wait_sync(_sync: gl::types::GLsync, _flags: u32, _timeout: u64) -> u32 { gl::ALREADY_SIGNALED }
+
+#[cfg(test)]
+mod tests {
+    use crate::{EngineError, ResourceManager, ShaderPathBundle, capture_framebuffer};
+    use super::*;
+
+    #[test]
+    fn unknown_entry_points_are_null() {
+        assert!(!null_proc_address("glDrawElements").is_null());
+        assert!(!null_proc_address("glCreateTextures").is_null());
+        assert!(null_proc_address("glReadPixels").is_null());
+        assert!(null_proc_address("glGetTextureImage").is_null());
+        assert!(null_proc_address("glDispatchCompute").is_null());
+    }
+
+    #[test]
+    fn captures_fail_when_headless() {
+        load_null_gl();
+
+        assert!(matches!(capture_framebuffer(4, 4), Err(EngineError::CaptureError(_))));
+    }
+
+    #[test]
+    fn bare_resource_manager_needs_graphics_library() {
+        let mut resource_manager = ResourceManager::new();
+
+        assert!(matches!(resource_manager.load_shader_program(ShaderPathBundle::composite()), Err(EngineError::ResourceManagerError(_))));
+    }
+}
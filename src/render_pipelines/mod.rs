@@ -1,5 +1,7 @@
 mod view_3d_render_pipeline;
 mod widget_2d_render_pipeline;
+mod transparent_pass;
 
 pub use view_3d_render_pipeline::*;
-pub use widget_2d_render_pipeline::*;
\ No newline at end of file
+pub use widget_2d_render_pipeline::*;
+pub use transparent_pass::*;
\ No newline at end of file
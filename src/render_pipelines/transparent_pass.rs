@@ -0,0 +1,159 @@
+use std::{rc::Rc, collections::HashMap};
+use cgmath::{Matrix4, Point3, MetricSpace, SquareMatrix};
+use silver_gl::{ShaderProgram, RenderPipeline, gl};
+use crate::{EngineError, GameObject, Model, ResourceManager};
+
+// Forward pass for the meshes split off from models for being transparent, drawn
+// with blending over the render pipeline's lighting output after it is done, as
+// alpha can't be stored in a G-buffer. Depth is tested against the depth texture
+// after the pipeline's outputs in get_link(), so opaque geometry in front still
+// hides them, but isn't written. Pipelines without one are drawn over untested.
+pub(crate) struct TransparentPass {
+    framebuffer: u32
+}
+
+impl TransparentPass {
+    pub fn new(resource_manager: &ResourceManager) -> Result<Self, EngineError> {
+        resource_manager.prepare_graphics_library("framebuffers")?;
+
+        let mut framebuffer = 0;
+        unsafe { gl::CreateFramebuffers(1, &mut framebuffer) };
+
+        Ok(Self { framebuffer })
+    }
+
+    // Models are paired with their transparent part. Parts are drawn with their
+    // model's instances in the world object's tree, sorted back-to-front by writing
+    // their transforms into the part in that order, and as parts can only be drawn
+    // whole they are sorted by their farthest instance. Models with no instances in
+    // the tree keep the transforms their part was created with, same as the model.
+    pub fn draw(
+        &self,
+        render_pipeline: &dyn RenderPipeline,
+        shader_program: &ShaderProgram,
+        models: &[(Rc<Model>, Rc<Model>)],
+        world_obj: &GameObject,
+        camera_position: Point3<f32>
+    ) -> Result<(), EngineError> {
+        if models.is_empty() {
+            return Ok(());
+        }
+
+        let output = render_pipeline.get_link()?;
+        let (width, height) = render_pipeline.get_height();
+        let depth = output.get(2).map_or(0, |texture| texture.get_id());
+
+        unsafe {
+            gl::NamedFramebufferTexture(self.framebuffer, gl::COLOR_ATTACHMENT0, output[0].get_id(), 0);
+            gl::NamedFramebufferTexture(self.framebuffer, gl::DEPTH_ATTACHMENT, depth, 0);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::Viewport(0, 0, width, height);
+            if depth != 0 {
+                gl::Enable(gl::DEPTH_TEST);
+            } else {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+            gl::DepthMask(gl::FALSE);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+
+        shader_program.use_program();
+
+        let mut result = Ok(());
+        for (part, instances) in sort_instances(models, world_obj, camera_position) {
+            if let Some(instances) = instances {
+                set_instances(&part, &instances);
+            }
+
+            if let Err(err) = part.borrow().draw(shader_program) {
+                result = Err(err.into());
+                break;
+            }
+        }
+
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::DEPTH_TEST);
+        }
+
+        result
+    }
+
+    // Number of instances of each part in the order they would be drawn, for recording
+    pub fn sorted_instance_counts(models: &[(Rc<Model>, Rc<Model>)], world_obj: &GameObject, camera_position: Point3<f32>) -> Vec<usize> {
+        sort_instances(models, world_obj, camera_position)
+            .iter()
+            .map(|(part, instances)| match instances {
+                Some(instances) => instances.len(),
+                None => part.borrow().get_transform_array().len()
+            })
+            .collect()
+    }
+}
+
+impl Drop for TransparentPass {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteFramebuffers(1, &self.framebuffer) };
+    }
+}
+
+// The instance's transform and its distance from the camera
+type Instance = (Matrix4<f32>, f32);
+
+// Parts are paired with None when their model has no instances in the tree
+fn sort_instances(models: &[(Rc<Model>, Rc<Model>)], world_obj: &GameObject, camera_position: Point3<f32>) -> Vec<(Rc<Model>, Option<Vec<Instance>>)> {
+    let mut drawables = Vec::new();
+    world_obj.collect_drawables(Matrix4::identity(), &mut drawables);
+
+    let mut instances: HashMap<*const Model, Vec<Instance>> = HashMap::new();
+    for (drawable, _, transform) in drawables {
+        let position = Point3::new(transform.w.x, transform.w.y, transform.w.z);
+        instances
+            .entry(Rc::as_ptr(&drawable))
+            .or_default()
+            .push((transform, position.distance2(camera_position)));
+    }
+
+    let mut sorted: Vec<(Rc<Model>, Option<Vec<Instance>>)> = models
+        .iter()
+        .map(|(model, part)| {
+            let mut instances = instances.get(&Rc::as_ptr(model)).cloned();
+            if let Some(instances) = &mut instances {
+                instances.sort_by(|a, b| b.1.total_cmp(&a.1));
+            }
+
+            (Rc::clone(part), instances)
+        })
+        .collect();
+
+    sorted.sort_by(|a, b| {
+        let farthest = |instances: &Option<Vec<Instance>>| instances
+            .as_ref()
+            .and_then(|instances| instances.first())
+            .map_or(0.0, |instance| instance.1);
+        farthest(&b.1).total_cmp(&farthest(&a.1))
+    });
+
+    sorted
+}
+
+// Parts are only drawn here, so their transforms are replaced with the instances
+fn set_instances(part: &Model, instances: &[Instance]) {
+    let mut part = part.borrow_mut();
+    let transforms = part.get_transform_array_mut();
+
+    while transforms.len() > instances.len() {
+        let last = transforms.len() - 1;
+        transforms.remove(last);
+    }
+
+    for (i, (transform, _)) in instances.iter().enumerate() {
+        if i < transforms.len() {
+            transforms.set_data_index(*transform, i);
+        } else {
+            transforms.push(*transform);
+        }
+    }
+}
@@ -1,10 +1,14 @@
 use std::rc::Rc;
-use silver_gl::{Framebuffer, ShaderProgram, GlError, RenderPipeline, Texture, gl};
+use silver_gl::{Framebuffer, ShaderProgram, GlError, RenderPipeline, Texture, GlImage, gl};
 
 use crate::{ResourceManager, EngineError, ShaderPathBundle};
 
+// Number of gaussian blur passes for bloom, alternating horizontal and vertical
+const BLUR_AMOUNT: usize = 10;
+
 pub struct View3DRenderPipeline {
     deffered_fb: Framebuffer,
+    depth_texture: Rc<Texture>,
     lighting_pass_fb: Framebuffer,
     lighting_pass_shader_program: Rc<ShaderProgram>,
     ping_framebuffer: Framebuffer,
@@ -27,13 +31,14 @@ impl View3DRenderPipeline {
         let lighting_pass_shader_program = resource_manager.load_shader_program(lighting_pass_shader_paths)?;
         let blur_shader_program = resource_manager.load_shader_program(blur_shader_paths)?;
 
-        // Create g_buffer for deferred shading
+        // Create g_buffer for deferred shading, with a depth texture that can be shared
         let deffered_fb = Framebuffer::new(
             width,
             height,
             3,
-            true
+            false
         )?;
+        let depth_texture = attach_depth_texture(&deffered_fb, width, height);
 
         // Create framebuffer with second colour attachment for lighting calculations and bloom
         let mut lighting_pass_fb = Framebuffer::new(
@@ -64,6 +69,7 @@ impl View3DRenderPipeline {
         Ok(
             Self {
                 deffered_fb,
+                depth_texture,
                 lighting_pass_fb,
                 lighting_pass_shader_program,
                 ping_framebuffer,
@@ -76,6 +82,17 @@ impl View3DRenderPipeline {
             }
         )
     }
+
+    // Uses the built-in lighting and blur shaders
+    pub fn with_default_shaders(resource_manager: &mut ResourceManager, width: i32, height: i32) -> Result<Self, EngineError> {
+        View3DRenderPipeline::new(
+            resource_manager,
+            width,
+            height,
+            ShaderPathBundle::lighting(),
+            ShaderPathBundle::blur()
+        )
+    }
 }
 
 impl RenderPipeline for View3DRenderPipeline {
@@ -96,14 +113,13 @@ impl RenderPipeline for View3DRenderPipeline {
         // Draw gaussian blur
         // ------------------
 
-        let amount = 10;
         self.ping_pong_hoz = true;
         self.ping_pong_first_iter = true;
 
         self.blur_shader_program.use_program();
 
         // TODO: Could there be a way to do this in one FB? Would cut down on links
-        for _ in 0..amount {
+        for _ in 0..BLUR_AMOUNT {
             self.blur_shader_program.set_bool("horizontal", self.ping_pong_hoz)?;
 
             if self.ping_pong_first_iter {
@@ -137,6 +153,7 @@ impl RenderPipeline for View3DRenderPipeline {
 
         // Resize FBs
         self.deffered_fb.set_size(width, height)?;
+        self.depth_texture = attach_depth_texture(&self.deffered_fb, width, height);
         self.lighting_pass_fb.set_size(width, height)?;
         self.ping_framebuffer.set_size(width, height)?;
         self.pong_framebuffer.set_size(width, height)?;
@@ -144,9 +161,18 @@ impl RenderPipeline for View3DRenderPipeline {
         Ok(())
     }
 
+    // Lighting output followed by the bloom blur, which is in whichever
+    // framebuffer drew the last blur pass, then the G-buffer's depth for passes
+    // drawn over the output, like View3DScene's transparent pass
     fn get_link(&self) -> Result<Vec<Rc<Texture>>, GlError> {
+        let blur_framebuffer = if BLUR_AMOUNT % 2 == 0 {
+            &self.pong_framebuffer
+        } else {
+            &self.ping_framebuffer
+        };
+
         Ok(
-            vec![self.lighting_pass_fb.get(0).unwrap(), self.ping_framebuffer.get(0).unwrap()]
+            vec![self.lighting_pass_fb.get(0).unwrap(), blur_framebuffer.get(0).unwrap(), Rc::clone(&self.depth_texture)]
         )
     }
 
@@ -167,4 +193,23 @@ impl RenderPipeline for View3DRenderPipeline {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// Replaces the G-buffer's depth attachment with a new depth texture of its size
+fn attach_depth_texture(deffered_fb: &Framebuffer, width: i32, height: i32) -> Rc<Texture> {
+    let texture = Rc::new(Texture::from_2d(GlImage {
+        bytes: vec![0; width.max(1) as usize * height.max(1) as usize * 4],
+        internal_format: gl::DEPTH_COMPONENT32F,
+        data_format: gl::DEPTH_COMPONENT,
+        width,
+        height
+    }));
+
+    unsafe {
+        deffered_fb.bind();
+        gl::FramebufferTexture(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, texture.get_id(), 0);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    }
+
+    texture
+}
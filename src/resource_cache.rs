@@ -0,0 +1,179 @@
+use std::collections::{HashMap, HashSet};
+use crate::ShaderPathBundle;
+
+// Identifies an entry in one of the resource manager's stores
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AssetKey {
+    Model(String),
+    Texture(String),
+    AnimatedTexture(String),
+    ShaderProgram(ShaderPathBundle)
+}
+
+struct CacheInfo {
+    size: usize, // Estimated bytes used on the GPU
+    last_used: u64
+}
+
+// Bookkeeping for the resource manager's stores, keeping track of roughly how
+// much memory each asset uses, when it was last used, and which groups use it
+pub(crate) struct ResourceCache {
+    info: HashMap<AssetKey, CacheInfo>,
+    groups: HashMap<String, HashSet<AssetKey>>,
+    current_group: Option<String>,
+    clock: u64,
+    memory_budget: Option<usize>
+}
+
+impl ResourceCache {
+    pub fn new() -> Self {
+        Self {
+            info: HashMap::new(),
+            groups: HashMap::new(),
+            current_group: None,
+            clock: 0,
+            memory_budget: None
+        }
+    }
+
+    pub fn insert(&mut self, key: AssetKey, size: usize) {
+        self.info.insert(key.clone(), CacheInfo { size, last_used: 0 });
+        self.touch(&key);
+    }
+
+    // Marks the asset as used, which also adds it to the current group
+    pub fn touch(&mut self, key: &AssetKey) {
+        self.clock += 1;
+
+        if let Some(info) = self.info.get_mut(key) {
+            info.last_used = self.clock;
+        }
+
+        if let Some(group) = self.current_group.clone() {
+            self.add_to_group(&group, key.clone());
+        }
+    }
+
+    pub fn remove(&mut self, key: &AssetKey) {
+        self.info.remove(key);
+
+        for keys in self.groups.values_mut() {
+            keys.remove(key);
+        }
+    }
+
+    pub fn add_to_group(&mut self, group: &str, key: AssetKey) {
+        self.groups.entry(group.to_owned()).or_default().insert(key);
+    }
+
+    pub fn get_current_group(&self) -> Option<&str> { self.current_group.as_deref() }
+    pub fn set_current_group(&mut self, group: Option<&str>) { self.current_group = group.map(str::to_owned) }
+
+    // Removes the group, returning the assets no other group uses
+    pub fn take_group(&mut self, group: &str) -> Vec<AssetKey> {
+        let keys = self.groups.remove(group).unwrap_or_default();
+
+        keys
+            .into_iter()
+            .filter(|key| !self.groups.values().any(|keys| keys.contains(key)))
+            .collect()
+    }
+
+    pub fn get_memory_budget(&self) -> Option<usize> { self.memory_budget }
+    pub fn set_memory_budget(&mut self, memory_budget: Option<usize>) { self.memory_budget = memory_budget }
+
+    pub fn get_memory_usage(&self) -> usize {
+        self.info.values().map(|info| info.size).sum()
+    }
+
+    // Least recently used first
+    pub fn lru_keys(&self) -> Vec<AssetKey> {
+        let mut keys: Vec<(&AssetKey, &CacheInfo)> = self.info.iter().collect();
+        keys.sort_by_key(|(_, info)| info.last_used);
+
+        keys.into_iter().map(|(key, _)| key.clone()).collect()
+    }
+
+    pub fn get_size(&self, key: &AssetKey) -> usize {
+        self.info.get(key).map_or(0, |info| info.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use silver_gl::{GlImage, Texture, gl};
+    use crate::{CSEngine, CSEngineConfig, GraphicsLibrary, ResourceManager, TextureOptions};
+    use super::*;
+
+    fn texture_key(path: &str) -> AssetKey {
+        AssetKey::Texture(String::from(path))
+    }
+
+    fn upload(resource_manager: &mut ResourceManager, path: &str) -> Rc<Texture> {
+        let image = GlImage { bytes: vec![255; 4], internal_format: gl::RGBA8, data_format: gl::RGBA, width: 1, height: 1 };
+        resource_manager.upload_texture_2d(path, image, TextureOptions::default()).unwrap()
+    }
+
+    fn headless_engine() -> CSEngine {
+        CSEngine::new(CSEngineConfig {
+            gl: GraphicsLibrary::None,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn lru_keys_are_least_recently_used_first() {
+        let mut cache = ResourceCache::new();
+        cache.insert(texture_key("a.png"), 1);
+        cache.insert(texture_key("b.png"), 1);
+        cache.insert(texture_key("c.png"), 1);
+        cache.touch(&texture_key("a.png"));
+
+        assert_eq!(cache.lru_keys(), vec![texture_key("b.png"), texture_key("c.png"), texture_key("a.png")]);
+    }
+
+    #[test]
+    fn take_group_keeps_shared_keys() {
+        let mut cache = ResourceCache::new();
+        cache.add_to_group("chapter_1", texture_key("a.png"));
+        cache.add_to_group("chapter_1", texture_key("b.png"));
+        cache.add_to_group("chapter_2", texture_key("b.png"));
+
+        assert_eq!(cache.take_group("chapter_1"), vec![texture_key("a.png")]);
+        assert_eq!(cache.take_group("chapter_2"), vec![texture_key("b.png")]);
+        assert!(cache.take_group("chapter_1").is_empty());
+    }
+
+    #[test]
+    fn memory_budget_skips_assets_in_use() {
+        let mut engine = headless_engine();
+        let resource_manager = &mut engine.resource_manager;
+
+        let held = upload(resource_manager, "a.png");
+        upload(resource_manager, "b.png");
+        assert_eq!(resource_manager.get_memory_usage(), 8);
+
+        // a.png is the least recently used, but is still held
+        resource_manager.set_memory_budget(Some(4));
+        assert_eq!(resource_manager.get_memory_usage(), 4);
+        let cached = resource_manager.load_texture_2d("a.png", TextureOptions::default()).unwrap();
+        assert!(Rc::ptr_eq(&held, &cached));
+    }
+
+    #[test]
+    fn unload_group_keeps_assets_in_use() {
+        let mut engine = headless_engine();
+        let resource_manager = &mut engine.resource_manager;
+
+        resource_manager.begin_group("chapter_1");
+        let held = upload(resource_manager, "a.png");
+        upload(resource_manager, "b.png");
+        resource_manager.end_group();
+
+        resource_manager.unload_group("chapter_1");
+        assert_eq!(resource_manager.get_memory_usage(), 4);
+        let cached = resource_manager.load_texture_2d("a.png", TextureOptions::default()).unwrap();
+        assert!(Rc::ptr_eq(&held, &cached));
+    }
+}
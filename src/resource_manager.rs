@@ -1,20 +1,48 @@
-use std::{rc::Rc, collections::HashMap, path::Path, cell::RefCell, fs::File, io::Read};
-use cgmath::{vec3, vec2, Matrix4, Vector3, Vector2};
+use std::{rc::{Rc, Weak}, collections::{HashMap, HashSet}, path::Path, cell::RefCell, io::{Cursor, BufReader}, fs::File};
+use cgmath::{vec3, vec2, Matrix4, Matrix3, Vector3, Vector2, Vector4, Quaternion, SquareMatrix, InnerSpace, Matrix};
 use silver_gl::{Texture, Vertex, Mesh, GlImage, Skybox, ShaderProgram, ShaderCodeBundle, gl, ModelTrait, BindlessModel, ModelCreateTrait, MultiBindModel};
 use image::DynamicImage::*;
-use crate::{EngineError, Model, GraphicsLibrary};
+use serde::{Serialize, Deserialize};
+use crate::{EngineError, Model, GraphicsLibrary, GlyphAtlas, GameObject, generate_sdf, AssetLoader, AssetRequest, AssetData, Pending, LoadState, PendingJob, PendingSlot, LoadResult, ResourceCache, AssetKey, FileWatcher, reupload_texture, relink_shader_program, Vfs, join_path, TextureOptions, TextureFilter, TextureWrap, image_has_transparency, create_texture_2d, pixel_type, to_8_bit, tint_image, cut_alpha, metallic_roughness_to_specular, SkyboxSource, create_cubemap, equirectangular_to_faces, gradient_faces, generate_normals, generate_tangents, ShaderPreprocessor, map_shader_log, AnimatedTexture, SpriteSheet, decode_animation, split_sprite_sheet, VideoStream, VideoSource, parse_avi, Handle, HandleResource, HandleTable, AssetRef, Font, AssetManifest};
 
 pub struct ResourceManager {
     pub gl: GraphicsLibrary,
+    // Set by a headless CSEngine, which has loaded the null backend
+    pub(crate) headless: bool,
+    vfs: Vfs,
     model_store: HashMap<String, Rc<Model>>,
     texture_store: HashMap<String, Rc<Texture>>,
+    texture_sources: HashMap<String, (String, TextureOptions)>, // Path and options of textures loaded from their own file
+    transparent_textures: HashSet<u32>, // Ids of textures with any alpha below 1
+    // Meshes needing blending split off from a model, keyed by its address. The weak
+    // reference keeps the address from being reused while the entry is there.
+    transparent_parts: HashMap<*const Model, (Weak<Model>, Rc<Model>)>,
+    animated_texture_store: HashMap<String, Rc<AnimatedTexture>>,
     shader_store: HashMap<ShaderPathBundle, Rc<ShaderProgram>>,
+    shader_files: HashMap<ShaderPathBundle, Vec<String>>, // Every file each program was built from, including includes
     glyph_store: HashMap<GlyphMetaDeta, Rc<GlyphData>>,
+    glyph_atlas: GlyphAtlas,
+    glyph_generation: u64, // Increased whenever loaded glyphs are dropped or replaced
     face_store: HashMap<String, freetype::Face>,
     face_library: freetype::Library,
+    face_paths: HashMap<String, String>,
+    font_render_modes: HashMap<String, FontRenderMode>,
+    content_scale: f32,
+    asset_loader: Option<AssetLoader>, // Started when the first asset is queued
+    pending_jobs: HashMap<u64, PendingJob>,
+    group_errors: HashMap<String, Vec<String>>,
+    loading_progress: (usize, usize),
+    loading_feedback: String,
+    resource_cache: ResourceCache,
+    file_watcher: Option<FileWatcher>, // Only set while hot reloading
+    reload_errors: Vec<EngineError>, // Kept until taken by the game
+    texture_handles: HandleTable<Texture>,
+    model_handles: HandleTable<Model>,
+    shader_handles: HandleTable<ShaderProgram>,
+    font_handles: HandleTable<Font>,
+    manifest: AssetManifest,
 }
 
-// TODO: Make all loading async so that it is faster :)
 // TODO: Time to beat: ~15 seconds on laptop
 // TODO: Learn to use Rayon, Tokio
 impl ResourceManager {
@@ -22,20 +50,77 @@ impl ResourceManager {
         Self {
             model_store: Default::default(),
             texture_store: Default::default(),
+            texture_sources: Default::default(),
+            transparent_textures: Default::default(),
+            transparent_parts: Default::default(),
+            animated_texture_store: Default::default(),
             shader_store: Default::default(),
+            shader_files: Default::default(),
             glyph_store: Default::default(),
+            glyph_atlas: GlyphAtlas::new(4),
+            glyph_generation: 0,
             face_store: Default::default(),
             face_library: freetype::Library::init().unwrap(),
-            gl: GraphicsLibrary::None
+            face_paths: Default::default(),
+            font_render_modes: Default::default(),
+            content_scale: 1.0,
+            asset_loader: None,
+            pending_jobs: Default::default(),
+            group_errors: Default::default(),
+            loading_progress: (0, 0),
+            loading_feedback: String::new(),
+            resource_cache: ResourceCache::new(),
+            file_watcher: None,
+            reload_errors: Vec::new(),
+            texture_handles: Default::default(),
+            model_handles: Default::default(),
+            shader_handles: Default::default(),
+            font_handles: Default::default(),
+            manifest: Default::default(),
+            vfs: ResourceManager::default_vfs(),
+            gl: GraphicsLibrary::None,
+            headless: false
         }
     }
 
-    fn _load_model(&mut self, path: &str) -> Result<Rc<Model>, EngineError> {
-        let path = Path::new(path);
-        let obj_path = path.to_str().unwrap().to_owned();
-        let directory = path.parent().unwrap_or_else(|| Path::new("")).to_str().unwrap();
-        
-        let obj = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS);
+    // Paths are read through the virtual file system, which starts with the working
+    // directory mounted so paths work the same as plain file paths
+    fn default_vfs() -> Vfs {
+        let mut vfs = Vfs::new();
+        vfs.mount_dir("");
+
+        vfs
+    }
+
+    // With no graphics library selected, GPU objects can only be created when the
+    // engine was configured headless, as it loads the null backend. Loading it here
+    // would replace the GL functions for the whole process, so a manager used
+    // before its engine's context exists would leave every later draw a no-op.
+    pub(crate) fn prepare_graphics_library(&self, kind: &str) -> Result<(), EngineError> {
+        match self.gl {
+            GraphicsLibrary::None if !self.headless => {
+                Err(EngineError::ResourceManagerError(format!("Trying to load {} without selected graphics library!", kind)))
+            },
+            _ => Ok(())
+        }
+    }
+
+    pub fn get_vfs(&self) -> &Vfs { &self.vfs }
+    pub fn get_vfs_mut(&mut self) -> &mut Vfs { &mut self.vfs }
+
+    // Reading and decoding doesn't touch the GPU, so can be done on any thread.
+    // Missing normals are generated, missing UVs are all 0, and tangents are
+    // generated for meshes with a normal map.
+    pub(crate) fn read_obj(vfs: &Vfs, path: &str) -> Result<ObjData, EngineError> {
+        let directory = Path::new(path).parent().unwrap_or_else(|| Path::new("")).to_str().unwrap().to_owned();
+        let bytes = vfs.read(path)?;
+
+        let obj = tobj::load_obj_buf(&mut BufReader::new(Cursor::new(bytes)), &tobj::GPU_LOAD_OPTIONS, |mtl_path| {
+            let mtl_path = join_path(&directory, mtl_path.to_str().unwrap_or_default());
+            let bytes = vfs.read(&mtl_path).map_err(|_| tobj::LoadError::OpenFileFailed)?;
+
+            tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(bytes)))
+        });
 
         let (models, materials) = obj?;
         let materials = materials?;
@@ -43,91 +128,559 @@ impl ResourceManager {
         // Combine all meshes for optimized rendering
         let mut vertices: Vec<Vertex> = Vec::new();
         let mut indices: Vec<u32> = Vec::new();
-        let mut meshes: Vec<Mesh> = Vec::new();
+        let mut meshes: Vec<ObjMesh> = Vec::new();
 
         for model in models {
             let mesh = &model.mesh;
+            let material = check_obj_mesh(path, &model.name, mesh, &materials)?;
             let num_vertices = mesh.positions.len() / 3;
 
-            // Push to model vertices
+            let mut mesh_vertices: Vec<Vertex> = Vec::with_capacity(num_vertices);
             let (p, n, t) = (&mesh.positions, &mesh.normals, &mesh.texcoords);
             for i in 0..num_vertices {
-                vertices.push(
+                mesh_vertices.push(
                     Vertex {
                         position: vec3(p[i*3], p[i*3+1], p[i*3+2]),
-                        normal: vec3(n[i*3], n[i*3+1], n[i*3+2]),
-                        tex_coord: vec2(t[i*2], t[i*2+1]),
+                        normal: if n.is_empty() { vec3(0.0, 0.0, 0.0) } else { vec3(n[i*3], n[i*3+1], n[i*3+2]) },
+                        tex_coord: if t.is_empty() { vec2(0.0, 0.0) } else { vec2(t[i*2], t[i*2+1]) },
                         ..Vertex::default()
                     }
                 )
             }
 
-            // Push to model indices while adjusting for offset
+            if n.is_empty() {
+                generate_normals(&mut mesh_vertices, &mesh.indices);
+            }
+            if material.as_ref().map_or(false, |material| !material.normal_texture.is_empty()) {
+                generate_tangents(&mut mesh_vertices, &mesh.indices);
+            }
+
+            // Indices are relative to the mesh, so offset them by the vertices before it
+            let base_vertex = vertices.len() as u32;
             let offset = indices.len();
-            let mut adjusted_indices: Vec<u32> = mesh.indices.iter().map(|index| { index + offset as u32 }).collect();
-            indices.append(&mut adjusted_indices);
+            indices.extend(mesh.indices.iter().map(|index| index + base_vertex));
+            vertices.append(&mut mesh_vertices);
 
+            meshes.push(
+                ObjMesh {
+                    offset,
+                    count: mesh.indices.len() as i32,
+                    material
+                }
+            );
+        }
+
+        Ok(ObjData { directory, vertices, indices, meshes })
+    }
+
+    // Textures already decoded on another thread are taken from images, anything
+    // else is loaded as normal
+    pub(crate) fn upload_obj(
+        &mut self,
+        path: &str,
+        data: ObjData,
+        mut images: HashMap<String, GlImage>
+    ) -> Result<Rc<Model>, EngineError> {
+        let mut meshes: Vec<Mesh> = Vec::new();
+
+        for mesh in data.meshes {
             // Process material
-            let mut gl_mesh = Mesh::new(offset, mesh.indices.len() as i32);
-            if let Some(material_id) = mesh.material_id {
-                let material = &materials[material_id];
+            let mut gl_mesh = Mesh::new(mesh.offset, mesh.count);
+            if let Some(material) = &mesh.material {
+                // Only colour is sRGB, the other maps hold data. Images are taken by
+                // the first map using them, the same image as another map is loaded again.
+                let mut load_texture = |name: &str, options: TextureOptions| {
+                    let path = join_path(&data.directory, name);
+                    match images.remove(&path) {
+                        Some(image) if !self.texture_store.contains_key(&options.cache_key(&path)) => {
+                            self.upload_texture_2d(&path, image, options)
+                        },
+                        _ => self.load_texture_2d(&path, options),
+                    }
+                };
 
                 // Diffuse map
                 if !material.diffuse_texture.is_empty() {
-                    let texture = self.load_texture_2d(&format!("{}/{}", directory, &material.diffuse_texture))?;
-                    gl_mesh.diffuse_textures.push(texture);
+                    gl_mesh.diffuse_textures.push(load_texture(&material.diffuse_texture, TextureOptions::default())?);
                 } else {
                     gl_mesh.diffuse = vec3(material.diffuse[0], material.diffuse[1], material.diffuse[2]);
                 }
                 // Specular map
                 if !material.specular_texture.is_empty() {
-                    let texture = self.load_texture_2d(&format!("{}/{}", directory, &material.specular_texture))?;
-                    gl_mesh.specular_textures.push(texture);
+                    gl_mesh.specular_textures.push(load_texture(&material.specular_texture, TextureOptions::linear())?);
                 } else {
                     gl_mesh.specular = vec3(material.specular[0], material.specular[1], material.specular[2]);
                 }
                 // Normal map
                 if !material.normal_texture.is_empty() {
-                    let texture = self.load_texture_2d(&format!("{}/{}", directory, &material.normal_texture))?;
-                    gl_mesh.normal_textures.push(texture);
+                    gl_mesh.normal_textures.push(load_texture(&material.normal_texture, TextureOptions::linear())?);
                 }
                 // Shininess map
                 if !material.shininess_texture.is_empty() {
-                    let texture = self.load_texture_2d(&format!("{}/{}", directory, &material.shininess_texture))?;
-                    gl_mesh.shininess_textures.push(texture);
+                    gl_mesh.shininess_textures.push(load_texture(&material.shininess_texture, TextureOptions::linear())?);
                 } else {
                     gl_mesh.shininess = material.shininess; // Get all-mesh shininess if there is no map present
                 }
             }
+            self.add_fallback_maps(&mut gl_mesh, 1.0)?;
 
             meshes.push(gl_mesh);
         }
 
-        let model: Box<dyn ModelTrait> = self.create_model(vertices, indices, vec![], meshes)?;
-        let model: Rc<Model> = Rc::new(RefCell::new(model));
-        self.model_store.insert(obj_path, Rc::clone(&model));
+        let meshes = meshes
+            .into_iter()
+            .map(|mesh| {
+                let transparent = self.is_mesh_transparent(&mesh);
+                (mesh, transparent)
+            })
+            .collect();
+        let (model, transparent_part, size) = self.create_split_model(data.vertices, data.indices, meshes)?;
+        self.store_model(path, &model, size, transparent_part);
 
         Ok(model)
     }
 
+    fn _load_model(&mut self, path: &str) -> Result<Rc<Model>, EngineError> {
+        let data = ResourceManager::read_obj(&self.vfs, path)?;
+
+        self.upload_obj(path, data, HashMap::new())
+    }
+
     pub fn load_model(&mut self, path: &str) -> Result<Rc<Model>, EngineError> {
         if let Some(model) = self.model_store.get(path) {
-            Ok(Rc::clone(model))
+            let model = Rc::clone(model);
+            self.resource_cache.touch(&AssetKey::Model(path.to_owned()));
+
+            Ok(model)
         } else {
             self._load_model(path)
         }
     }
 
-    fn load_image(path: &str) -> Result<GlImage, EngineError> {
-        let img = image::io::Reader::open(path)?.decode()?;
+    // Every primitive is combined into one model the same way as OBJ meshes, with
+    // transforms from the default scene's nodes applied to the vertices. Materials
+    // are converted from metallic-roughness: base colour becomes diffuse, the
+    // metallic-roughness texture is used as the specular map, and roughness is
+    // converted to a shininess. Animations and skins are ignored.
+    // Files on disk can reference other files, but files in archives need to be
+    // GLB or have their buffers and images embedded
+    pub(crate) fn import_gltf(
+        vfs: &Vfs,
+        path: &str
+    ) -> Result<(gltf::Document, Vec<gltf::buffer::Data>, Vec<gltf::image::Data>), EngineError> {
+        match vfs.real_path(path) {
+            Some(real_path) => Ok(gltf::import(real_path)?),
+            None => Ok(gltf::import_slice(vfs.read(path)?)?)
+        }
+    }
+
+    fn _load_gltf(&mut self, path: &str) -> Result<Rc<Model>, EngineError> {
+        let (document, buffers, images) = ResourceManager::import_gltf(&self.vfs, path)?;
+
+        self.upload_gltf(path, document, buffers, images)
+    }
+
+    pub(crate) fn upload_gltf(
+        &mut self,
+        path: &str,
+        document: gltf::Document,
+        buffers: Vec<gltf::buffer::Data>,
+        images: Vec<gltf::image::Data>
+    ) -> Result<Rc<Model>, EngineError> {
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or(EngineError::ResourceManagerError(format!("glTF file '{}' has no scenes!", path)))?;
+
+        let mut primitives = Vec::new();
+        for node in scene.nodes() {
+            collect_gltf_primitives(node, Matrix4::identity(), &mut primitives);
+        }
+
+        let (model, transparent_part, size) = self.create_gltf_model(path, &buffers, &images, primitives)?;
+        self.store_model(path, &model, size, transparent_part);
+
+        Ok(model)
+    }
+
+    pub fn load_gltf(&mut self, path: &str) -> Result<Rc<Model>, EngineError> {
+        if let Some(model) = self.model_store.get(path) {
+            let model = Rc::clone(model);
+            self.resource_cache.touch(&AssetKey::Model(path.to_owned()));
+
+            Ok(model)
+        } else {
+            self._load_gltf(path)
+        }
+    }
+
+    // Creates a game object for every node in the default scene, with the same
+    // hierarchy and transforms. Each glTF mesh is its own model, cached as
+    // "path#mesh{index}", so game objects using the same mesh share a model.
+    // Game objects only have a uniform scale, so the node's x scale is used.
+    pub fn load_gltf_game_object(&mut self, path: &str) -> Result<GameObject, EngineError> {
+        let document = gltf::Gltf::from_slice(&self.vfs.read(path)?)?.document;
+
+        // Held until the game objects use them, so the memory budget can't evict them first
+        let mut loaded_models = Vec::new();
+
+        let missing_meshes = document
+            .meshes()
+            .any(|mesh| !self.model_store.contains_key(&gltf_mesh_key(path, mesh.index())));
+        if missing_meshes {
+            let (document, buffers, images) = ResourceManager::import_gltf(&self.vfs, path)?;
+
+            for mesh in document.meshes() {
+                let key = gltf_mesh_key(path, mesh.index());
+                if self.model_store.contains_key(&key) {
+                    continue;
+                }
+
+                let primitives = mesh.primitives().map(|primitive| (primitive, Matrix4::identity())).collect();
+                let (model, transparent_part, size) = self.create_gltf_model(path, &buffers, &images, primitives)?;
+                self.store_model(&key, &model, size, transparent_part);
+                loaded_models.push(model);
+            }
+        }
+
+        for mesh in document.meshes() {
+            self.resource_cache.touch(&AssetKey::Model(gltf_mesh_key(path, mesh.index())));
+        }
+
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or(EngineError::ResourceManagerError(format!("glTF file '{}' has no scenes!", path)))?;
+
+        let mut root = GameObject::default();
+        for node in scene.nodes() {
+            root.children.push(self.create_gltf_game_object(path, node));
+        }
+
+        Ok(root)
+    }
+
+    fn create_gltf_game_object(&self, path: &str, node: gltf::Node) -> GameObject {
+        let (translation, rotation, scale) = node.transform().decomposed();
+
+        let mut game_object = GameObject::default();
+        game_object.position = Vector3::from(translation);
+        game_object.rotation = Quaternion::new(rotation[3], rotation[0], rotation[1], rotation[2]);
+        game_object.scale = scale[0];
+
+        if let Some(mesh) = node.mesh() {
+            let model = self.model_store.get(&gltf_mesh_key(path, mesh.index())).map(Rc::clone);
+            game_object.set_drawable(model);
+        }
+
+        for child in node.children() {
+            game_object.children.push(self.create_gltf_game_object(path, child));
+        }
+
+        game_object
+    }
+
+    fn create_gltf_model(
+        &mut self,
+        path: &str,
+        buffers: &[gltf::buffer::Data],
+        images: &[gltf::image::Data],
+        primitives: Vec<(gltf::Primitive, Matrix4<f32>)>
+    ) -> Result<(Rc<Model>, Option<Rc<Model>>, usize), EngineError> {
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        let mut meshes: Vec<(Mesh, bool)> = Vec::new();
+
+        for (primitive, transform) in primitives {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                return Err(EngineError::ResourceManagerError(format!("glTF file '{}' has a primitive that isn't made of triangles!", path)));
+            }
+
+            let reader = primitive.reader(|buffer| Some(&*buffers[buffer.index()]));
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or(EngineError::ResourceManagerError(format!("glTF file '{}' has a primitive without positions!", path)))?
+                .collect();
+            let normals: Vec<[f32; 3]> = reader.read_normals().map_or(Vec::new(), |normals| normals.collect());
+            let tangents: Vec<[f32; 4]> = reader.read_tangents().map_or(Vec::new(), |tangents| tangents.collect());
+            let tex_coords: Vec<[f32; 2]> = reader.read_tex_coords(0).map_or(Vec::new(), |tex_coords| tex_coords.into_f32().collect());
+            let primitive_indices: Vec<u32> = match reader.read_indices() {
+                Some(read_indices) => read_indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect() // Primitives without indices draw their vertices in order
+            };
+
+            if primitive_indices.len() % 3 != 0 {
+                return Err(EngineError::ResourceManagerError(format!("glTF file '{}' has a primitive that isn't made of triangles!", path)));
+            }
+            if let Some(index) = primitive_indices.iter().find(|index| **index as usize >= positions.len()) {
+                return Err(
+                    EngineError::ResourceManagerError(
+                        format!("glTF file '{}' has a primitive that uses vertex {} but only has {} vertices!", path, index, positions.len())
+                    )
+                );
+            }
+
+            // Normals need the inverse transpose so non-uniform scales don't skew them
+            let normal_matrix = Matrix3::new(
+                transform.x.x, transform.x.y, transform.x.z,
+                transform.y.x, transform.y.y, transform.y.z,
+                transform.z.x, transform.z.y, transform.z.z
+            ).invert().unwrap_or(Matrix3::identity()).transpose();
+
+            let mut primitive_vertices: Vec<Vertex> = Vec::with_capacity(positions.len());
+            for (i, position) in positions.iter().enumerate() {
+                let position = transform * Vector3::from(*position).extend(1.0);
+                let normal = normals.get(i).map_or(vec3(0.0, 0.0, 0.0), |normal| {
+                    (normal_matrix * Vector3::from(*normal)).normalize()
+                });
+                // glTF UVs start at the top left, flipped to match OBJ
+                let tex_coord = tex_coords.get(i).map_or(vec2(0.0, 0.0), |uv| vec2(uv[0], 1.0 - uv[1]));
+
+                primitive_vertices.push(
+                    Vertex {
+                        position: position.truncate(),
+                        normal,
+                        tex_coord,
+                        ..Vertex::default()
+                    }
+                );
+            }
+
+            let material = primitive.material();
+
+            if normals.len() != positions.len() {
+                generate_normals(&mut primitive_vertices, &primitive_indices);
+            }
+            if material.normal_texture().is_some() {
+                if tangents.len() == positions.len() {
+                    // W is the handedness of the bitangent. Flipping the UVs also flips it.
+                    let tangent_matrix = Matrix3::new(
+                        transform.x.x, transform.x.y, transform.x.z,
+                        transform.y.x, transform.y.y, transform.y.z,
+                        transform.z.x, transform.z.y, transform.z.z
+                    );
+                    for (vertex, tangent) in primitive_vertices.iter_mut().zip(tangents.iter()) {
+                        vertex.tangent = (tangent_matrix * vec3(tangent[0], tangent[1], tangent[2])).normalize();
+                        vertex.bitangent = vertex.normal.cross(vertex.tangent) * -tangent[3];
+                    }
+                } else {
+                    generate_tangents(&mut primitive_vertices, &primitive_indices);
+                }
+            }
+
+            let base_vertex = vertices.len() as u32;
+            let offset = indices.len();
+            indices.extend(primitive_indices.iter().map(|index| index + base_vertex));
+            vertices.append(&mut primitive_vertices);
+
+            let mut gl_mesh = Mesh::new(offset, (indices.len() - offset) as i32);
+            let pbr = material.pbr_metallic_roughness();
+            let base_colour = pbr.base_color_factor();
+
+            // Diffuse map, tinted by the base colour. Only blended materials keep
+            // their alpha as it is, masked ones are alpha tested in the G-buffer.
+            let alpha_mode = material.alpha_mode();
+            if let Some(info) = pbr.base_color_texture() {
+                let conversion = GltfImageConversion::BaseColour {
+                    factor: base_colour,
+                    alpha_mode,
+                    alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5)
+                };
+                let texture = self.load_gltf_texture(path, images, info.texture(), true, conversion)?;
+                gl_mesh.diffuse_textures.push(texture);
+            } else {
+                gl_mesh.diffuse = vec3(base_colour[0], base_colour[1], base_colour[2]);
+            }
+            // Specular map, metals reflect their own colour while everything else is dim and white
+            if let Some(info) = pbr.metallic_roughness_texture() {
+                let conversion = GltfImageConversion::Specular { metallic: pbr.metallic_factor(), roughness: pbr.roughness_factor() };
+                let texture = self.load_gltf_texture(path, images, info.texture(), false, conversion)?;
+                gl_mesh.specular_textures.push(texture);
+            } else {
+                let metallic = pbr.metallic_factor();
+                gl_mesh.specular = vec3(0.04, 0.04, 0.04) * (1.0 - metallic) + vec3(base_colour[0], base_colour[1], base_colour[2]) * metallic;
+            }
+            // Normal map
+            if let Some(normal_texture) = material.normal_texture() {
+                let texture = self.load_gltf_texture(path, images, normal_texture.texture(), false, GltfImageConversion::None)?;
+                gl_mesh.normal_textures.push(texture);
+            }
+            // Approximate Blinn-Phong exponent for the roughness
+            let roughness = pbr.roughness_factor().max(0.05);
+            gl_mesh.shininess = (2.0 / roughness.powi(4) - 2.0).min(1024.0);
+
+            let blend = matches!(alpha_mode, gltf::material::AlphaMode::Blend);
+            self.add_fallback_maps(&mut gl_mesh, if blend { base_colour[3] } else { 1.0 })?;
+
+            meshes.push((gl_mesh, blend));
+        }
+
+        self.create_split_model(vertices, indices, meshes)
+    }
+
+    // Meshes missing a diffuse, specular or normal map get a 1x1 one of their
+    // material's values, so shaders can always sample them. Specular strength is
+    // the average of the specular colour, as the built-in shaders read it from red.
+    fn add_fallback_maps(&mut self, mesh: &mut Mesh, alpha: f32) -> Result<(), EngineError> {
+        let to_byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        if mesh.diffuse_textures.is_empty() {
+            let diffuse = [to_byte(mesh.diffuse.x), to_byte(mesh.diffuse.y), to_byte(mesh.diffuse.z), to_byte(alpha)];
+            mesh.diffuse_textures.push(self.fallback_texture(&diffuse)?);
+        }
+        if mesh.specular_textures.is_empty() {
+            let strength = (mesh.specular.x + mesh.specular.y + mesh.specular.z) / 3.0;
+            mesh.specular_textures.push(self.fallback_texture(&[to_byte(strength)])?);
+        }
+        if mesh.normal_textures.is_empty() {
+            // Straight out of the surface
+            mesh.normal_textures.push(self.fallback_texture(&[128, 128, 255])?);
+        }
+
+        Ok(())
+    }
+
+    // Linear 1x1 textures with one, three or four channels, cached as
+    // "#fallback_" followed by the pixel's values, e.g. "#fallback_128_128_255"
+    fn fallback_texture(&mut self, pixel: &[u8]) -> Result<Rc<Texture>, EngineError> {
+        let values: Vec<String> = pixel.iter().map(u8::to_string).collect();
+        let key = format!("#fallback_{}", values.join("_"));
+
+        if let Some(texture) = self.texture_store.get(&key) {
+            let texture = Rc::clone(texture);
+            self.resource_cache.touch(&AssetKey::Texture(key));
+
+            return Ok(texture);
+        }
+
+        let (internal_format, data_format) = match pixel.len() {
+            1 => (gl::R8, gl::RED),
+            3 => (gl::RGB8, gl::RGB),
+            _ => (gl::RGBA8, gl::RGBA)
+        };
+        let image = GlImage { bytes: pixel.to_vec(), internal_format, data_format, width: 1, height: 1 };
+        let transparent = image_has_transparency(&image);
+
+        self.prepare_graphics_library("texture")?;
+        let texture = Rc::new(create_texture_2d(image));
+        TextureOptions::linear().apply(&texture);
+        self.set_texture_transparency(&texture, transparent);
+        self.store_texture(&key, None, &texture, pixel.len());
+
+        Ok(texture)
+    }
+
+    // Meshes flagged as transparent are split off into a model of their own for
+    // View3DScene's transparent pass, so they aren't drawn in the G-buffer too.
+    // Both models get every vertex, as the meshes index into them. The size is of both.
+    fn create_split_model(
+        &self,
+        vertices: Vec<Vertex>,
+        indices: Vec<u32>,
+        meshes: Vec<(Mesh, bool)>
+    ) -> Result<(Rc<Model>, Option<Rc<Model>>, usize), EngineError> {
+        let size = model_size(&vertices, &indices);
+        let (transparent_meshes, opaque_meshes): (Vec<_>, Vec<_>) = meshes.into_iter().partition(|(_, transparent)| *transparent);
+        let opaque_meshes: Vec<Mesh> = opaque_meshes.into_iter().map(|(mesh, _)| mesh).collect();
+        let transparent_meshes: Vec<Mesh> = transparent_meshes.into_iter().map(|(mesh, _)| mesh).collect();
+
+        let transparent_part = if transparent_meshes.is_empty() {
+            None
+        } else {
+            let part: Box<dyn ModelTrait> = self.create_model(vertices.clone(), indices.clone(), vec![], transparent_meshes)?;
+            Some(Rc::new(RefCell::new(part)))
+        };
+
+        let model: Box<dyn ModelTrait> = self.create_model(vertices, indices, vec![], opaque_meshes)?;
+        let size = if transparent_part.is_some() { size * 2 } else { size };
+
+        Ok((Rc::new(RefCell::new(model)), transparent_part, size))
+    }
+
+    // Images are decoded by the glTF importer, since they can be embedded in the file.
+    // They are cached as "path#image{index}", or "path#image{index}#{options}" when
+    // the texture's sampler isn't the default, with the conversion's name before the
+    // options if it was converted.
+    fn load_gltf_texture(
+        &mut self,
+        path: &str,
+        images: &[gltf::image::Data],
+        texture: gltf::Texture,
+        srgb: bool,
+        conversion: GltfImageConversion
+    ) -> Result<Rc<Texture>, EngineError> {
+        let index = texture.source().index();
+        let options = gltf_texture_options(&texture.sampler(), srgb);
+        let key = options.cache_key(&format!("{}#image{}{}", path, index, conversion.key_suffix()));
+
+        if let Some(texture) = self.texture_store.get(&key) {
+            let texture = Rc::clone(texture);
+            self.resource_cache.touch(&AssetKey::Texture(key));
+
+            return Ok(texture);
+        }
+
+        let data = &images[index];
+        let (internal_format, data_format) = match data.format {
+            gltf::image::Format::R8 => (gl::R8, gl::RED),
+            gltf::image::Format::R8G8 => (gl::RG8, gl::RG),
+            gltf::image::Format::R8G8B8 => (gl::RGB8, gl::RGB),
+            gltf::image::Format::R8G8B8A8 => (gl::RGBA8, gl::RGBA),
+            gltf::image::Format::R16 => (gl::R16, gl::RED),
+            gltf::image::Format::R16G16 => (gl::RG16, gl::RG),
+            gltf::image::Format::R16G16B16 => (gl::RGB16, gl::RGB),
+            gltf::image::Format::R16G16B16A16 => (gl::RGBA16, gl::RGBA),
+            gltf::image::Format::R32G32B32FLOAT => (gl::RGB16F, gl::RGB),
+            gltf::image::Format::R32G32B32A32FLOAT => (gl::RGBA16F, gl::RGBA),
+            #[allow(unreachable_patterns)]
+            format => {
+                return Err(EngineError::ResourceManagerError(format!("glTF file '{}' has an image in an unsupported format: {:?}", path, format)))
+            }
+        };
+
+        let size = data.pixels.len();
+        let image = GlImage {
+            bytes: data.pixels.clone(),
+            internal_format,
+            data_format,
+            width: data.width as i32,
+            height: data.height as i32
+        };
+        let mut image = conversion.apply(image, srgb);
+        let transparent = image_has_transparency(&image);
+        options.apply_to_image(&mut image);
+        self.prepare_graphics_library("texture")?;
+        let texture = Rc::new(create_texture_2d(image));
+        options.apply(&texture);
+        self.set_texture_transparency(&texture, transparent);
+        self.store_texture(&key, None, &texture, size);
+
+        Ok(texture)
+    }
+
+    pub(crate) fn load_image(vfs: &Vfs, path: &str) -> Result<GlImage, EngineError> {
+        let mut img = image::io::Reader::new(Cursor::new(vfs.read(path)?)).with_guessed_format()?.decode()?;
 
-        // TODO: if there is an alpha, mark texture as transparent
+        // Whether there is any alpha below 1 is checked when the image is uploaded.
+        // Colour starts as sRGB and is switched to linear by the texture's options,
+        // while HDR formats like Radiance and OpenEXR decode to linear floats, which
+        // are stored as half floats.
         let (internal_format, data_format) = match img {
             ImageLuma8(_) => (gl::R8, gl::RED),
             ImageLumaA8(_) => (gl::RG8, gl::RG),
             ImageRgb8(_) => (gl::SRGB8, gl::RGB),
             ImageRgba8(_) => (gl::SRGB8_ALPHA8, gl::RGBA),
-            _ => (gl::SRGB, gl::RGB) // If nothing else, try default
+            ImageLuma16(_) => (gl::R16, gl::RED),
+            ImageLumaA16(_) => (gl::RG16, gl::RG),
+            ImageRgb16(_) => (gl::RGB16, gl::RGB),
+            ImageRgba16(_) => (gl::RGBA16, gl::RGBA),
+            ImageRgb32F(_) => (gl::RGB16F, gl::RGB),
+            ImageRgba32F(_) => (gl::RGBA16F, gl::RGBA),
+            _ => {
+                // Anything else is converted to a format that can be uploaded
+                img = ImageRgba8(img.to_rgba8());
+                (gl::SRGB8_ALPHA8, gl::RGBA)
+            }
         };
 
         Ok(
@@ -141,49 +694,267 @@ impl ResourceManager {
         )
     }
 
-    fn _load_texture_2d(&mut self, path: &str) -> Result<Rc<Texture>, EngineError> {
-        let image = ResourceManager::load_image(path)?;
-        let texture = match self.gl {
-            GraphicsLibrary::OpenGL4_6(_, _) => Rc::new(Texture::from_2d(image)),
-            GraphicsLibrary::None => {
-                return Err(EngineError::ResourceManagerError(String::from("Trying to load texture without selected graphics library!")))
-            },
-        };
-        self.texture_store.insert(path.to_owned(), Rc::clone(&texture));
+    fn _load_texture_2d(&mut self, path: &str, options: TextureOptions) -> Result<Rc<Texture>, EngineError> {
+        let image = ResourceManager::load_image(&self.vfs, path)?;
+
+        self.upload_texture_2d(path, image, options)
+    }
+
+    pub(crate) fn upload_texture_2d(&mut self, path: &str, mut image: GlImage, options: TextureOptions) -> Result<Rc<Texture>, EngineError> {
+        let size = image.bytes.len();
+        let transparent = image_has_transparency(&image);
+        options.apply_to_image(&mut image);
+        self.prepare_graphics_library("texture")?;
+        let texture = Rc::new(create_texture_2d(image));
+        options.apply(&texture);
+        self.set_texture_transparency(&texture, transparent);
+        self.store_texture(&options.cache_key(path), Some((path, options)), &texture, size);
 
         Ok(texture)
     }
 
-    pub fn load_texture_2d(&mut self, path: &str) -> Result<Rc<Texture>, EngineError> {
-        if let Some(texture) = self.texture_store.get(path) {
-            Ok(Rc::clone(texture))
+    pub fn load_texture_2d(&mut self, path: &str, options: TextureOptions) -> Result<Rc<Texture>, EngineError> {
+        let key = options.cache_key(path);
+
+        if let Some(texture) = self.texture_store.get(&key) {
+            let texture = Rc::clone(texture);
+            self.resource_cache.touch(&AssetKey::Texture(key));
+
+            Ok(texture)
         } else {
-            self._load_texture_2d(path)
+            self._load_texture_2d(path, options)
         }
     }
 
-    fn _load_texture_cubemap(&mut self, path: &str) -> Result<Rc<Texture>, EngineError> {
-        let image = ResourceManager::load_image(path)?;
-        let texture = match self.gl {
-            GraphicsLibrary::OpenGL4_6(_, _) => Rc::new(Texture::from_cubemap(image)),
-            GraphicsLibrary::None => {
-                return Err(EngineError::ResourceManagerError(String::from("Trying to load texture without selected graphics library!")))
-            },
-        };
-        self.texture_store.insert(path.to_owned(), Rc::clone(&texture));
+    fn _load_texture_cubemap(&mut self, path: &str, options: TextureOptions) -> Result<Rc<Texture>, EngineError> {
+        let image = ResourceManager::load_image(&self.vfs, path)?;
+
+        self.upload_texture_cubemap(path, image, options)
+    }
+
+    pub(crate) fn upload_texture_cubemap(&mut self, path: &str, mut image: GlImage, options: TextureOptions) -> Result<Rc<Texture>, EngineError> {
+        options.apply_to_image(&mut image);
+        if pixel_type(&image) != gl::UNSIGNED_BYTE {
+            image = to_8_bit(&image, options.srgb);
+        }
+        let size = image.bytes.len();
+        self.prepare_graphics_library("texture")?;
+        let texture = Rc::new(Texture::from_cubemap(image));
+        options.apply(&texture);
+        self.store_texture(&options.cache_key(path), Some((path, options)), &texture, size);
 
         Ok(texture)
     }
 
-    pub fn load_texture_cubemap(&mut self, path: &str) -> Result<Rc<Texture>, EngineError> {
-        if let Some(texture) = self.texture_store.get(path) {
-            Ok(Rc::clone(texture))
+    fn set_texture_transparency(&mut self, texture: &Texture, transparent: bool) {
+        if transparent {
+            self.transparent_textures.insert(texture.get_id());
+        } else {
+            self.transparent_textures.remove(&texture.get_id());
+        }
+    }
+
+    // Whether any of the texture's pixels have an alpha below 1, only known for 2D
+    // textures loaded by the resource manager
+    pub fn is_texture_transparent(&self, texture: &Texture) -> bool {
+        self.transparent_textures.contains(&texture.get_id())
+    }
+
+    // Meshes are transparent when their diffuse map is
+    pub fn is_mesh_transparent(&self, mesh: &Mesh) -> bool {
+        mesh.diffuse_textures.iter().any(|texture| self.is_texture_transparent(texture))
+    }
+
+    // Models with any transparent mesh need to be drawn in View3DScene's transparent
+    // pass, see View3DScene::add_model(). Only known for models the resource manager
+    // loaded, while they are cached.
+    pub fn is_model_transparent(&self, model: &Rc<Model>) -> bool {
+        self.transparent_parts.contains_key(&Rc::as_ptr(model))
+    }
+
+    // The model's transparent meshes, split off when it was loaded. They are drawn
+    // with the model's transforms.
+    pub fn get_transparent_part(&self, model: &Rc<Model>) -> Option<Rc<Model>> {
+        self.transparent_parts.get(&Rc::as_ptr(model)).map(|(_, part)| Rc::clone(part))
+    }
+
+    pub fn load_texture_cubemap(&mut self, path: &str, options: TextureOptions) -> Result<Rc<Texture>, EngineError> {
+        let key = options.cache_key(path);
+
+        if let Some(texture) = self.texture_store.get(&key) {
+            let texture = Rc::clone(texture);
+            self.resource_cache.touch(&AssetKey::Texture(key));
+
+            Ok(texture)
         } else {
-            self._load_texture_cubemap(path)
+            self._load_texture_cubemap(path, options)
+        }
+    }
+
+    // Animated GIFs and PNGs, see TextureWidget::new_animated() to play them.
+    // Animated textures aren't hot reloaded.
+    pub fn load_animated_texture(&mut self, path: &str, options: TextureOptions) -> Result<Rc<AnimatedTexture>, EngineError> {
+        let key = options.cache_key(path);
+
+        if let Some(animation) = self.animated_texture_store.get(&key).map(Rc::clone) {
+            self.resource_cache.touch(&AssetKey::AnimatedTexture(key));
+
+            return Ok(animation);
+        }
+
+        let frames = decode_animation(path, &self.vfs.read(path)?)?;
+        self.upload_animated_texture(&key, frames, options)
+    }
+
+    pub fn load_sprite_sheet(&mut self, path: &str, sheet: SpriteSheet, options: TextureOptions) -> Result<Rc<AnimatedTexture>, EngineError> {
+        let key = sheet.cache_key(path, &options);
+
+        if let Some(animation) = self.animated_texture_store.get(&key).map(Rc::clone) {
+            self.resource_cache.touch(&AssetKey::AnimatedTexture(key));
+
+            return Ok(animation);
+        }
+
+        let image = ResourceManager::load_image(&self.vfs, path)?;
+        let frames = split_sprite_sheet(path, &image, &sheet)?;
+        self.upload_animated_texture(&key, frames, options)
+    }
+
+    fn upload_animated_texture(&mut self, key: &str, frames: Vec<(GlImage, f32)>, options: TextureOptions) -> Result<Rc<AnimatedTexture>, EngineError> {
+        if frames.is_empty() {
+            return Err(EngineError::ResourceManagerError(format!("'{}' doesn't have any frames!", key)));
+        }
+
+        let mut animation = AnimatedTexture { frames: Vec::new(), durations: Vec::new() };
+        let mut size = 0;
+
+        for (mut image, duration) in frames {
+            size += image.bytes.len();
+            let transparent = image_has_transparency(&image);
+            options.apply_to_image(&mut image);
+
+            self.prepare_graphics_library("texture")?;
+            let texture = Rc::new(create_texture_2d(image));
+            options.apply(&texture);
+            self.set_texture_transparency(&texture, transparent);
+
+            animation.frames.push(texture);
+            animation.durations.push(duration);
         }
+
+        let animation = Rc::new(animation);
+        self.animated_texture_store.insert(key.to_owned(), Rc::clone(&animation));
+        self.resource_cache.insert(AssetKey::AnimatedTexture(key.to_owned()), size);
+        self.enforce_memory_budget(Some(&AssetKey::AnimatedTexture(key.to_owned())));
+
+        Ok(animation)
+    }
+
+    // Motion JPEG AVI files, e.g. from ffmpeg -i clip.mp4 -c:v mjpeg -q:v 3 clip.avi.
+    // Videos aren't cached, as each stream has its own playback. Frames are read and
+    // decoded one at a time in the background, see VideoWidget. Files on disk are
+    // streamed, but ones in a pak archive are read into memory whole, as mounts only
+    // read whole files, so keep long videos out of archives.
+    pub fn open_video(&self, path: &str) -> Result<VideoStream, EngineError> {
+        let mut source: Box<dyn VideoSource> = match self.vfs.real_path(path) {
+            Some(real_path) => Box::new(File::open(real_path)?),
+            None => Box::new(Cursor::new(self.vfs.read(path)?))
+        };
+        let index = parse_avi(path, source.as_mut())?;
+
+        self.prepare_graphics_library("video")?;
+        VideoStream::new(source, index)
     }
 
     pub fn load_skybox(&mut self, path: &str) -> Result<Skybox, EngineError> {
+        let texture = self.load_texture_cubemap(path, TextureOptions::default())?;
+
+        self.create_skybox(texture)
+    }
+
+    // Faces are in OpenGL's order: +X, -X, +Y, -Y, +Z, -Z (right, left, top, bottom,
+    // front, back). The cubemap is cached under the first face's path.
+    pub fn load_skybox_faces(&mut self, paths: &[&str; 6]) -> Result<Skybox, EngineError> {
+        let key = format!("{}#faces({})", paths[0], paths[1..].join(","));
+
+        if let Some(texture) = self.texture_store.get(&key).map(Rc::clone) {
+            self.resource_cache.touch(&AssetKey::Texture(key));
+
+            return self.create_skybox(texture);
+        }
+
+        let mut faces = Vec::new();
+        for path in paths {
+            faces.push(ResourceManager::load_image(&self.vfs, path)?);
+        }
+
+        let texture = self.upload_skybox_faces(&key, faces)?;
+        self.create_skybox(texture)
+    }
+
+    // Converts a panorama, e.g. an HDR environment, to a cubemap with faces of
+    // face_size pixels, which has to be at least 1. The centre of the panorama faces
+    // the default camera direction.
+    pub fn load_skybox_equirectangular(&mut self, path: &str, face_size: i32) -> Result<Skybox, EngineError> {
+        let key = format!("{}#cubemap{}", path, face_size);
+
+        if let Some(texture) = self.texture_store.get(&key).map(Rc::clone) {
+            self.resource_cache.touch(&AssetKey::Texture(key));
+
+            return self.create_skybox(texture);
+        }
+
+        let image = ResourceManager::load_image(&self.vfs, path)?;
+        let faces = equirectangular_to_faces(&image, face_size)?;
+
+        let texture = self.upload_skybox_faces(&key, faces)?;
+        self.create_skybox(texture)
+    }
+
+    // Skybox without any files, fading from top to horizon to bottom. Generated
+    // skyboxes aren't cached.
+    pub fn create_skybox_gradient(&mut self, top: Vector3<f32>, horizon: Vector3<f32>, bottom: Vector3<f32>) -> Result<Skybox, EngineError> {
+        self.prepare_graphics_library("texture")?;
+        let texture = Rc::new(create_cubemap(&gradient_faces(top, horizon, bottom))?);
+        TextureOptions::default().apply(&texture);
+
+        self.create_skybox(texture)
+    }
+
+    pub fn create_skybox_colour(&mut self, colour: Vector3<f32>) -> Result<Skybox, EngineError> {
+        self.create_skybox_gradient(colour, colour, colour)
+    }
+
+    pub fn load_skybox_from(&mut self, source: &SkyboxSource) -> Result<Skybox, EngineError> {
+        match source {
+            SkyboxSource::Cubemap(path) => self.load_skybox(path),
+            SkyboxSource::Faces(paths) => {
+                let paths = [&*paths[0], &*paths[1], &*paths[2], &*paths[3], &*paths[4], &*paths[5]];
+                self.load_skybox_faces(&paths)
+            },
+            SkyboxSource::Equirectangular { path, face_size } => self.load_skybox_equirectangular(path, *face_size),
+            SkyboxSource::Colour(colour) => self.create_skybox_colour(*colour),
+            SkyboxSource::Gradient { top, horizon, bottom } => self.create_skybox_gradient(*top, *horizon, *bottom),
+        }
+    }
+
+    fn upload_skybox_faces(&mut self, key: &str, mut faces: Vec<GlImage>) -> Result<Rc<Texture>, EngineError> {
+        let options = TextureOptions::default();
+        for face in faces.iter_mut() {
+            options.apply_to_image(face);
+        }
+
+        let size = faces.iter().map(|face| face.bytes.len()).sum();
+        self.prepare_graphics_library("texture")?;
+        let texture = Rc::new(create_cubemap(&faces)?);
+        options.apply(&texture);
+        self.store_texture(key, None, &texture, size);
+
+        Ok(texture)
+    }
+
+    // Puts the cubemap on a cube to draw around the camera
+    fn create_skybox(&self, texture: Rc<Texture>) -> Result<Skybox, EngineError> {
         // Cube definition
         let vertices = vec![
             Vertex {
@@ -247,58 +1018,65 @@ impl ResourceManager {
 
         let model_transforms = vec![Matrix4::<f32>::from_translation(vec3(0.0, 0.0, 0.0))];
 
-        let mut model = match self.gl {
-            GraphicsLibrary::OpenGL4_6(_, _) => {
-                MultiBindModel::new(
-                    vertices,
-                    indices,
-                    model_transforms,
-                    vec![Mesh::new(0, 36)]
-                )
-            },
-            GraphicsLibrary::None => {
-                return Err(EngineError::ResourceManagerError(String::from("Trying to load model without selected graphics library!")))
-            },
-        };
-        model.meshes[0].diffuse_textures.push(self.load_texture_cubemap(path)?);
+        self.prepare_graphics_library("model")?;
+        let mut model = MultiBindModel::new(
+            vertices,
+            indices,
+            model_transforms,
+            vec![Mesh::new(0, 36)]
+        );
+        model.meshes[0].diffuse_textures.push(texture);
 
         Ok(Skybox { model })
     }
 
-    fn load_shader(&mut self, path: &str) -> Result<String, EngineError> {
-        let mut shader_file = File::open(path)?;
-        let mut shader_code = String::new();
-
-        shader_file.read_to_string(&mut shader_code)?;
-
-        Ok(shader_code)
-    }
-
-    fn load_shaders(&mut self, paths: ShaderPathBundle) -> Result<Rc<ShaderProgram>, EngineError> {
+    // Returns the code with includes expanded and defines added, along with every
+    // file it was built from, indexed by the source string numbers in compiler logs
+    fn load_shader_code(&self, paths: &ShaderPathBundle) -> Result<(ShaderCodeBundle, Vec<String>), EngineError> {
+        let mut preprocessor = ShaderPreprocessor::new(&self.vfs, &paths.defines);
         let mut code_bundle = ShaderCodeBundle::default();
 
         if let Some(vert) = &paths.vertex {
-            code_bundle.vertex = Some(self.load_shader(vert)?);
+            code_bundle.vertex = Some(preprocessor.process(vert)?);
         }
         if let Some(geom) = &paths.geometry {
-            code_bundle.geometry = Some(self.load_shader(geom)?);
+            code_bundle.geometry = Some(preprocessor.process(geom)?);
         }
         if let Some(frag) = &paths.fragment {
-            code_bundle.fragment = Some(self.load_shader(frag)?);
+            code_bundle.fragment = Some(preprocessor.process(frag)?);
         }
 
-        let shader_program = match self.gl {
-            GraphicsLibrary::OpenGL4_6(_, _) => Rc::new(ShaderProgram::new(code_bundle)?),
-            GraphicsLibrary::None => return Err(EngineError::ResourceManagerError(String::from("Trying to load shaders without selected graphics library!"))),
-        };
+        Ok((code_bundle, preprocessor.files().to_vec()))
+    }
 
+    fn load_shaders(&mut self, paths: ShaderPathBundle) -> Result<Rc<ShaderProgram>, EngineError> {
+        let (code_bundle, files) = self.load_shader_code(&paths)?;
+
+        self.prepare_graphics_library("shaders")?;
+        let shader_program = Rc::new(
+            ShaderProgram::new(code_bundle)
+                .map_err(|err| EngineError::ResourceManagerError(format!("Failed to build shaders {:?}: {}", paths, map_shader_log(&err.to_string(), &files))))?
+        );
+
+        if let Some(file_watcher) = &mut self.file_watcher {
+            for path in files.iter() {
+                file_watcher.watch(&self.vfs, path);
+            }
+        }
+
+        // Shaders are small, so don't count towards the memory budget
+        self.resource_cache.insert(AssetKey::ShaderProgram(paths.clone()), 0);
+        self.shader_files.insert(paths.clone(), files);
         self.shader_store.insert(paths, Rc::clone(&shader_program));
         Ok(shader_program)
     }
 
     pub fn load_shader_program(&mut self, paths: ShaderPathBundle) -> Result<Rc<ShaderProgram>, EngineError> {
         if let Some(shader) = self.shader_store.get(&paths) {
-            Ok(Rc::clone(shader))
+            let shader = Rc::clone(shader);
+            self.resource_cache.touch(&AssetKey::ShaderProgram(paths));
+
+            Ok(shader)
         } else {
             self.load_shaders(paths)
         }
@@ -309,69 +1087,1016 @@ impl ResourceManager {
     // of a normal resource manager. Instead the font_family is stored so
     // that glyphs can be loaded easily.
     pub fn load_face(&mut self, path: &str) -> Result<(), EngineError> {
-        let face = self.face_library.new_face(path, 0)?;
+        self._load_face(path).map(|_| ())
+    }
+
+    // Returns the face's family name
+    fn _load_face(&mut self, path: &str) -> Result<String, EngineError> {
+        let face = self.face_library.new_memory_face(self.vfs.read(path)?, 0)?;
 
         if let Some(family) = face.family_name() {
-            self.face_store.insert(family, face);
+            self.face_paths.insert(family.clone(), path.to_owned());
+            self.face_store.insert(family.clone(), face);
 
-            Ok(())
+            Ok(family)
         } else {
             Err(EngineError::ResourceManagerError(String::from("The font you are attempting to load does not have a family name!")))
         }
     }
 
-    fn _load_glyph(&mut self, glyph_metadata: GlyphMetaDeta) -> Result<Rc<GlyphData>, EngineError> {
-        let face = self.face_store.get(&glyph_metadata.font_family);
-        if let Some(face) = face {
-            face.load_char(glyph_metadata.glyph as usize, freetype::face::LoadFlag::RENDER)?;
-            let glyph = face.glyph();
-
-            let image = GlImage {
-                bytes: glyph.bitmap().buffer().to_vec(),
-                internal_format: gl::RED,
-                data_format: gl::RED,
-                width: glyph.bitmap().width(),
-                height: glyph.bitmap().rows(),
-            };
+    // Scale of the window's framebuffer compared to its size, e.g. 2.0 on most
+    // high DPI displays. Font sizes are multiplied by this when rasterizing.
+    pub fn get_content_scale(&self) -> f32 { self.content_scale }
+    pub fn set_content_scale(&mut self, content_scale: f32) {
+        if content_scale != self.content_scale {
+            self.content_scale = content_scale;
+            // Every glyph was rasterized at the wrong size
+            self.glyph_store.clear();
+            self.glyph_generation += 1;
+            self.glyph_atlas.clear();
+        }
+    }
+
+    pub fn get_font_render_mode(&self, font_family: &str) -> FontRenderMode {
+        self.font_render_modes.get(font_family).copied().unwrap_or_default()
+    }
 
-            // Disable pixel alignment so one byte textures can be stored in GPU
-            // TODO: might not be needed since buffer is supposed to be aligned to 32-bit?
-            unsafe {
-                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+    // Glyphs already loaded for the family are dropped, so text using it is laid
+    // out again
+    pub fn set_font_render_mode(&mut self, font_family: &str, mode: FontRenderMode) {
+        self.font_render_modes.insert(font_family.to_owned(), mode);
+        self.glyph_store.retain(|metadata, _| metadata.font_family != font_family);
+        self.glyph_generation += 1;
+    }
+
+    // Faces are shared between sizes, so the size needs to be set before every use
+    fn get_sized_face(&self, font_family: &str, font_size: u32) -> Result<&freetype::Face, EngineError> {
+        let face = self.face_store
+            .get(font_family)
+            .ok_or(EngineError::FontFamilyNotFound(font_family.to_owned()))?;
+        let pixel_size = (font_size as f32 * self.content_scale).round() as u32;
+        face.set_pixel_sizes(0, pixel_size)?;
+
+        Ok(face)
+    }
+
+    // Metrics are in pixels at the content scale, the same as glyphs
+    pub fn load_face_metrics(&self, font_family: &str, font_size: u32) -> Result<FaceMetrics, EngineError> {
+        let face = self.get_sized_face(font_family, font_size)?;
+        let metrics = face
+            .size_metrics()
+            .ok_or(EngineError::ResourceManagerError(String::from("The font you are using does not have size metrics!")))?;
+
+        // Metrics are stored in 1/64ths of a pixel
+        Ok(
+            FaceMetrics {
+                ascender: metrics.ascender as f32 / 64.0,
+                descender: metrics.descender as f32 / 64.0,
+                line_height: metrics.height as f32 / 64.0
             }
+        )
+    }
 
-            let data = Rc::new(
-                GlyphData {
-                    texture: match self.gl {
-                        GraphicsLibrary::OpenGL4_6(_, _) => Texture::from_2d_ui(image),
-                        GraphicsLibrary::None => {
-                            return Err(EngineError::ResourceManagerError(String::from("Trying to load fonts without selected graphics library!")))
-                        },
-                    },
-                    size: vec2(glyph.bitmap().width(), glyph.bitmap().rows()),
-                    bearing: vec2(glyph.bitmap_left(), glyph.bitmap_top()),
-                    advance: glyph.advance().x,
-                }
-            );
+    // Horizontal adjustment in pixels between two characters that are next to each other
+    pub fn get_kerning(&self, font_family: &str, font_size: u32, left: char, right: char) -> Result<f32, EngineError> {
+        let face = self.get_sized_face(font_family, font_size)?;
+
+        if !face.has_kerning() {
+            return Ok(0.0);
+        }
 
-            self.glyph_store.insert(glyph_metadata, Rc::clone(&data));
+        let kerning = face.get_kerning(
+            face.get_char_index(left as usize),
+            face.get_char_index(right as usize),
+            freetype::face::KerningMode::KerningDefault
+        )?;
 
-            Ok(data)
-        } else {
-            Err(EngineError::FontFamilyNotFound(glyph_metadata.font_family))
+        Ok(kerning.x as f32 / 64.0)
+    }
+
+    // The face needs to be set to the right size first. Doesn't touch the GPU, so can
+    // be done on any thread with its own face.
+    pub(crate) fn rasterize_glyph(face: &freetype::Face, glyph: char, mode: FontRenderMode) -> Result<RasterizedGlyph, EngineError> {
+        face.load_char(glyph as usize, freetype::face::LoadFlag::RENDER)?;
+        let glyph = face.glyph();
+        let bitmap = glyph.bitmap();
+
+        // Rows can be padded, so copy them out tightly packed
+        let (width, rows, pitch) = (bitmap.width(), bitmap.rows(), bitmap.pitch().unsigned_abs() as usize);
+        let mut bytes = Vec::with_capacity((width * rows) as usize);
+        for row in 0..rows as usize {
+            bytes.extend_from_slice(&bitmap.buffer()[row * pitch..row * pitch + width as usize]);
+        }
+
+        let mut size = vec2(width, rows);
+        let mut bearing = vec2(glyph.bitmap_left(), glyph.bitmap_top());
+        let advance = glyph.advance().x;
+
+        // The field is padded by the spread, so the bitmap grows and moves up and left
+        if let FontRenderMode::Sdf { spread } = mode {
+            if width > 0 && rows > 0 {
+                let (field, field_width, field_rows) = generate_sdf(&bytes, width, rows, spread)?;
+
+                bytes = field;
+                size = vec2(field_width, field_rows);
+                bearing += vec2(-(spread as i32), spread as i32);
+            }
         }
+
+        Ok(RasterizedGlyph { bytes, size, bearing, advance })
+    }
+
+    pub(crate) fn insert_glyph(&mut self, glyph_metadata: GlyphMetaDeta, glyph: RasterizedGlyph) -> Result<Rc<GlyphData>, EngineError> {
+        self.prepare_graphics_library("fonts")?;
+        let (region, evicted) = self.glyph_atlas.insert(&glyph.bytes, glyph.size.x, glyph.size.y)?;
+
+        // Glyphs on a cleared page need to be rasterized again
+        if let Some(evicted) = evicted {
+            self.glyph_store.retain(|_, glyph| glyph.page != evicted);
+            self.glyph_generation += 1;
+        }
+
+        let data = Rc::new(
+            GlyphData {
+                texture: region.texture,
+                page: region.page,
+                uv: region.uv,
+                size: glyph.size,
+                bearing: glyph.bearing,
+                advance: glyph.advance,
+            }
+        );
+
+        self.glyph_store.insert(glyph_metadata, Rc::clone(&data));
+
+        Ok(data)
+    }
+
+    fn _load_glyph(&mut self, glyph_metadata: GlyphMetaDeta) -> Result<Rc<GlyphData>, EngineError> {
+        let mode = self.get_font_render_mode(&glyph_metadata.font_family);
+        let face = self.get_sized_face(&glyph_metadata.font_family, glyph_metadata.font_size)?;
+        let glyph = ResourceManager::rasterize_glyph(face, glyph_metadata.glyph, mode)?;
+
+        self.insert_glyph(glyph_metadata, glyph)
     }
 
     // Lazy loading of glyphs enabled this way
     pub fn load_glyph(&mut self, glyph_metadata: GlyphMetaDeta) -> Result<Rc<GlyphData>, EngineError> {
         if let Some(glyph) = self.glyph_store.get(&glyph_metadata) {
-            Ok(Rc::clone(glyph))
+            let glyph = Rc::clone(glyph);
+            self.glyph_atlas.touch(glyph.page);
+
+            Ok(glyph)
         } else {
             self._load_glyph(glyph_metadata)
         }
     }
 
-    // TODO: add an eager font load function
+    // Changes whenever loaded glyphs are dropped, e.g. when they are removed from
+    // the atlas or the content scale changes, after which any text laid out with
+    // the old glyphs needs to be laid out again
+    pub fn get_glyph_generation(&self) -> u64 { self.glyph_generation }
+
+    // Between these, glyphs loaded can't be evicted from the atlas to make room for
+    // later ones, so text can be laid out without losing its own glyphs part way
+    pub(crate) fn pin_glyphs(&mut self) { self.glyph_atlas.pin() }
+    pub(crate) fn unpin_glyphs(&mut self) { self.glyph_atlas.unpin() }
+
+    // Loads every glyph in the character set at each size up front, so text shown
+    // later doesn't have to wait for rasterizing. Characters the font doesn't have
+    // are skipped. Progress is called after each glyph with (loaded, total).
+    // Preloading more glyphs than fit in the glyph atlas evicts the earliest ones.
+    pub fn preload_font(
+        &mut self,
+        font_family: &str,
+        font_sizes: &[u32],
+        characters: &CharacterSet,
+        mut progress: impl FnMut(usize, usize)
+    ) -> Result<(), EngineError> {
+        let face = self.face_store
+            .get(font_family)
+            .ok_or(EngineError::FontFamilyNotFound(font_family.to_owned()))?;
+        let chars: Vec<char> = characters
+            .chars()
+            .into_iter()
+            .filter(|c| face.get_char_index(*c as usize) != 0)
+            .collect();
+
+        let total = chars.len() * font_sizes.len();
+        let mut loaded = 0;
+
+        for font_size in font_sizes {
+            for glyph in chars.iter() {
+                self.load_glyph(GlyphMetaDeta {
+                    font_family: font_family.to_owned(),
+                    font_size: *font_size,
+                    glyph: *glyph
+                })?;
+
+                loaded += 1;
+                progress(loaded, total);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Every model and texture added to a store goes through these, so the cache can
+    // keep track of them
+    fn store_model(&mut self, key: &str, model: &Rc<Model>, size: usize, transparent_part: Option<Rc<Model>>) {
+        // Parts of models which have been dropped since are no use to anyone
+        self.transparent_parts.retain(|_, (model, _)| model.strong_count() > 0);
+        if let Some(transparent_part) = transparent_part {
+            self.transparent_parts.insert(Rc::as_ptr(model), (Rc::downgrade(model), transparent_part));
+        }
+
+        if let Some(replaced) = self.model_store.insert(key.to_owned(), Rc::clone(model)) {
+            self.transparent_parts.remove(&Rc::as_ptr(&replaced));
+        }
+        self.resource_cache.insert(AssetKey::Model(key.to_owned()), size);
+        self.enforce_memory_budget(Some(&AssetKey::Model(key.to_owned())));
+    }
+
+    // Source is the path and options for textures loaded from their own file, which
+    // are the only ones that can be hot reloaded
+    fn store_texture(&mut self, key: &str, source: Option<(&str, TextureOptions)>, texture: &Rc<Texture>, size: usize) {
+        if let Some((path, options)) = source {
+            if let Some(file_watcher) = &mut self.file_watcher {
+                file_watcher.watch(&self.vfs, path);
+            }
+
+            self.texture_sources.insert(key.to_owned(), (path.to_owned(), options));
+        }
+
+        self.texture_store.insert(key.to_owned(), Rc::clone(texture));
+        self.resource_cache.insert(AssetKey::Texture(key.to_owned()), size);
+        self.enforce_memory_budget(Some(&AssetKey::Texture(key.to_owned())));
+    }
+
+    // Nothing is freed while something else holds the asset, only the cache's
+    // reference is dropped
+    fn unload_asset(&mut self, key: &AssetKey) -> bool {
+        self.resource_cache.remove(key);
+
+        if let AssetKey::Texture(texture_key) = key {
+            if let Some((path, _)) = self.texture_sources.remove(texture_key) {
+                // The same file can be loaded with other options
+                let still_used = self.texture_sources.values().any(|(other_path, _)| *other_path == path);
+
+                if let (Some(file_watcher), false) = (&mut self.file_watcher, still_used) {
+                    file_watcher.unwatch(&path);
+                }
+            }
+        }
+
+        match key {
+            AssetKey::Model(path) => self.model_handles.remove(path),
+            AssetKey::Texture(path) => self.texture_handles.remove(path),
+            AssetKey::ShaderProgram(paths) => self.shader_handles.remove(paths),
+            AssetKey::AnimatedTexture(_) => ()
+        }
+
+        match key {
+            // Transparency is only forgotten once the asset is freed, as anything still
+            // holding it can still add it to a View3DScene
+            AssetKey::Model(path) => match self.model_store.remove(path) {
+                Some(model) => {
+                    if Rc::strong_count(&model) == 1 {
+                        self.transparent_parts.remove(&Rc::as_ptr(&model));
+                    }
+                    true
+                },
+                None => false
+            },
+            AssetKey::Texture(path) => match self.texture_store.remove(path) {
+                Some(texture) => {
+                    if Rc::strong_count(&texture) == 1 {
+                        self.transparent_textures.remove(&texture.get_id());
+                    }
+                    true
+                },
+                None => false
+            },
+            AssetKey::AnimatedTexture(key) => match self.animated_texture_store.remove(key) {
+                Some(animation) => {
+                    if Rc::strong_count(&animation) == 1 {
+                        for frame in animation.frames.iter().filter(|frame| Rc::strong_count(frame) == 1) {
+                            self.transparent_textures.remove(&frame.get_id());
+                        }
+                    }
+                    true
+                },
+                None => false
+            },
+            AssetKey::ShaderProgram(paths) => {
+                self.shader_files.remove(paths);
+                self.shader_store.remove(paths).is_some()
+            },
+        }
+    }
+
+    fn is_unused(&self, key: &AssetKey) -> bool {
+        match key {
+            AssetKey::Model(path) => self.model_store.get(path).map_or(true, |model| Rc::strong_count(model) == 1),
+            AssetKey::Texture(path) => self.texture_store.get(path).map_or(true, |texture| Rc::strong_count(texture) == 1),
+            AssetKey::AnimatedTexture(key) => self.animated_texture_store.get(key).map_or(true, |animation| Rc::strong_count(animation) == 1),
+            AssetKey::ShaderProgram(paths) => self.shader_store.get(paths).map_or(true, |shader| Rc::strong_count(shader) == 1),
+        }
+    }
+
+    // Removes the model or texture at the path, along with anything loaded from
+    // inside it such as a glTF file's meshes and images
+    pub fn unload(&mut self, path: &str) -> bool {
+        let prefix = format!("{}#", path);
+        let keys: Vec<AssetKey> = self.model_store
+            .keys()
+            .filter(|key| *key == path || key.starts_with(&prefix))
+            .map(|key| AssetKey::Model(key.clone()))
+            .chain(
+                self.texture_store
+                    .keys()
+                    .filter(|key| *key == path || key.starts_with(&prefix))
+                    .map(|key| AssetKey::Texture(key.clone()))
+            )
+            .chain(
+                self.animated_texture_store
+                    .keys()
+                    .filter(|key| *key == path || key.starts_with(&prefix))
+                    .map(|key| AssetKey::AnimatedTexture(key.clone()))
+            )
+            .collect();
+
+        keys.iter().fold(false, |unloaded, key| self.unload_asset(key) || unloaded)
+    }
+
+    pub fn unload_shader_program(&mut self, paths: &ShaderPathBundle) -> bool {
+        self.unload_asset(&AssetKey::ShaderProgram(paths.clone()))
+    }
+
+    // Removes the face and all of its glyphs, the glyphs' space in the glyph atlas is
+    // reused once their page is evicted
+    pub fn unload_face(&mut self, font_family: &str) -> bool {
+        self.glyph_store.retain(|metadata, _| metadata.font_family != font_family);
+        self.font_render_modes.remove(font_family);
+        self.face_paths.remove(font_family);
+        self.font_handles.remove(&font_family.to_owned());
+
+        self.face_store.remove(font_family).is_some()
+    }
+
+    // Removes every model, texture, animated texture and shader program that nothing
+    // outside the resource manager is using, returning how many were removed
+    pub fn purge_unused(&mut self) -> usize {
+        let mut purged = 0;
+
+        // Models go first since they hold their textures
+        let models: Vec<AssetKey> = self.model_store.keys().map(|key| AssetKey::Model(key.clone())).collect();
+        let textures: Vec<AssetKey> = self.texture_store.keys().map(|key| AssetKey::Texture(key.clone())).collect();
+        let animated_textures: Vec<AssetKey> = self.animated_texture_store.keys().map(|key| AssetKey::AnimatedTexture(key.clone())).collect();
+        let shaders: Vec<AssetKey> = self.shader_store.keys().map(|key| AssetKey::ShaderProgram(key.clone())).collect();
+
+        for keys in [models, textures, animated_textures, shaders] {
+            for key in keys {
+                if self.is_unused(&key) && self.unload_asset(&key) {
+                    purged += 1;
+                }
+            }
+        }
+
+        purged
+    }
+
+    // Every asset loaded or used until end_group() is added to the group, e.g. for a
+    // chapter. Assets can be in more than one group.
+    pub fn begin_group(&mut self, group: &str) {
+        self.resource_cache.set_current_group(Some(group));
+    }
+
+    pub fn end_group(&mut self) {
+        self.resource_cache.set_current_group(None);
+    }
+
+    pub fn get_current_group(&self) -> Option<&str> {
+        self.resource_cache.get_current_group()
+    }
+
+    // Unloads every asset in the group that isn't in another group. Assets something
+    // else is still holding stay cached, but are no longer in the group.
+    pub fn unload_group(&mut self, group: &str) {
+        for key in self.resource_cache.take_group(group) {
+            if self.is_unused(&key) {
+                self.unload_asset(&key);
+            }
+        }
+    }
+
+    // Estimated bytes used by cached models and textures. When a budget is set, the
+    // least recently used assets that nothing else is using are unloaded to stay
+    // under it.
+    pub fn get_memory_usage(&self) -> usize { self.resource_cache.get_memory_usage() }
+    pub fn get_memory_budget(&self) -> Option<usize> { self.resource_cache.get_memory_budget() }
+    pub fn set_memory_budget(&mut self, memory_budget: Option<usize>) {
+        self.resource_cache.set_memory_budget(memory_budget);
+        self.enforce_memory_budget(None);
+    }
+
+    // Keep is never evicted, as it was just added and nothing holds it yet
+    fn enforce_memory_budget(&mut self, keep: Option<&AssetKey>) {
+        let memory_budget = match self.resource_cache.get_memory_budget() {
+            Some(memory_budget) => memory_budget,
+            None => return
+        };
+
+        let mut memory_usage = self.resource_cache.get_memory_usage();
+        for key in self.resource_cache.lru_keys() {
+            if memory_usage <= memory_budget {
+                break;
+            }
+
+            if Some(&key) != keep && self.is_unused(&key) {
+                memory_usage -= self.resource_cache.get_size(&key);
+                self.unload_asset(&key);
+            }
+        }
+    }
+
+    // While hot reloading, the files behind shader programs and textures are watched
+    // and reloaded in place when they change, so everything already holding them
+    // sees the new version. Only 2D textures that keep the same size can be reloaded.
+    pub fn set_hot_reload(&mut self, enabled: bool) {
+        if !enabled {
+            self.file_watcher = None;
+            return;
+        }
+
+        if self.file_watcher.is_none() {
+            let mut file_watcher = FileWatcher::new();
+
+            for (path, _) in self.texture_sources.values() {
+                file_watcher.watch(&self.vfs, path);
+            }
+            for path in self.shader_files.values().flatten() {
+                file_watcher.watch(&self.vfs, path);
+            }
+
+            self.file_watcher = Some(file_watcher);
+        }
+    }
+
+    pub fn is_hot_reloading(&self) -> bool { self.file_watcher.is_some() }
+
+    // Reloads anything whose files changed. Anything that fails to reload keeps its
+    // old version, and the error is kept for take_reload_errors().
+    pub fn update_hot_reload(&mut self) {
+        let changed = match &mut self.file_watcher {
+            Some(file_watcher) => file_watcher.poll(),
+            None => return
+        };
+
+        let mut errors = Vec::new();
+
+        for path in changed {
+            // Every texture loaded from the file, with any options
+            let textures: Vec<(Rc<Texture>, TextureOptions)> = self.texture_sources
+                .iter()
+                .filter(|(_, (source_path, _))| *source_path == path)
+                .filter_map(|(key, (_, options))| self.texture_store.get(key).map(|texture| (Rc::clone(texture), *options)))
+                .collect();
+
+            if !textures.is_empty() {
+                let result = ResourceManager::load_image(&self.vfs, &path).and_then(|image| {
+                    for (texture, options) in textures.iter() {
+                        let mut image = GlImage { bytes: image.bytes.clone(), ..image };
+                        options.apply_to_image(&mut image);
+                        reupload_texture(texture, &image)?;
+                    }
+
+                    Ok(image_has_transparency(&image))
+                });
+
+                // Models already loaded with the texture keep their old classification
+                match result {
+                    Ok(transparent) => for (texture, _) in textures.iter() {
+                        self.set_texture_transparency(texture, transparent);
+                    },
+                    Err(err) => errors.push(EngineError::ResourceManagerError(format!("Failed to reload '{}': {}", path, err))),
+                }
+            }
+
+            // Every program built from the file, directly or through an include
+            let shader_paths: Vec<ShaderPathBundle> = self.shader_files
+                .iter()
+                .filter(|(_, files)| files.contains(&path))
+                .map(|(paths, _)| paths.clone())
+                .collect();
+
+            for paths in shader_paths {
+                let shader_program = Rc::clone(&self.shader_store[&paths]);
+                let result = self.load_shader_code(&paths).and_then(|(code, files)| {
+                    relink_shader_program(&shader_program, &code)
+                        .map_err(|err| EngineError::ResourceManagerError(map_shader_log(&err.to_string(), &files)))?;
+
+                    Ok(files)
+                });
+
+                match result {
+                    // Includes may have been added or removed
+                    Ok(files) => {
+                        if let Some(file_watcher) = &mut self.file_watcher {
+                            for file in files.iter() {
+                                file_watcher.watch(&self.vfs, file);
+                            }
+                        }
+                        self.shader_files.insert(paths, files);
+                    },
+                    Err(err) => errors.push(EngineError::ResourceManagerError(format!("Failed to reload '{}': {}", path, err))),
+                }
+            }
+        }
+
+        self.reload_errors.append(&mut errors);
+    }
+
+    // Errors from files that failed to hot reload since this was last called,
+    // oldest first, e.g. for showing shader compile errors in game
+    pub fn take_reload_errors(&mut self) -> Vec<EngineError> {
+        std::mem::take(&mut self.reload_errors)
+    }
+
+    // Handles to assets that are already loaded, by the key they are cached under
+    pub fn get_handle<T: HandleResource>(&mut self, key: &T::Key) -> Option<Handle<T>> {
+        T::resolve(self, key)?;
+
+        Some(T::handles_mut(self).get_or_insert(key))
+    }
+
+    pub fn get_handle_key<T: HandleResource>(&self, handle: Handle<T>) -> Option<&T::Key> {
+        T::handles(self).get_key(handle)
+    }
+
+    // Resolves the handle to what it currently points to, None once its asset is unloaded
+    pub fn get<T: HandleResource>(&self, handle: Handle<T>) -> Option<Rc<T>> {
+        T::resolve(self, self.get_handle_key(handle)?)
+    }
+
+    pub fn is_handle_valid<T: HandleResource>(&self, handle: Handle<T>) -> bool {
+        self.get_handle_key(handle).is_some()
+    }
+
+    // How many references to the asset there are outside the resource manager,
+    // e.g. held by widgets and scenes. Fonts are only used by name, so aren't counted.
+    pub fn get_usage<T: HandleResource>(&self, handle: Handle<T>) -> Option<usize> {
+        T::usage(self, self.get_handle_key(handle)?)
+    }
+
+    // Every handle of the type that has been given out and is still valid
+    pub fn get_handles<T: HandleResource>(&self) -> Vec<Handle<T>> {
+        T::handles(self).get_handles()
+    }
+
+    // Points the handle at another asset, e.g. a placeholder texture until the real
+    // one has loaded. Anything that resolves the handle from then on gets the new
+    // asset, while anything already holding the old one keeps it. Swapped in
+    // assets aren't hot reloaded.
+    pub fn swap<T: HandleResource>(&mut self, handle: Handle<T>, asset: Rc<T>) -> Result<(), EngineError> {
+        let key = self.get_handle_key(handle)
+            .cloned()
+            .ok_or_else(|| EngineError::ResourceManagerError(format!("{:?} points to an asset that has been unloaded!", handle)))?;
+
+        T::swap(self, &key, asset)
+    }
+
+    pub fn load_texture_2d_handle(&mut self, path: &str, options: TextureOptions) -> Result<Handle<Texture>, EngineError> {
+        self.load_texture_2d(path, options)?;
+
+        Ok(self.texture_handles.get_or_insert(&options.cache_key(path)))
+    }
+
+    pub fn load_model_handle(&mut self, path: &str) -> Result<Handle<Model>, EngineError> {
+        self.load_model(path)?;
+
+        Ok(self.model_handles.get_or_insert(&path.to_owned()))
+    }
+
+    pub fn load_gltf_handle(&mut self, path: &str) -> Result<Handle<Model>, EngineError> {
+        self.load_gltf(path)?;
+
+        Ok(self.model_handles.get_or_insert(&path.to_owned()))
+    }
+
+    pub fn load_shader_program_handle(&mut self, paths: ShaderPathBundle) -> Result<Handle<ShaderProgram>, EngineError> {
+        self.load_shader_program(paths.clone())?;
+
+        Ok(self.shader_handles.get_or_insert(&paths))
+    }
+
+    pub fn load_face_handle(&mut self, path: &str) -> Result<Handle<Font>, EngineError> {
+        let family = self._load_face(path)?;
+
+        Ok(self.font_handles.get_or_insert(&family))
+    }
+
+    // Loads the manifest entry with the name, see AssetManifest
+    pub fn load_named_handle<T: HandleResource>(&mut self, name: &str) -> Result<Handle<T>, EngineError> {
+        let key = T::load_named(self, name)?;
+
+        Ok(T::handles_mut(self).get_or_insert(&key))
+    }
+
+    // Name of the manifest entry the handle's asset is from, None for assets
+    // loaded by path
+    pub fn get_handle_name<T: HandleResource>(&self, handle: Handle<T>) -> Option<String> {
+        T::find_name(self, self.get_handle_key(handle)?)
+    }
+
+    // What to store to save the handle, e.g. in a save file. None once its asset is unloaded.
+    pub fn save_handle<T: HandleResource>(&self, handle: Handle<T>) -> Option<AssetRef<T::Key>> {
+        let key = self.get_handle_key(handle)?;
+
+        match T::find_name(self, key) {
+            Some(name) => Some(AssetRef::Name(name)),
+            None => Some(AssetRef::Key(key.clone()))
+        }
+    }
+
+    // Named assets are loaded if they aren't already, while assets saved by key
+    // have to be loaded first, the same as with get_handle()
+    pub fn load_handle<T: HandleResource>(&mut self, asset_ref: &AssetRef<T::Key>) -> Result<Handle<T>, EngineError> {
+        match asset_ref {
+            AssetRef::Name(name) => self.load_named_handle(name),
+            AssetRef::Key(key) => self.get_handle(key).ok_or_else(|| {
+                EngineError::ResourceManagerError(format!("{:?} isn't loaded, so its saved handle can't be loaded!", key))
+            })
+        }
+    }
+
+    // Assets can be queued to be read and decoded on a background thread as part
+    // of a named group, e.g. everything a chapter needs. update_loading() has to be
+    // called regularly (CSEngine::run() does this every frame) to upload finished
+    // assets, after which they are cached the same as if they were loaded directly.
+    fn queue(&mut self, group: &str, description: String, request: AssetRequest, slot: PendingSlot) -> Result<(), EngineError> {
+        if self.asset_loader.is_none() {
+            self.asset_loader = Some(AssetLoader::new()?);
+        }
+
+        // Progress starts again for each batch of loading
+        if self.pending_jobs.is_empty() {
+            self.loading_progress = (0, 0);
+            self.loading_feedback = format!("Loading {}", description);
+        }
+
+        let id = self.asset_loader.as_mut().unwrap().send(request, self.vfs.clone())?;
+        self.pending_jobs.insert(id, PendingJob { group: group.to_owned(), description, slot });
+        self.loading_progress.1 += 1;
+
+        Ok(())
+    }
+
+    pub fn queue_texture_2d(&mut self, group: &str, path: &str, options: TextureOptions) -> Result<Pending<Texture>, EngineError> {
+        let key = options.cache_key(path);
+        self.resource_cache.add_to_group(group, AssetKey::Texture(key.clone()));
+
+        if let Some(texture) = self.texture_store.get(&key) {
+            return Ok(Pending::ready(Rc::clone(texture)));
+        }
+
+        let pending = Pending::loading();
+        let slot = PendingSlot::Texture2D(path.to_owned(), options, pending.clone());
+        self.queue(group, path.to_owned(), AssetRequest::Texture2D(path.to_owned()), slot)?;
+
+        Ok(pending)
+    }
+
+    pub fn queue_texture_cubemap(&mut self, group: &str, path: &str, options: TextureOptions) -> Result<Pending<Texture>, EngineError> {
+        let key = options.cache_key(path);
+        self.resource_cache.add_to_group(group, AssetKey::Texture(key.clone()));
+
+        if let Some(texture) = self.texture_store.get(&key) {
+            return Ok(Pending::ready(Rc::clone(texture)));
+        }
+
+        let pending = Pending::loading();
+        let slot = PendingSlot::TextureCubemap(path.to_owned(), options, pending.clone());
+        self.queue(group, path.to_owned(), AssetRequest::TextureCubemap(path.to_owned()), slot)?;
+
+        Ok(pending)
+    }
+
+    pub fn queue_model(&mut self, group: &str, path: &str) -> Result<Pending<Model>, EngineError> {
+        self.resource_cache.add_to_group(group, AssetKey::Model(path.to_owned()));
+
+        if let Some(model) = self.model_store.get(path) {
+            return Ok(Pending::ready(Rc::clone(model)));
+        }
+
+        let pending = Pending::loading();
+        let slot = PendingSlot::Model(path.to_owned(), pending.clone());
+        self.queue(group, path.to_owned(), AssetRequest::Model(path.to_owned()), slot)?;
+
+        Ok(pending)
+    }
+
+    pub fn queue_gltf(&mut self, group: &str, path: &str) -> Result<Pending<Model>, EngineError> {
+        self.resource_cache.add_to_group(group, AssetKey::Model(path.to_owned()));
+
+        if let Some(model) = self.model_store.get(path) {
+            return Ok(Pending::ready(Rc::clone(model)));
+        }
+
+        let pending = Pending::loading();
+        let slot = PendingSlot::Model(path.to_owned(), pending.clone());
+        self.queue(group, path.to_owned(), AssetRequest::Gltf(path.to_owned()), slot)?;
+
+        Ok(pending)
+    }
+
+    // Background version of preload_font(), the family has to be loaded with load_face() first
+    pub fn queue_font(
+        &mut self,
+        group: &str,
+        font_family: &str,
+        font_sizes: &[u32],
+        characters: &CharacterSet
+    ) -> Result<(), EngineError> {
+        let path = self.face_paths
+            .get(font_family)
+            .ok_or(EngineError::FontFamilyNotFound(font_family.to_owned()))?
+            .clone();
+
+        let request = AssetRequest::Glyphs {
+            font_family: font_family.to_owned(),
+            path,
+            font_sizes: font_sizes.to_vec(),
+            chars: characters.chars(),
+            content_scale: self.content_scale,
+            mode: self.get_font_render_mode(font_family)
+        };
+        let slot = PendingSlot::Glyphs { content_scale: self.content_scale };
+
+        self.queue(group, format!("{} glyphs", font_family), request, slot)
+    }
+
+    // Uploads everything that has finished loading without waiting for the rest
+    pub fn update_loading(&mut self) {
+        while let Some(result) = self.asset_loader.as_ref().and_then(|loader| loader.try_recv()) {
+            self.finish_job(result);
+        }
+    }
+
+    // Blocks until every asset queued in the group is loaded, returning the
+    // errors of any that failed
+    pub fn wait_for_group(&mut self, group: &str) -> Result<(), EngineError> {
+        while !self.is_group_loaded(group) {
+            match self.asset_loader.as_ref().and_then(|loader| loader.recv()) {
+                Some(result) => self.finish_job(result),
+                None => {
+                    return Err(EngineError::ResourceManagerError(String::from("The asset loading thread has stopped!")))
+                }
+            }
+        }
+
+        match self.group_errors.remove(group) {
+            Some(errors) => Err(EngineError::ResourceManagerError(format!("Failed to load group '{}':\n{}", group, errors.join("\n")))),
+            None => Ok(())
+        }
+    }
+
+    pub fn is_group_loaded(&self, group: &str) -> bool {
+        !self.pending_jobs.values().any(|job| job.group == group)
+    }
+
+    // Assets loaded and queued since loading last finished, for loading screens
+    pub fn get_loading_progress(&self) -> (usize, usize) { self.loading_progress }
+
+    // Describes what is currently being loaded
+    pub fn get_loading_feedback(&self) -> &str { &self.loading_feedback }
+
+    // Adds the assets declared in a manifest, see AssetManifest. Games can split
+    // theirs up, e.g. one per chapter, with later entries replacing earlier ones
+    // of the same name. Nothing is loaded until it is used or its group is queued.
+    pub fn load_manifest(&mut self, path: &str) -> Result<(), EngineError> {
+        let manifest = AssetManifest::parse(path, &self.vfs.read_to_string(path)?)?;
+        self.manifest.merge(manifest);
+
+        Ok(())
+    }
+
+    pub fn get_manifest(&self) -> &AssetManifest { &self.manifest }
+
+    // Checks every file in the manifest exists and can be read, e.g. before the
+    // game starts, returning an error for each asset that can't be loaded
+    pub fn validate_manifest(&self) -> Vec<EngineError> {
+        self.manifest.validate(&self.vfs, &self.face_library)
+    }
+
+    // Assets loaded by name are added to their group, so unload_group() unloads
+    // them along with everything queued in it
+    fn add_to_manifest_group(&mut self, group: Option<String>, key: AssetKey) {
+        if let Some(group) = group {
+            self.resource_cache.add_to_group(&group, key);
+        }
+    }
+
+    pub fn load_named_texture(&mut self, name: &str) -> Result<Rc<Texture>, EngineError> {
+        let entry = self.manifest.get_texture(name)?.clone();
+        let options = entry.options.to_options();
+
+        let texture = if entry.cubemap {
+            self.load_texture_cubemap(&entry.path, options)?
+        } else {
+            self.load_texture_2d(&entry.path, options)?
+        };
+        self.add_to_manifest_group(entry.group, AssetKey::Texture(options.cache_key(&entry.path)));
+
+        Ok(texture)
+    }
+
+    pub fn load_named_animation(&mut self, name: &str) -> Result<Rc<AnimatedTexture>, EngineError> {
+        let entry = self.manifest.get_animation(name)?.clone();
+        let options = entry.options.to_options();
+
+        let (animation, key) = match entry.sprite_sheet {
+            Some(sheet) => (self.load_sprite_sheet(&entry.path, sheet, options)?, sheet.cache_key(&entry.path, &options)),
+            None => (self.load_animated_texture(&entry.path, options)?, options.cache_key(&entry.path))
+        };
+        self.add_to_manifest_group(entry.group, AssetKey::AnimatedTexture(key));
+
+        Ok(animation)
+    }
+
+    pub fn load_named_model(&mut self, name: &str) -> Result<Rc<Model>, EngineError> {
+        let entry = self.manifest.get_model(name)?.clone();
+
+        let model = if entry.is_gltf() {
+            self.load_gltf(&entry.path)?
+        } else {
+            self.load_model(&entry.path)?
+        };
+        self.add_to_manifest_group(entry.group, AssetKey::Model(entry.path));
+
+        Ok(model)
+    }
+
+    pub fn load_named_shader_program(&mut self, name: &str) -> Result<Rc<ShaderProgram>, EngineError> {
+        let entry = self.manifest.get_shader(name)?.clone();
+        let paths = entry.to_paths();
+
+        let shader_program = self.load_shader_program(paths.clone())?;
+        self.add_to_manifest_group(entry.group, AssetKey::ShaderProgram(paths));
+
+        Ok(shader_program)
+    }
+
+    // Returns the font's family name, which text styles use. The face is only
+    // loaded the first time.
+    pub fn load_named_face(&mut self, name: &str) -> Result<String, EngineError> {
+        let entry = self.manifest.get_font(name)?.clone();
+
+        let loaded_family = self.face_paths
+            .iter()
+            .find(|(_, path)| **path == entry.path)
+            .map(|(family, _)| family.clone());
+        let family = match loaded_family {
+            Some(family) => family,
+            None => self._load_face(&entry.path)?
+        };
+
+        // Setting the mode drops the family's glyphs, so only do it when it changes
+        let mode = entry.render_mode();
+        if self.get_font_render_mode(&family) != mode {
+            self.set_font_render_mode(&family, mode);
+        }
+
+        Ok(family)
+    }
+
+    // Queues every texture and model in the group to load in the background, e.g.
+    // for a chapter, along with glyphs for each font's sizes. Shaders, animations
+    // and font faces are loaded straight away, as they are small or need the GL
+    // thread. Wait on it with wait_for_group(), or show get_loading_progress().
+    pub fn queue_manifest_group(&mut self, group: &str) -> Result<(), EngineError> {
+        let assets = self.manifest.get_group(group);
+        if assets.is_empty() {
+            return Err(EngineError::ManifestError(format!("There are no assets in group '{}'!", group)));
+        }
+
+        for entry in assets.textures.values() {
+            let options = entry.options.to_options();
+
+            if entry.cubemap {
+                self.queue_texture_cubemap(group, &entry.path, options)?;
+            } else {
+                self.queue_texture_2d(group, &entry.path, options)?;
+            }
+        }
+
+        for entry in assets.models.values() {
+            if entry.is_gltf() {
+                self.queue_gltf(group, &entry.path)?;
+            } else {
+                self.queue_model(group, &entry.path)?;
+            }
+        }
+
+        for name in assets.animations.keys() {
+            self.load_named_animation(name)?;
+        }
+
+        for name in assets.shaders.keys() {
+            self.load_named_shader_program(name)?;
+        }
+
+        for (name, entry) in &assets.fonts {
+            let family = self.load_named_face(name)?;
+
+            if !entry.sizes.is_empty() {
+                self.queue_font(group, &family, &entry.sizes, &entry.get_characters())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Blocking version of queue_manifest_group()
+    pub fn load_manifest_group(&mut self, group: &str) -> Result<(), EngineError> {
+        self.queue_manifest_group(group)?;
+        self.wait_for_group(group)
+    }
+
+    fn finish_job(&mut self, result: LoadResult) {
+        let job = match self.pending_jobs.remove(&result.id) {
+            Some(job) => job,
+            None => return
+        };
+
+        let error = match (job.slot, result.data) {
+            (PendingSlot::Texture2D(path, options, pending), Ok(AssetData::Image(image))) => {
+                let texture = match self.texture_store.get(&options.cache_key(&path)) {
+                    Some(texture) => Ok(Rc::clone(texture)),
+                    None => self.upload_texture_2d(&path, image, options)
+                };
+                set_pending(&pending, texture)
+            },
+            (PendingSlot::TextureCubemap(path, options, pending), Ok(AssetData::Image(image))) => {
+                let texture = match self.texture_store.get(&options.cache_key(&path)) {
+                    Some(texture) => Ok(Rc::clone(texture)),
+                    None => self.upload_texture_cubemap(&path, image, options)
+                };
+                set_pending(&pending, texture)
+            },
+            (PendingSlot::Model(path, pending), Ok(data)) => {
+                let model = match (self.model_store.get(&path), data) {
+                    (Some(model), _) => Ok(Rc::clone(model)),
+                    (None, AssetData::Obj(data, images)) => self.upload_obj(&path, data, images),
+                    (None, AssetData::Gltf(document, buffers, images)) => {
+                        self.upload_gltf(&path, document, buffers, images)
+                    },
+                    (None, _) => Err(EngineError::ResourceManagerError(format!("'{}' was not loaded as a model!", path)))
+                };
+                set_pending(&pending, model)
+            },
+            (PendingSlot::Glyphs { content_scale }, Ok(AssetData::Glyphs(glyphs))) => {
+                let mut error = None;
+
+                // Otherwise they were rasterized at a scale that is no longer used
+                if content_scale == self.content_scale {
+                    for (metadata, glyph) in glyphs {
+                        if self.glyph_store.contains_key(&metadata) {
+                            continue;
+                        }
+
+                        if let Err(err) = self.insert_glyph(metadata, glyph) {
+                            error = Some(err.to_string());
+                            break;
+                        }
+                    }
+                }
+
+                error
+            },
+            (PendingSlot::Texture2D(_, _, pending) | PendingSlot::TextureCubemap(_, _, pending), Err(err)) => {
+                pending.set_state(LoadState::Failed(err.clone()));
+                Some(err)
+            },
+            (PendingSlot::Model(_, pending), Err(err)) => {
+                pending.set_state(LoadState::Failed(err.clone()));
+                Some(err)
+            },
+            (_, Err(err)) => Some(err),
+            (_, Ok(_)) => Some(format!("'{}' was loaded as the wrong type of asset!", job.description)),
+        };
+
+        if let Some(error) = error {
+            self.group_errors.entry(job.group).or_default().push(format!("{}: {}", job.description, error));
+        }
+
+        self.loading_progress.0 += 1;
+        // Jobs are done in order, so the next one is the oldest
+        self.loading_feedback = match self.pending_jobs.keys().min() {
+            Some(next) => format!("Loading {}", self.pending_jobs[next].description),
+            None => String::from("Finished loading")
+        };
+    }
 
     pub fn create_model(
         &self,
@@ -380,24 +2105,337 @@ impl ResourceManager {
         model_transforms: Vec<Matrix4<f32>>,
         meshes: Vec<Mesh>
     ) -> Result<Box<dyn ModelTrait>, EngineError> {
+        self.prepare_graphics_library("model")?;
+
         match self.gl {
-            GraphicsLibrary::OpenGL4_6(_, exts) => if exts.supports_bindless {
+            GraphicsLibrary::OpenGL4_6(_, exts) if exts.supports_bindless => {
                 Ok(Box::new(BindlessModel::new(vertices, indices, model_transforms, meshes)))
-            } else {
-                Ok(Box::new(MultiBindModel::new(vertices, indices, model_transforms, meshes)))
             },
-            GraphicsLibrary::None => {
-                return Err(EngineError::ResourceManagerError(String::from("Trying to load model without selected graphics library!")))
+            _ => Ok(Box::new(MultiBindModel::new(vertices, indices, model_transforms, meshes)))
+        }
+    }
+}
+
+impl HandleResource for Texture {
+    type Key = String;
+
+    fn handles(resource_manager: &ResourceManager) -> &HandleTable<Self> { &resource_manager.texture_handles }
+    fn handles_mut(resource_manager: &mut ResourceManager) -> &mut HandleTable<Self> { &mut resource_manager.texture_handles }
+
+    fn resolve(resource_manager: &ResourceManager, key: &String) -> Option<Rc<Self>> {
+        resource_manager.texture_store.get(key).map(Rc::clone)
+    }
+
+    fn usage(resource_manager: &ResourceManager, key: &String) -> Option<usize> {
+        resource_manager.texture_store.get(key).map(|texture| Rc::strong_count(texture) - 1)
+    }
+
+    fn swap(resource_manager: &mut ResourceManager, key: &String, asset: Rc<Self>) -> Result<(), EngineError> {
+        // Transparency is kept by texture, so stays right for both
+        resource_manager.texture_sources.remove(key);
+        resource_manager.texture_store.insert(key.clone(), asset);
+
+        Ok(())
+    }
+
+    fn load_named(resource_manager: &mut ResourceManager, name: &str) -> Result<String, EngineError> {
+        let entry = resource_manager.manifest.get_texture(name)?;
+        let key = entry.options.to_options().cache_key(&entry.path);
+        resource_manager.load_named_texture(name)?;
+
+        Ok(key)
+    }
+
+    fn find_name(resource_manager: &ResourceManager, key: &String) -> Option<String> {
+        resource_manager.manifest.textures
+            .iter()
+            .find(|(_, entry)| entry.options.to_options().cache_key(&entry.path) == *key)
+            .map(|(name, _)| name.clone())
+    }
+}
+
+impl HandleResource for Model {
+    type Key = String;
+
+    fn handles(resource_manager: &ResourceManager) -> &HandleTable<Self> { &resource_manager.model_handles }
+    fn handles_mut(resource_manager: &mut ResourceManager) -> &mut HandleTable<Self> { &mut resource_manager.model_handles }
+
+    fn resolve(resource_manager: &ResourceManager, key: &String) -> Option<Rc<Self>> {
+        resource_manager.model_store.get(key).map(Rc::clone)
+    }
+
+    fn usage(resource_manager: &ResourceManager, key: &String) -> Option<usize> {
+        resource_manager.model_store.get(key).map(|model| Rc::strong_count(model) - 1)
+    }
+
+    fn swap(resource_manager: &mut ResourceManager, key: &String, asset: Rc<Self>) -> Result<(), EngineError> {
+        // Transparent parts are kept by model, so the new one keeps its own
+        if let Some(replaced) = resource_manager.model_store.insert(key.clone(), asset) {
+            resource_manager.transparent_parts.remove(&Rc::as_ptr(&replaced));
+        }
+
+        Ok(())
+    }
+
+    fn load_named(resource_manager: &mut ResourceManager, name: &str) -> Result<String, EngineError> {
+        let key = resource_manager.manifest.get_model(name)?.path.clone();
+        resource_manager.load_named_model(name)?;
+
+        Ok(key)
+    }
+
+    fn find_name(resource_manager: &ResourceManager, key: &String) -> Option<String> {
+        resource_manager.manifest.models
+            .iter()
+            .find(|(_, entry)| entry.path == *key)
+            .map(|(name, _)| name.clone())
+    }
+}
+
+impl HandleResource for ShaderProgram {
+    type Key = ShaderPathBundle;
+
+    fn handles(resource_manager: &ResourceManager) -> &HandleTable<Self> { &resource_manager.shader_handles }
+    fn handles_mut(resource_manager: &mut ResourceManager) -> &mut HandleTable<Self> { &mut resource_manager.shader_handles }
+
+    fn resolve(resource_manager: &ResourceManager, key: &ShaderPathBundle) -> Option<Rc<Self>> {
+        resource_manager.shader_store.get(key).map(Rc::clone)
+    }
+
+    fn usage(resource_manager: &ResourceManager, key: &ShaderPathBundle) -> Option<usize> {
+        resource_manager.shader_store.get(key).map(|shader_program| Rc::strong_count(shader_program) - 1)
+    }
+
+    fn swap(resource_manager: &mut ResourceManager, key: &ShaderPathBundle, asset: Rc<Self>) -> Result<(), EngineError> {
+        resource_manager.shader_files.remove(key);
+        resource_manager.shader_store.insert(key.clone(), asset);
+
+        Ok(())
+    }
+
+    fn load_named(resource_manager: &mut ResourceManager, name: &str) -> Result<ShaderPathBundle, EngineError> {
+        let key = resource_manager.manifest.get_shader(name)?.to_paths();
+        resource_manager.load_named_shader_program(name)?;
+
+        Ok(key)
+    }
+
+    fn find_name(resource_manager: &ResourceManager, key: &ShaderPathBundle) -> Option<String> {
+        resource_manager.manifest.shaders
+            .iter()
+            .find(|(_, entry)| entry.to_paths() == *key)
+            .map(|(name, _)| name.clone())
+    }
+}
+
+impl HandleResource for Font {
+    type Key = String;
+
+    fn handles(resource_manager: &ResourceManager) -> &HandleTable<Self> { &resource_manager.font_handles }
+    fn handles_mut(resource_manager: &mut ResourceManager) -> &mut HandleTable<Self> { &mut resource_manager.font_handles }
+
+    fn resolve(resource_manager: &ResourceManager, key: &String) -> Option<Rc<Self>> {
+        resource_manager.face_store.get(key)?;
+
+        Some(
+            Rc::new(
+                Font {
+                    family: key.clone(),
+                    path: resource_manager.face_paths.get(key).cloned().unwrap_or_default()
+                }
+            )
+        )
+    }
+
+    fn usage(_resource_manager: &ResourceManager, _key: &String) -> Option<usize> { None }
+
+    fn swap(_resource_manager: &mut ResourceManager, key: &String, _asset: Rc<Self>) -> Result<(), EngineError> {
+        Err(EngineError::ResourceManagerError(format!("Fonts can't be swapped, load the new face as the '{}' family instead!", key)))
+    }
+
+    fn load_named(resource_manager: &mut ResourceManager, name: &str) -> Result<String, EngineError> {
+        resource_manager.load_named_face(name)
+    }
+
+    // Fonts are cached under their family, so are matched by the face's path
+    fn find_name(resource_manager: &ResourceManager, key: &String) -> Option<String> {
+        let path = resource_manager.face_paths.get(key)?;
+
+        resource_manager.manifest.fonts
+            .iter()
+            .find(|(_, entry)| entry.path == *path)
+            .map(|(name, _)| name.clone())
+    }
+}
+
+// Anything in the mesh that would be read out of bounds is an error instead of a
+// panic. Returns the mesh's material.
+fn check_obj_mesh(path: &str, name: &str, mesh: &tobj::Mesh, materials: &[tobj::Material]) -> Result<Option<tobj::Material>, EngineError> {
+    let error = |problem: String| {
+        EngineError::ResourceManagerError(format!("OBJ file '{}' has a mesh '{}' that {}!", path, name, problem))
+    };
+
+    let num_vertices = mesh.positions.len() / 3;
+    if mesh.positions.len() % 3 != 0 {
+        return Err(error(format!("has {} position values, which isn't a multiple of 3", mesh.positions.len())));
+    }
+    if !mesh.normals.is_empty() && mesh.normals.len() != mesh.positions.len() {
+        return Err(error(format!("has {} normals for {} vertices", mesh.normals.len() / 3, num_vertices)));
+    }
+    if !mesh.texcoords.is_empty() && mesh.texcoords.len() != num_vertices * 2 {
+        return Err(error(format!("has {} UVs for {} vertices", mesh.texcoords.len() / 2, num_vertices)));
+    }
+    if mesh.indices.len() % 3 != 0 {
+        return Err(error(String::from("isn't made of triangles")));
+    }
+    if let Some(index) = mesh.indices.iter().find(|index| **index as usize >= num_vertices) {
+        return Err(error(format!("uses vertex {} but only has {} vertices", index, num_vertices)));
+    }
+
+    match mesh.material_id {
+        Some(material_id) => Ok(Some(
+            materials
+                .get(material_id)
+                .ok_or_else(|| error(format!("uses material {} but there are only {}", material_id, materials.len())))?
+                .clone()
+        )),
+        None => Ok(None)
+    }
+}
+
+// Roughly how much GPU memory the model's buffers use
+fn model_size(vertices: &[Vertex], indices: &[u32]) -> usize {
+    vertices.len() * std::mem::size_of::<Vertex>() + indices.len() * std::mem::size_of::<u32>()
+}
+
+// Returns the error message if there was one
+fn set_pending<T>(pending: &Pending<T>, result: Result<Rc<T>, EngineError>) -> Option<String> {
+    match result {
+        Ok(asset) => {
+            pending.set_state(LoadState::Ready(asset));
+            None
+        },
+        Err(err) => {
+            let message = err.to_string();
+            pending.set_state(LoadState::Failed(message.clone()));
+            Some(message)
+        }
+    }
+}
+
+// Changes made to glTF maps before they are uploaded, so they suit the engine's materials
+enum GltfImageConversion {
+    None,
+    // Base colour factor, which multiplies the base colour texture, and the alpha
+    // mode. Opaque materials are made fully opaque and masked ones cut at the cutoff.
+    BaseColour { factor: [f32; 4], alpha_mode: gltf::material::AlphaMode, alpha_cutoff: f32 },
+    // Metallic-roughness maps store roughness in green and metalness in blue,
+    // which is turned into a specular map
+    Specular { metallic: f32, roughness: f32 }
+}
+
+impl GltfImageConversion {
+    fn key_suffix(&self) -> String {
+        match self {
+            GltfImageConversion::None => String::new(),
+            GltfImageConversion::BaseColour { factor, alpha_mode, alpha_cutoff } => {
+                let [r, g, b, a] = factor;
+                let tint = if *factor == [1.0; 4] { String::new() } else { format!("#tint_{}_{}_{}_{}", r, g, b, a) };
+
+                match alpha_mode {
+                    gltf::material::AlphaMode::Opaque => format!("{}#opaque", tint),
+                    gltf::material::AlphaMode::Mask => format!("{}#mask_{}", tint, alpha_cutoff),
+                    gltf::material::AlphaMode::Blend => tint
+                }
+            },
+            GltfImageConversion::Specular { metallic, roughness } => format!("#specular_{}_{}", metallic, roughness)
+        }
+    }
+
+    fn apply(&self, image: GlImage, srgb: bool) -> GlImage {
+        match self {
+            GltfImageConversion::None => image,
+            GltfImageConversion::BaseColour { factor, alpha_mode, alpha_cutoff } => {
+                let image = if *factor == [1.0; 4] { image } else { tint_image(&image, *factor, srgb) };
+
+                match alpha_mode {
+                    gltf::material::AlphaMode::Opaque => cut_alpha(image, None),
+                    gltf::material::AlphaMode::Mask => cut_alpha(image, Some(*alpha_cutoff)),
+                    gltf::material::AlphaMode::Blend => image
+                }
             },
+            GltfImageConversion::Specular { metallic, roughness } => metallic_roughness_to_specular(&image, *metallic, *roughness)
         }
     }
 }
 
-#[derive(PartialEq, Eq, Hash)]
+// Samplers without filters are left to the defaults
+fn gltf_texture_options(sampler: &gltf::texture::Sampler, srgb: bool) -> TextureOptions {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+    let filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => TextureFilter::Nearest,
+        _ => TextureFilter::Linear
+    };
+    let mipmaps = !matches!(sampler.min_filter(), Some(MinFilter::Nearest | MinFilter::Linear));
+    // Only one wrap mode is supported, so the horizontal one is used
+    let wrap = match sampler.wrap_s() {
+        WrappingMode::ClampToEdge => TextureWrap::ClampToEdge,
+        WrappingMode::MirroredRepeat => TextureWrap::MirroredRepeat,
+        WrappingMode::Repeat => TextureWrap::Repeat,
+    };
+
+    TextureOptions { filter, mipmaps, wrap, srgb, ..Default::default() }
+}
+
+fn gltf_mesh_key(path: &str, mesh: usize) -> String {
+    format!("{}#mesh{}", path, mesh)
+}
+
+// Walks the node tree, pairing every primitive with its node's global transform
+fn collect_gltf_primitives<'a>(
+    node: gltf::Node<'a>,
+    parent_transform: Matrix4<f32>,
+    primitives: &mut Vec<(gltf::Primitive<'a>, Matrix4<f32>)>
+) {
+    let transform = parent_transform * Matrix4::from(node.transform().matrix());
+
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives() {
+            primitives.push((primitive, transform));
+        }
+    }
+
+    for child in node.children() {
+        collect_gltf_primitives(child, transform, primitives);
+    }
+}
+
+// Shader files can #include others, and are given the defines after their
+// #version, so one file can be built into several variants. Each set of defines
+// is loaded and cached as a separate program.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ShaderPathBundle {
     pub vertex: Option<String>,
     pub geometry: Option<String>,
-    pub fragment: Option<String>
+    pub fragment: Option<String>,
+    pub defines: Vec<(String, String)>
+}
+
+// Bitmap glyphs are sharpest at their font size, while signed distance field glyphs
+// stay sharp when scaled and can have outlines and glows, but need the SDF text
+// shader. Spread is how many pixels the field extends past the glyph's edge,
+// which limits how wide outlines and glows can be, up to MAX_SDF_SPREAD.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FontRenderMode {
+    Bitmap,
+    Sdf { spread: u32 }
+}
+
+impl Default for FontRenderMode {
+    fn default() -> Self {
+        FontRenderMode::Bitmap
+    }
 }
 
 #[derive(PartialEq, Eq, Hash)]
@@ -407,9 +2445,100 @@ pub struct GlyphMetaDeta {
     pub glyph: char
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CharacterSet {
+    Ascii, // Printable characters only
+    Latin1, // Printable characters of ASCII and the Latin-1 supplement
+    Range(char, char), // Inclusive, e.g. ('\u{3040}', '\u{309F}') for hiragana
+    Chars(String) // Every character used in a script, duplicates are ignored
+}
+
+impl CharacterSet {
+    pub fn chars(&self) -> Vec<char> {
+        let mut chars: Vec<char> = match self {
+            CharacterSet::Ascii => (' '..='~').collect(),
+            CharacterSet::Latin1 => (' '..='~').chain('\u{A0}'..='\u{FF}').collect(),
+            CharacterSet::Range(start, end) => (*start..=*end).collect(),
+            CharacterSet::Chars(string) => string.chars().filter(|c| !c.is_control()).collect(),
+        };
+
+        chars.sort_unstable();
+        chars.dedup();
+        chars
+    }
+}
+
+// In pixels, descender is negative as it goes below the baseline
+#[derive(Debug, Clone, Copy)]
+pub struct FaceMetrics {
+    pub ascender: f32,
+    pub descender: f32,
+    pub line_height: f32
+}
+
+// Glyph bitmap before it is added to the glyph atlas, tightly packed
+pub(crate) struct RasterizedGlyph {
+    pub bytes: Vec<u8>,
+    pub size: Vector2<i32>,
+    pub bearing: Vector2<i32>,
+    pub advance: i64
+}
+
+// OBJ file read into memory, with materials' textures still to be loaded
+pub(crate) struct ObjData {
+    pub directory: String,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub meshes: Vec<ObjMesh>
+}
+
+pub(crate) struct ObjMesh {
+    pub offset: usize,
+    pub count: i32,
+    pub material: Option<tobj::Material>
+}
+
 pub struct GlyphData {
-    pub texture: Texture,
+    pub texture: Rc<Texture>, // Atlas page the glyph is stored on
+    pub page: usize,
+    pub uv: Vector4<f32>, // Left, Top, Right, Bottom
     pub size: Vector2<i32>,
     pub bearing: Vector2<i32>,
     pub advance: i64
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A triangle, with the mesh changed by each test
+    fn triangle() -> tobj::Mesh {
+        tobj::Mesh {
+            positions: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        }
+    }
+
+    fn check_error(mesh: tobj::Mesh) -> String {
+        check_obj_mesh("test.obj", "triangle", &mesh, &[]).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn obj_meshes_are_checked() {
+        assert!(check_obj_mesh("test.obj", "triangle", &triangle(), &[]).unwrap().is_none());
+
+        let error = check_error(tobj::Mesh { indices: vec![0, 1, 3], ..triangle() });
+        assert!(error.contains("mesh 'triangle' that uses vertex 3 but only has 3 vertices"), "{}", error);
+
+        let error = check_error(tobj::Mesh { texcoords: vec![0.0, 0.0], ..triangle() });
+        assert!(error.contains("has 1 UVs for 3 vertices"), "{}", error);
+
+        let error = check_error(tobj::Mesh { material_id: Some(2), ..triangle() });
+        assert!(error.contains("uses material 2 but there are only 0"), "{}", error);
+
+        let error = check_error(tobj::Mesh { indices: vec![0, 1], ..triangle() });
+        assert!(error.contains("isn't made of triangles"), "{}", error);
+    }
+}
@@ -1,5 +1,5 @@
 use silver_gl::RenderPipeline;
-use crate::EngineError;
+use crate::{EngineError, DrawRecorder, ResourceManager};
 
 // TODO: rewrite to include children tree as well
 pub trait Scene {
@@ -10,5 +10,14 @@ pub trait Scene {
     fn get_render_pipeline_mut(&mut self) -> &mut Box<dyn RenderPipeline>;
     fn set_render_pipeline(&mut self, render_pipeline: Box<dyn RenderPipeline>);
 
+    // Called once a frame before drawing with the seconds since the last frame
+    fn update(&mut self, _delta_time: f32) {}
+
+    // Called once a frame before drawing, so widgets can rebuild anything made from
+    // resources that have since been dropped
+    fn refresh_layout(&mut self, _resource_manager: &mut ResourceManager) -> Result<(), EngineError> { Ok(()) }
+
     fn draw(&mut self) -> Result<(), EngineError>;
+    // Headless counterpart to draw, used with GraphicsLibrary::None
+    fn record(&mut self, recorder: &mut DrawRecorder);
 }
\ No newline at end of file
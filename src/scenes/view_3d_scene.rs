@@ -1,13 +1,21 @@
 use std::rc::Rc;
 use cgmath::{Matrix4, SquareMatrix};
 use silver_gl::{Skybox, ShaderProgram, RenderPipeline, gl};
-use crate::{Camera, GameObject, CameraSize, ShaderPathBundle, ResourceManager, EngineError, Scene, Model};
+use crate::{Camera, GameObject, CameraSize, ShaderPathBundle, ResourceManager, EngineError, Scene, Model, DrawRecorder, DrawRecord, TransparentPass, SkyboxSource, View3DRenderPipeline};
 
 // TODO: add lights, need a light trait
-// TODO: See if qsort is fast enough that  to allow me to sort models based on distance from the camera every frame, enabling transparency
+// Models loaded by the resource manager have their transparent meshes split off
+// into a part of their own, so models are drawn in the G-buffer with only their
+// opaque meshes, then the parts in a forward pass sorted back-to-front after the
+// lighting pass. Models are only added through add_model() so their parts can't
+// be missed.
 pub struct View3DScene {
-    pub models: Vec<Rc<Model>>,
+    models: Vec<Rc<Model>>,
     pub model_shader_program: Rc<ShaderProgram>,
+    // Models with transparent meshes, paired with the part holding those meshes
+    transparent_models: Vec<(Rc<Model>, Rc<Model>)>,
+    pub transparent_shader_program: Rc<ShaderProgram>,
+    transparent_pass: TransparentPass,
     pub skybox: Skybox,
     pub skybox_shader_program: Rc<ShaderProgram>,
     pub camera: Camera,
@@ -16,29 +24,54 @@ pub struct View3DScene {
 }
 
 impl View3DScene {
+    // Transparent meshes are drawn with the built-in transparent shader
     pub fn new(
         resource_manager: &mut ResourceManager,
         model_shader_paths: ShaderPathBundle,
-        skybox_path: &str,
+        skybox_source: SkyboxSource,
+        skybox_shader_paths: ShaderPathBundle,
+        camera_bundle: CameraSize,
+        render_pipeline: Box<dyn RenderPipeline>
+    ) -> Result<View3DScene, EngineError> {
+        View3DScene::new_with_transparent_shader(
+            resource_manager,
+            model_shader_paths,
+            ShaderPathBundle::transparent(),
+            skybox_source,
+            skybox_shader_paths,
+            camera_bundle,
+            render_pipeline
+        )
+    }
+
+    pub fn new_with_transparent_shader(
+        resource_manager: &mut ResourceManager,
+        model_shader_paths: ShaderPathBundle,
+        transparent_shader_paths: ShaderPathBundle,
+        skybox_source: SkyboxSource,
         skybox_shader_paths: ShaderPathBundle,
         camera_bundle: CameraSize,
         render_pipeline: Box<dyn RenderPipeline>
     ) -> Result<View3DScene, EngineError> {
         let model_shader_program = resource_manager.load_shader_program(model_shader_paths)?;
+        let transparent_shader_program = resource_manager.load_shader_program(transparent_shader_paths)?;
         let skybox_shader_program = resource_manager.load_shader_program(skybox_shader_paths)?;
 
-        let skybox = resource_manager.load_skybox(skybox_path)?;
+        let skybox = resource_manager.load_skybox_from(&skybox_source)?;
 
         let camera = Camera::new(
             camera_bundle,
             crate::CameraProjection::PERSPECTIVE,
-            vec![&model_shader_program, &skybox_shader_program]
+            vec![&model_shader_program, &transparent_shader_program, &skybox_shader_program]
         )?;
         
         Ok(
             View3DScene {
                 models: vec![],
                 model_shader_program,
+                transparent_models: vec![],
+                transparent_shader_program,
+                transparent_pass: TransparentPass::new(resource_manager)?,
                 skybox,
                 skybox_shader_program,
                 camera,
@@ -47,6 +80,42 @@ impl View3DScene {
             }
         )
     }
+
+    // Uses the built-in G-buffer, transparent and skybox shaders, drawn with a
+    // View3DRenderPipeline at the camera's size using the built-in shaders too
+    pub fn with_default_shaders(
+        resource_manager: &mut ResourceManager,
+        skybox_source: SkyboxSource,
+        camera_bundle: CameraSize
+    ) -> Result<View3DScene, EngineError> {
+        let render_pipeline = View3DRenderPipeline::with_default_shaders(resource_manager, camera_bundle.width, camera_bundle.height)?;
+
+        View3DScene::new(
+            resource_manager,
+            ShaderPathBundle::model(),
+            skybox_source,
+            ShaderPathBundle::skybox(),
+            camera_bundle,
+            Box::new(render_pipeline)
+        )
+    }
+
+    // Adds the model to the models drawn, and its transparent part to the
+    // transparent pass if it has one
+    pub fn add_model(&mut self, resource_manager: &ResourceManager, model: Rc<Model>) {
+        if let Some(part) = resource_manager.get_transparent_part(&model) {
+            self.transparent_models.push((Rc::clone(&model), part));
+        }
+
+        self.models.push(model);
+    }
+
+    pub fn remove_model(&mut self, model: &Rc<Model>) {
+        self.models.retain(|other| !Rc::ptr_eq(other, model));
+        self.transparent_models.retain(|(other, _)| !Rc::ptr_eq(other, model));
+    }
+
+    pub fn get_models(&self) -> &[Rc<Model>] { &self.models }
 }
 
 impl Scene for View3DScene {
@@ -90,6 +159,84 @@ impl Scene for View3DScene {
 
         self.render_pipeline.draw()?;
 
+        self.transparent_pass.draw(
+            self.render_pipeline.as_ref(),
+            &self.transparent_shader_program,
+            &self.transparent_models,
+            &self.world_obj,
+            self.camera.position
+        )?;
+
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn record(&mut self, recorder: &mut DrawRecorder) {
+        let (width, height) = self.get_size();
+        recorder.push(DrawRecord::Scene { name: std::any::type_name::<Self>(), width, height });
+
+        for model in self.models.iter() {
+            recorder.push(DrawRecord::Model { instances: model.borrow().get_transform_array().len() });
+        }
+
+        recorder.push(DrawRecord::Skybox);
+
+        for instances in TransparentPass::sorted_instance_counts(&self.transparent_models, &self.world_obj, self.camera.position) {
+            recorder.push(DrawRecord::TransparentModel { instances });
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use cgmath::vec3;
+    use silver_gl::{GlImage, Vertex};
+    use crate::{CSEngine, CSEngineConfig, GraphicsLibrary, ObjData, ObjMesh};
+    use super::*;
+
+    #[test]
+    fn transparent_meshes_are_drawn_without_game_objects() {
+        let mut engine = CSEngine::new(CSEngineConfig {
+            width: 640,
+            height: 480,
+            gl: GraphicsLibrary::None,
+            ..Default::default()
+        });
+
+        let data = ObjData {
+            directory: String::new(),
+            vertices: (0..3).map(|_| Vertex::default()).collect(),
+            indices: vec![0, 1, 2],
+            meshes: vec![ObjMesh {
+                offset: 0,
+                count: 3,
+                material: Some(tobj::Material { diffuse_texture: String::from("glass.png"), ..Default::default() })
+            }]
+        };
+        let image = GlImage { bytes: vec![255, 255, 255, 128], internal_format: gl::SRGB8_ALPHA8, data_format: gl::RGBA, width: 1, height: 1 };
+        let model = engine.resource_manager
+            .upload_obj("glass.obj", data, HashMap::from([(String::from("glass.png"), image)]))
+            .unwrap();
+        assert!(engine.resource_manager.is_model_transparent(&model));
+
+        let mut scene = View3DScene::with_default_shaders(
+            &mut engine.resource_manager,
+            SkyboxSource::Colour(vec3(0.0, 0.0, 0.0)),
+            CameraSize { width: 640, height: 480, fov: 90.0 }
+        ).unwrap();
+        scene.add_model(&engine.resource_manager, Rc::clone(&model));
+
+        let mut recorder = DrawRecorder::new();
+        scene.record(&mut recorder);
+        let instances = model.borrow().get_transform_array().len();
+        assert!(recorder.records.iter().any(|record| matches!(record, DrawRecord::TransparentModel { instances: count } if *count == instances)));
+
+        // Once in the tree, the part is drawn with the model's instances there instead
+        scene.world_obj.children.push(GameObject::from_model(Rc::clone(&model)));
+        let mut recorder = DrawRecorder::new();
+        scene.record(&mut recorder);
+        assert!(recorder.records.iter().any(|record| matches!(record, DrawRecord::TransparentModel { instances: 1 })));
+
+        scene.remove_model(&model);
+        assert!(scene.get_models().is_empty());
+    }
+}
@@ -1,5 +1,5 @@
 use silver_gl::{RenderPipeline, gl};
-use crate::{EngineError, Widget, Scene, Widget2dRenderPipeline};
+use crate::{EngineError, Widget, Scene, Widget2dRenderPipeline, DrawRecorder, DrawRecord, ResourceManager};
 
 pub struct Widget2dScene {
     pub children: Vec<Box<dyn Widget>>,
@@ -25,6 +25,8 @@ impl Scene for Widget2dScene {
     fn get_size(&self) -> (i32, i32) { (self.width, self.height) }
     fn set_size(&mut self, width: i32, height: i32) -> Result<(), EngineError> {
         self.render_pipeline.set_size(width, height)?;
+        self.width = width;
+        self.height = height;
         
         Ok(())
     }
@@ -33,7 +35,26 @@ impl Scene for Widget2dScene {
     fn get_render_pipeline_mut(&mut self) -> &mut Box<dyn RenderPipeline> { &mut self.render_pipeline }
     fn set_render_pipeline(&mut self, render_pipeline: Box<(dyn RenderPipeline + 'static)>) { self.render_pipeline = render_pipeline }
 
+    fn update(&mut self, delta_time: f32) {
+        for widget in &mut self.children {
+            widget.update(delta_time);
+        }
+    }
+
+    fn refresh_layout(&mut self, resource_manager: &mut ResourceManager) -> Result<(), EngineError> {
+        for widget in &mut self.children {
+            widget.refresh_layout(resource_manager)?;
+        }
+
+        Ok(())
+    }
+
     fn draw(&mut self) -> Result<(), EngineError> {
+        // Done before binding, as some widgets draw to their own framebuffers
+        for widget in &mut self.children {
+            widget.pre_draw(self.width, self.height)?;
+        }
+
         unsafe { gl::Disable(gl::DEPTH_TEST) };
 
         self.render_pipeline.bind();
@@ -47,4 +68,16 @@ impl Scene for Widget2dScene {
 
         Ok(())
     }
+
+    fn record(&mut self, recorder: &mut DrawRecorder) {
+        recorder.push(DrawRecord::Scene {
+            name: std::any::type_name::<Self>(),
+            width: self.width,
+            height: self.height
+        });
+
+        for widget in &mut self.children {
+            widget.record(&cgmath::ortho(0.0, 1.0, 1.0, 0.0, -1.0, 1.0), recorder);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,148 @@
+// Signed distance field generation for glyphs, using the exact euclidean distance
+// transform from Felzenszwalb and Huttenlocher's "Distance Transforms of Sampled Functions"
+
+use crate::EngineError;
+
+const INF: f32 = 1e20;
+// Fields are stored in the glyph atlas, so they can't be much wider than this
+pub const MAX_SDF_SPREAD: u32 = 128;
+
+// Takes a single channel coverage bitmap and returns a field padded by spread on
+// every side, so the result is (width + 2 * spread) by (height + 2 * spread).
+// The edge of the glyph is 0.5 (128), inside is higher, and the value reaches 0 or
+// 1 at spread pixels away from the edge.
+pub fn generate_sdf(bytes: &[u8], width: i32, height: i32, spread: u32) -> Result<(Vec<u8>, i32, i32), EngineError> {
+    if spread > MAX_SDF_SPREAD {
+        return Err(EngineError::ResourceManagerError(format!("An SDF spread of {} is larger than the maximum of {}!", spread, MAX_SDF_SPREAD)));
+    }
+
+    let spread = spread as i32;
+    let out_width = width + spread * 2;
+    let out_height = height + spread * 2;
+    let len = (out_width * out_height) as usize;
+
+    // Squared distances to the nearest inside and outside pixel respectively
+    let mut to_inside = vec![INF; len];
+    let mut to_outside = vec![0.0; len];
+
+    for y in 0..height {
+        for x in 0..width {
+            if bytes[(y * width + x) as usize] > 127 {
+                let i = ((y + spread) * out_width + x + spread) as usize;
+                to_inside[i] = 0.0;
+                to_outside[i] = INF;
+            }
+        }
+    }
+
+    transform_2d(&mut to_inside, out_width as usize, out_height as usize);
+    transform_2d(&mut to_outside, out_width as usize, out_height as usize);
+
+    let field = to_inside
+        .iter()
+        .zip(to_outside.iter())
+        .map(|(inside, outside)| {
+            // Distances are between pixel centres, so move the edge to halfway between them
+            let distance = if *inside == 0.0 {
+                outside.sqrt() - 0.5
+            } else {
+                0.5 - inside.sqrt()
+            };
+            let value = 0.5 + distance / (2.0 * spread.max(1) as f32);
+
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect();
+
+    Ok((field, out_width, out_height))
+}
+
+fn transform_2d(grid: &mut [f32], width: usize, height: usize) {
+    let mut column = vec![0.0; height];
+    let mut output = vec![0.0; width.max(height)];
+
+    for x in 0..width {
+        for y in 0..height {
+            column[y] = grid[y * width + x];
+        }
+        transform_1d(&column, &mut output[..height]);
+        for y in 0..height {
+            grid[y * width + x] = output[y];
+        }
+    }
+
+    for y in 0..height {
+        let row = &mut grid[y * width..(y + 1) * width];
+        transform_1d(row, &mut output[..width]);
+        row.copy_from_slice(&output[..width]);
+    }
+}
+
+// Lower envelope of parabolas rooted at each sample
+fn transform_1d(f: &[f32], output: &mut [f32]) {
+    let n = f.len();
+    let mut v = vec![0usize; n]; // Locations of parabolas in the envelope
+    let mut z = vec![0.0f32; n + 1]; // Boundaries between them
+    let mut k = 0;
+
+    z[0] = -INF;
+    z[1] = INF;
+
+    for q in 1..n {
+        let mut s = intersection(f, q, v[k]);
+        while s <= z[k] {
+            k -= 1;
+            s = intersection(f, q, v[k]);
+        }
+
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = INF;
+    }
+
+    k = 0;
+    for (q, value) in output.iter_mut().enumerate() {
+        while z[k + 1] < q as f32 {
+            k += 1;
+        }
+
+        let offset = q as f32 - v[k] as f32;
+        *value = offset * offset + f[v[k]];
+    }
+}
+
+fn intersection(f: &[f32], q: usize, p: usize) -> f32 {
+    let (qf, pf) = (q as f32, p as f32);
+
+    ((f[q] + qf * qf) - (f[p] + pf * pf)) / (2.0 * qf - 2.0 * pf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_is_padded_by_spread() {
+        let (field, width, height) = generate_sdf(&[255; 4], 2, 2, 3).unwrap();
+
+        assert_eq!((width, height), (8, 8));
+        assert_eq!(field.len(), 64);
+        // Far corner is past the spread, glyph centre is inside
+        assert_eq!(field[0], 0);
+        assert!(field[(3 * 8 + 3) as usize] > 128);
+    }
+
+    #[test]
+    fn zero_spread_keeps_size() {
+        let (_, width, height) = generate_sdf(&[0, 255, 255, 0], 2, 2, 0).unwrap();
+        assert_eq!((width, height), (2, 2));
+    }
+
+    #[test]
+    fn spread_past_max_is_an_error() {
+        assert!(generate_sdf(&[255], 1, 1, MAX_SDF_SPREAD).is_ok());
+        assert!(generate_sdf(&[255], 1, 1, MAX_SDF_SPREAD + 1).is_err());
+        assert!(generate_sdf(&[255], 1, 1, u32::MAX).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashSet;
+use crate::{EngineError, Vfs, ShaderPathBundle, join_path};
+
+// Shaders built into the engine, read whenever no mount has a file at the same
+// path, so games can override them by shipping their own
+const EMBEDDED_SHADERS: &[(&str, &str)] = &[
+    ("cs_engine/vertex_layout.glsl", include_str!("../shaders/cs_engine/vertex_layout.glsl")),
+    ("cs_engine/camera.glsl", include_str!("../shaders/cs_engine/camera.glsl")),
+    ("cs_engine/widget.vert", include_str!("../shaders/cs_engine/widget.vert")),
+    ("cs_engine/background.frag", include_str!("../shaders/cs_engine/background.frag")),
+    ("cs_engine/border.frag", include_str!("../shaders/cs_engine/border.frag")),
+    ("cs_engine/texture.frag", include_str!("../shaders/cs_engine/texture.frag")),
+    ("cs_engine/container.frag", include_str!("../shaders/cs_engine/container.frag")),
+    ("cs_engine/text_sdf.frag", include_str!("../shaders/cs_engine/text_sdf.frag")),
+    ("cs_engine/model.vert", include_str!("../shaders/cs_engine/model.vert")),
+    ("cs_engine/gbuffer.frag", include_str!("../shaders/cs_engine/gbuffer.frag")),
+    ("cs_engine/transparent.frag", include_str!("../shaders/cs_engine/transparent.frag")),
+    ("cs_engine/skybox.vert", include_str!("../shaders/cs_engine/skybox.vert")),
+    ("cs_engine/skybox.frag", include_str!("../shaders/cs_engine/skybox.frag")),
+    ("cs_engine/screen.vert", include_str!("../shaders/cs_engine/screen.vert")),
+    ("cs_engine/lighting.frag", include_str!("../shaders/cs_engine/lighting.frag")),
+    ("cs_engine/blur.frag", include_str!("../shaders/cs_engine/blur.frag")),
+    ("cs_engine/composite.frag", include_str!("../shaders/cs_engine/composite.frag")),
+];
+
+impl ShaderPathBundle {
+    pub fn new(vertex: &str, fragment: &str) -> Self {
+        Self {
+            vertex: Some(vertex.to_owned()),
+            fragment: Some(fragment.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    // Defines are kept sorted, so the same defines in any order load the same variant
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.defines.retain(|(existing, _)| existing != name);
+        self.defines.push((name.to_owned(), value.to_owned()));
+        self.defines.sort();
+
+        self
+    }
+
+    // Built-in shaders for each widget, View3DScene and View3DRenderPipeline
+    pub fn background() -> Self { Self::new("cs_engine/widget.vert", "cs_engine/background.frag") }
+    pub fn border() -> Self { Self::new("cs_engine/widget.vert", "cs_engine/border.frag") }
+    pub fn texture() -> Self { Self::new("cs_engine/widget.vert", "cs_engine/texture.frag") }
+    // For widgets that only hold other widgets, draws nothing itself
+    pub fn container() -> Self { Self::new("cs_engine/widget.vert", "cs_engine/container.frag") }
+    pub fn text_sdf() -> Self { Self::new("cs_engine/widget.vert", "cs_engine/text_sdf.frag") }
+    pub fn model() -> Self { Self::new("cs_engine/model.vert", "cs_engine/gbuffer.frag") }
+    pub fn transparent() -> Self { Self::new("cs_engine/model.vert", "cs_engine/transparent.frag") }
+    pub fn skybox() -> Self { Self::new("cs_engine/skybox.vert", "cs_engine/skybox.frag") }
+    pub fn lighting() -> Self { Self::new("cs_engine/screen.vert", "cs_engine/lighting.frag") }
+    pub fn blur() -> Self { Self::new("cs_engine/screen.vert", "cs_engine/blur.frag") }
+    // Used by the compositor unless another shader program is loaded
+    pub fn composite() -> Self { Self::new("cs_engine/widget.vert", "cs_engine/composite.frag") }
+}
+
+fn embedded_shader(path: &str) -> Option<&'static str> {
+    EMBEDDED_SHADERS.iter().find(|(embedded_path, _)| *embedded_path == path).map(|(_, source)| *source)
+}
+
+fn shader_exists(vfs: &Vfs, path: &str) -> bool {
+    vfs.exists(path) || embedded_shader(path).is_some()
+}
+
+fn read_shader(vfs: &Vfs, path: &str) -> Result<String, EngineError> {
+    match embedded_shader(path) {
+        Some(source) if !vfs.exists(path) => Ok(source.to_owned()),
+        _ => vfs.read_to_string(path)
+    }
+}
+
+// Includes are relative to the including file, then to the root, e.g. for the
+// built-in cs_engine/ files. ".." is resolved so paths match embedded shaders.
+fn resolve_include(vfs: &Vfs, including: &str, name: &str) -> String {
+    let directory = including.rsplit_once('/').map_or("", |(directory, _)| directory);
+    let relative = collapse_parents(&join_path(directory, name));
+
+    if shader_exists(vfs, &relative) {
+        relative
+    } else {
+        collapse_parents(name)
+    }
+}
+
+fn collapse_parents(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+
+    for part in path.split('/') {
+        match part {
+            ".." if parts.last().map_or(false, |last| *last != "..") => { parts.pop(); },
+            "." | "" => (),
+            _ => parts.push(part)
+        }
+    }
+
+    parts.join("/")
+}
+
+// Expands #include "file" lines and adds the defines after #version. Every file is
+// given a source string number through #line directives, so compiler errors can
+// be mapped back to the file and line they came from. Numbers are shared by all
+// stages of a program, as its link log doesn't say which stage an error is in.
+// Each file is only included once per stage, so shared files don't need guards.
+pub(crate) struct ShaderPreprocessor<'a> {
+    vfs: &'a Vfs,
+    defines: &'a [(String, String)],
+    files: Vec<String>
+}
+
+impl<'a> ShaderPreprocessor<'a> {
+    pub fn new(vfs: &'a Vfs, defines: &'a [(String, String)]) -> Self {
+        Self { vfs, defines, files: Vec::new() }
+    }
+
+    // Every file read so far, indexed by source string number
+    pub fn files(&self) -> &[String] { &self.files }
+
+    pub fn process(&mut self, path: &str) -> Result<String, EngineError> {
+        let mut output = String::new();
+        let mut included = HashSet::new();
+        let mut stack = Vec::new();
+        let found_version = self.expand(path, &mut included, &mut stack, &mut output)?;
+
+        // Without a #version the defines can go right at the start
+        if !found_version && !self.defines.is_empty() {
+            let number = self.file_number(path);
+            output = format!("{}#line 1 {}\n{}", self.define_lines(), number, output);
+        }
+
+        Ok(output)
+    }
+
+    // Returns whether the file had a #version line
+    fn expand(
+        &mut self,
+        path: &str,
+        included: &mut HashSet<String>,
+        stack: &mut Vec<String>,
+        output: &mut String
+    ) -> Result<bool, EngineError> {
+        if stack.iter().any(|parent| parent == path) {
+            let cycle: Vec<&str> = stack.iter().map(String::as_str).chain([path]).collect();
+            return Err(EngineError::ResourceManagerError(format!("Shader includes form a cycle: {}!", cycle.join(" -> "))));
+        }
+        if !included.insert(path.to_owned()) {
+            return Ok(false);
+        }
+
+        let source = read_shader(self.vfs, path)?;
+        let number = self.file_number(path);
+        let top_level = stack.is_empty();
+        let mut found_version = false;
+
+        if !top_level {
+            output.push_str(&format!("#line 1 {}\n", number));
+        }
+        stack.push(path.to_owned());
+
+        for (i, line) in source.lines().enumerate() {
+            let directive = line.trim_start();
+
+            if let Some(rest) = directive.strip_prefix("#include") {
+                let name = rest.trim();
+                let name = name
+                    .strip_prefix('"')
+                    .and_then(|name| name.strip_suffix('"'))
+                    .ok_or_else(|| EngineError::ResourceManagerError(format!("Expected a quoted path after #include in '{}' line {}!", path, i + 1)))?;
+
+                let include_path = resolve_include(self.vfs, path, name);
+                if !shader_exists(self.vfs, &include_path) {
+                    return Err(EngineError::ResourceManagerError(format!("'{}' included in '{}' line {} was not found!", name, path, i + 1)));
+                }
+
+                self.expand(&include_path, included, stack, output)?;
+                output.push_str(&format!("#line {} {}\n", i + 2, number));
+            } else if directive.starts_with("#version") {
+                // Only the top level file's version counts, as it has to come first
+                if top_level {
+                    output.push_str(line);
+                    output.push('\n');
+                    output.push_str(&self.define_lines());
+                    output.push_str(&format!("#line {} {}\n", i + 2, number));
+                    found_version = true;
+                } else {
+                    output.push('\n');
+                }
+            } else {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+
+        stack.pop();
+
+        Ok(found_version)
+    }
+
+    fn file_number(&mut self, path: &str) -> usize {
+        match self.files.iter().position(|file| file == path) {
+            Some(number) => number,
+            None => {
+                self.files.push(path.to_owned());
+                self.files.len() - 1
+            }
+        }
+    }
+
+    fn define_lines(&self) -> String {
+        self.defines
+            .iter()
+            .map(|(name, value)| format!("#define {} {}\n", name, value))
+            .collect()
+    }
+}
+
+// Replaces source string numbers in a compiler log with file names. Drivers write
+// locations as "0(12)", "0:12:" or "0:12(5):" with a column, with the number first.
+// The line and column are copied as they are, so they are never taken for numbers.
+pub(crate) fn map_shader_log(log: &str, files: &[String]) -> String {
+    let chars: Vec<char> = log.chars().collect();
+    let mut mapped = String::with_capacity(log.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let starts_number = chars[i].is_ascii_digit() && (i == 0 || !chars[i - 1].is_alphanumeric());
+        if !starts_number {
+            mapped.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        let end = (i..chars.len()).find(|j| !chars[*j].is_ascii_digit()).unwrap_or(chars.len());
+        let number: String = chars[i..end].iter().collect();
+        let line_end = (end + 1..chars.len()).find(|j| !chars[*j].is_ascii_digit()).unwrap_or(chars.len());
+
+        let is_location = line_end > end + 1 && matches!(
+            (chars.get(end), chars.get(line_end)),
+            (Some('('), Some(')')) | (Some(':'), Some(':' | '('))
+        );
+
+        match number.parse::<usize>().ok().and_then(|number| files.get(number)) {
+            Some(file) if is_location => {
+                mapped.push_str(file);
+                mapped.extend(&chars[end..line_end]);
+                i = line_end;
+            },
+            _ => {
+                mapped.push_str(&number);
+                i = end;
+            }
+        }
+    }
+
+    mapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> Vec<String> {
+        (0..20).map(|number| format!("file{}.glsl", number)).collect()
+    }
+
+    #[test]
+    fn maps_nvidia_locations() {
+        assert_eq!(
+            map_shader_log("0(12) : error C1008: undefined variable \"albedo\"", &files()),
+            "file0.glsl(12) : error C1008: undefined variable \"albedo\""
+        );
+    }
+
+    #[test]
+    fn maps_colon_locations() {
+        assert_eq!(
+            map_shader_log("ERROR: 2:7: 'albedo' : undeclared identifier", &files()),
+            "ERROR: file2.glsl:7: 'albedo' : undeclared identifier"
+        );
+    }
+
+    #[test]
+    fn maps_mesa_locations_with_columns() {
+        assert_eq!(
+            map_shader_log("0:12(5): error: `albedo' undeclared\n1:3(10): error: syntax error", &files()),
+            "file0.glsl:12(5): error: `albedo' undeclared\nfile1.glsl:3(10): error: syntax error"
+        );
+    }
+
+    #[test]
+    fn leaves_other_numbers() {
+        assert_eq!(
+            map_shader_log("error C7011: 3 arguments given, vec4 (1) expected", &files()),
+            "error C7011: 3 arguments given, vec4 (1) expected"
+        );
+        // No file with that number
+        assert_eq!(map_shader_log("25(3) : error", &files()), "25(3) : error");
+    }
+}
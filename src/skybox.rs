@@ -0,0 +1,226 @@
+use std::{f32::consts::PI, ffi::c_void};
+use cgmath::{Vector3, vec3, InnerSpace};
+use silver_gl::{Texture, GlImage, gl};
+use crate::{EngineError, pixel_type, channel_count, to_f32_channels, from_f32_channels};
+
+// Where a View3DScene's skybox comes from. Faces are in OpenGL's order: +X, -X,
+// +Y, -Y, +Z, -Z (right, left, top, bottom, front, back). Colours aren't images,
+// so are used as they are without any sRGB conversion.
+#[derive(Debug, Clone)]
+pub enum SkyboxSource {
+    Cubemap(String), // Single image in the layout Texture::from_cubemap() takes
+    Faces([String; 6]),
+    Equirectangular { path: String, face_size: i32 }, // Panorama, e.g. an HDR environment
+    Colour(Vector3<f32>),
+    Gradient { top: Vector3<f32>, horizon: Vector3<f32>, bottom: Vector3<f32> }
+}
+
+// Faces of generated skyboxes, textures are filtered so gradients are still smooth
+const GRADIENT_FACE_SIZE: i32 = 64;
+
+// Texture::from_cubemap() only takes a single image in a horizontal cross, so the
+// cubemap is created from a blank cross and the faces are uploaded into it. This
+// also lets faces be 16 bit or float. Faces have to be square and the same size
+// and format.
+pub(crate) fn create_cubemap(faces: &[GlImage]) -> Result<Texture, EngineError> {
+    let first = faces.first().ok_or(EngineError::ResourceManagerError(String::from("Cubemaps need six faces!")))?;
+    let size = first.width;
+
+    if faces.len() != 6 {
+        return Err(EngineError::ResourceManagerError(format!("Cubemaps need six faces, got {}!", faces.len())));
+    }
+    for face in faces {
+        if face.width != size || face.height != size {
+            return Err(
+                EngineError::ResourceManagerError(
+                    format!("Cubemap faces have to be square and the same size, expected {}x{} but got {}x{}!", size, size, face.width, face.height)
+                )
+            );
+        }
+        if (face.internal_format, face.data_format) != (first.internal_format, first.data_format) {
+            return Err(EngineError::ResourceManagerError(String::from("Cubemap faces have to be the same format!")));
+        }
+    }
+
+    let blank = GlImage {
+        bytes: vec![0; (size * 4 * size * 3) as usize * channel_count(first)],
+        internal_format: first.internal_format,
+        data_format: first.data_format,
+        width: size * 4,
+        height: size * 3
+    };
+    let texture = Texture::from_cubemap(blank);
+
+    let mut created_size = 0;
+    unsafe { gl::GetTextureLevelParameteriv(texture.get_id(), 0, gl::TEXTURE_WIDTH, &mut created_size) };
+    if created_size != size {
+        return Err(EngineError::ResourceManagerError(format!("Cubemap was created {} pixels wide instead of {}!", created_size, size)));
+    }
+
+    unsafe {
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        for (i, face) in faces.iter().enumerate() {
+            gl::TextureSubImage3D(
+                texture.get_id(),
+                0,
+                0,
+                0,
+                i as i32,
+                size,
+                size,
+                1,
+                face.data_format,
+                pixel_type(face),
+                face.bytes.as_ptr() as *const c_void
+            );
+        }
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        gl::GenerateTextureMipmap(texture.get_id());
+    }
+
+    Ok(texture)
+}
+
+// Direction through the centre of a face's pixel, following the cubemap
+// coordinate rules in the OpenGL spec
+fn face_direction(face: usize, x: i32, y: i32, size: i32) -> Vector3<f32> {
+    let u = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+    let v = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+
+    let direction = match face {
+        0 => vec3(1.0, -v, -u),
+        1 => vec3(-1.0, -v, u),
+        2 => vec3(u, 1.0, v),
+        3 => vec3(u, -1.0, -v),
+        4 => vec3(u, -v, 1.0),
+        _ => vec3(-u, -v, -1.0)
+    };
+
+    direction.normalize()
+}
+
+// Projects the panorama onto each face, sampling it bilinearly. The centre of the
+// panorama faces -Z, the default camera direction. Faces keep the panorama's format.
+pub(crate) fn equirectangular_to_faces(image: &GlImage, face_size: i32) -> Result<Vec<GlImage>, EngineError> {
+    if face_size <= 0 {
+        return Err(EngineError::ResourceManagerError(format!("Skybox faces have to be at least 1 pixel, got {}!", face_size)));
+    }
+    if image.width <= 0 || image.height <= 0 {
+        return Err(EngineError::ResourceManagerError(String::from("Equirectangular skyboxes can't be made from an empty image!")));
+    }
+
+    let channels = channel_count(image);
+    let values = to_f32_channels(image);
+    let (width, height) = (image.width as usize, image.height as usize);
+
+    let texel = |x: usize, y: usize, channel: usize| values[(y * width + x) * channels + channel];
+
+    let faces = (0..6)
+        .map(|face| {
+            let mut face_values = Vec::with_capacity((face_size * face_size) as usize * channels);
+
+            for y in 0..face_size {
+                for x in 0..face_size {
+                    let direction = face_direction(face, x, y, face_size);
+                    let longitude = direction.x.atan2(-direction.z);
+                    let latitude = direction.y.clamp(-1.0, 1.0).asin();
+
+                    // Pixel centres are at half pixels, longitude wraps around
+                    let u = (0.5 + longitude / (2.0 * PI)) * width as f32 - 0.5;
+                    let v = ((0.5 - latitude / PI) * height as f32 - 0.5).clamp(0.0, height as f32 - 1.0);
+
+                    let (x0, y0) = (u.floor(), v.floor());
+                    let (tx, ty) = (u - x0, v - y0);
+                    let x0 = (x0 as i64).rem_euclid(width as i64) as usize;
+                    let x1 = (x0 + 1) % width;
+                    let y0 = y0 as usize;
+                    let y1 = (y0 + 1).min(height - 1);
+
+                    for channel in 0..channels {
+                        let top = texel(x0, y0, channel) * (1.0 - tx) + texel(x1, y0, channel) * tx;
+                        let bottom = texel(x0, y1, channel) * (1.0 - tx) + texel(x1, y1, channel) * tx;
+                        face_values.push(top * (1.0 - ty) + bottom * ty);
+                    }
+                }
+            }
+
+            from_f32_channels(&face_values, face_size, face_size, image)
+        })
+        .collect();
+
+    Ok(faces)
+}
+
+// Blends from the top colour straight up to the horizon, then to the bottom
+// colour straight down. Stored as half floats, so colours can be brighter than 1.
+pub(crate) fn gradient_faces(top: Vector3<f32>, horizon: Vector3<f32>, bottom: Vector3<f32>) -> Vec<GlImage> {
+    // A single colour doesn't need more than a pixel
+    let size = if top == horizon && horizon == bottom { 1 } else { GRADIENT_FACE_SIZE };
+    let format = GlImage {
+        bytes: Vec::new(),
+        internal_format: gl::RGB16F,
+        data_format: gl::RGB,
+        width: 0,
+        height: 0
+    };
+
+    (0..6)
+        .map(|face| {
+            let mut values = Vec::with_capacity((size * size * 3) as usize);
+
+            for y in 0..size {
+                for x in 0..size {
+                    let height = face_direction(face, x, y, size).y;
+                    let colour = if height >= 0.0 {
+                        horizon + (top - horizon) * height
+                    } else {
+                        horizon + (bottom - horizon) * -height
+                    };
+
+                    values.extend([colour.x, colour.y, colour.z]);
+                }
+            }
+
+            from_f32_channels(&values, size, size, &format)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn panorama() -> GlImage {
+        GlImage {
+            bytes: vec![255; 4 * 2 * 3],
+            internal_format: gl::RGB8,
+            data_format: gl::RGB,
+            width: 4,
+            height: 2
+        }
+    }
+
+    #[test]
+    fn equirectangular_faces_are_face_size() {
+        let faces = equirectangular_to_faces(&panorama(), 3).unwrap();
+
+        assert_eq!(faces.len(), 6);
+        for face in faces {
+            assert_eq!((face.width, face.height), (3, 3));
+            assert_eq!(face.bytes, vec![255; 3 * 3 * 3]);
+        }
+    }
+
+    #[test]
+    fn equirectangular_rejects_bad_face_size() {
+        assert!(matches!(equirectangular_to_faces(&panorama(), 0), Err(EngineError::ResourceManagerError(_))));
+        assert!(matches!(equirectangular_to_faces(&panorama(), -16), Err(EngineError::ResourceManagerError(_))));
+    }
+
+    #[test]
+    fn equirectangular_rejects_empty_image() {
+        let image = GlImage { width: 0, height: 0, bytes: Vec::new(), ..panorama() };
+
+        assert!(matches!(equirectangular_to_faces(&image, 4), Err(EngineError::ResourceManagerError(_))));
+    }
+}
@@ -0,0 +1,173 @@
+use serde::Deserialize;
+use silver_gl::{Texture, GlImage, gl};
+use crate::{pixel_type, decode_srgb_16};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureFilter {
+    Nearest, // Keeps hard pixel edges, e.g. for pixel art
+    Linear
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder
+}
+
+// Names are the same as in manifests, and are part of cache keys so can't change
+impl TextureFilter {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextureFilter::Nearest => "nearest",
+            TextureFilter::Linear => "linear"
+        }
+    }
+}
+
+impl TextureWrap {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextureWrap::Repeat => "repeat",
+            TextureWrap::MirroredRepeat => "mirrored_repeat",
+            TextureWrap::ClampToEdge => "clamp_to_edge",
+            TextureWrap::ClampToBorder => "clamp_to_border"
+        }
+    }
+}
+
+// How a texture is stored and sampled. Textures loaded from the same path with
+// different options are cached separately, so an image can be loaded both as
+// colour and as linear data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureOptions {
+    pub filter: TextureFilter,
+    pub mipmaps: bool, // Stops large textures shimmering when drawn small
+    pub wrap: TextureWrap, // Cubemaps always clamp to their edges
+    pub anisotropy: u32, // Maximum samples for textures viewed at an angle, 1 turns it off
+    pub srgb: bool // Colour is stored in sRGB, while data like normal maps is linear
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            filter: TextureFilter::Linear,
+            mipmaps: true,
+            wrap: TextureWrap::Repeat,
+            anisotropy: 1,
+            srgb: true
+        }
+    }
+}
+
+impl TextureOptions {
+    // Sharp and unfiltered, for pixel art portraits and sprites
+    pub fn pixel_art() -> Self {
+        Self {
+            filter: TextureFilter::Nearest,
+            mipmaps: false,
+            wrap: TextureWrap::ClampToEdge,
+            ..Default::default()
+        }
+    }
+
+    // For maps that hold data rather than colour, e.g. normal and specular maps
+    pub fn linear() -> Self {
+        Self {
+            srgb: false,
+            ..Default::default()
+        }
+    }
+
+    // Textures loaded with the default options are cached under their path, so
+    // existing keys stay the same. Keys can be saved through handles, so every
+    // option is written out by name rather than with Debug, which can change.
+    pub(crate) fn cache_key(&self, path: &str) -> String {
+        if *self == TextureOptions::default() {
+            path.to_owned()
+        } else {
+            format!(
+                "{}#options(filter={},mipmaps={},wrap={},anisotropy={},srgb={})",
+                path, self.filter.name(), self.mipmaps, self.wrap.name(), self.anisotropy, self.srgb
+            )
+        }
+    }
+
+    // Switches colour formats between sRGB and linear before the image is uploaded
+    pub(crate) fn apply_to_image(&self, image: &mut GlImage) {
+        if self.srgb && pixel_type(image) == gl::UNSIGNED_SHORT {
+            decode_srgb_16(image);
+        }
+
+        image.internal_format = match (image.internal_format, self.srgb) {
+            (gl::RGB8, true) => gl::SRGB8,
+            (gl::RGBA8, true) => gl::SRGB8_ALPHA8,
+            (gl::SRGB8 | gl::SRGB, false) => gl::RGB8,
+            (gl::SRGB8_ALPHA8, false) => gl::RGBA8,
+            (internal_format, _) => internal_format
+        };
+    }
+
+    // Overrides the sampling parameters the texture was created with
+    pub(crate) fn apply(&self, texture: &Texture) {
+        let id = texture.get_id();
+
+        let mut target = 0;
+        unsafe { gl::GetTextureParameteriv(id, gl::TEXTURE_TARGET, &mut target) };
+        let cubemap = target as u32 == gl::TEXTURE_CUBE_MAP;
+
+        let (min_filter, mag_filter) = match (self.filter, self.mipmaps) {
+            (TextureFilter::Nearest, false) => (gl::NEAREST, gl::NEAREST),
+            (TextureFilter::Nearest, true) => (gl::NEAREST_MIPMAP_NEAREST, gl::NEAREST),
+            (TextureFilter::Linear, false) => (gl::LINEAR, gl::LINEAR),
+            (TextureFilter::Linear, true) => (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR),
+        };
+
+        let wrap = match (self.wrap, cubemap) {
+            (_, true) | (TextureWrap::ClampToEdge, _) => gl::CLAMP_TO_EDGE,
+            (TextureWrap::Repeat, _) => gl::REPEAT,
+            (TextureWrap::MirroredRepeat, _) => gl::MIRRORED_REPEAT,
+            (TextureWrap::ClampToBorder, _) => gl::CLAMP_TO_BORDER,
+        };
+
+        unsafe {
+            if self.mipmaps {
+                gl::TextureParameteri(id, gl::TEXTURE_MAX_LEVEL, 1000);
+                gl::GenerateTextureMipmap(id);
+            } else {
+                gl::TextureParameteri(id, gl::TEXTURE_MAX_LEVEL, 0);
+            }
+
+            gl::TextureParameteri(id, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+            gl::TextureParameteri(id, gl::TEXTURE_MAG_FILTER, mag_filter as i32);
+            gl::TextureParameteri(id, gl::TEXTURE_WRAP_S, wrap as i32);
+            gl::TextureParameteri(id, gl::TEXTURE_WRAP_T, wrap as i32);
+            gl::TextureParameteri(id, gl::TEXTURE_WRAP_R, wrap as i32);
+
+            let mut max_anisotropy = 1.0;
+            gl::GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+            gl::TextureParameterf(id, gl::TEXTURE_MAX_ANISOTROPY, (self.anisotropy.max(1) as f32).min(max_anisotropy));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_is_path_for_default_options() {
+        assert_eq!(TextureOptions::default().cache_key("textures/a.png"), "textures/a.png");
+    }
+
+    #[test]
+    fn cache_key_names_every_option() {
+        assert_eq!(
+            TextureOptions::pixel_art().cache_key("textures/a.png"),
+            "textures/a.png#options(filter=nearest,mipmaps=false,wrap=clamp_to_edge,anisotropy=1,srgb=true)"
+        );
+    }
+}
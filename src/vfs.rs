@@ -0,0 +1,413 @@
+use std::{fs::{self, File}, io::{self, Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}, collections::HashMap, sync::Arc};
+use crate::EngineError;
+
+const PAK_MAGIC: &[u8; 6] = b"CSPAK\0";
+const PAK_VERSION: u32 = 1;
+
+// Somewhere files can be read from, paths are relative to the mount and use '/'
+pub trait Mount: Send + Sync {
+    fn read(&self, path: &str) -> Option<io::Result<Vec<u8>>>;
+    fn contains(&self, path: &str) -> bool;
+    // Where the file is on disk, if it is a plain file
+    fn real_path(&self, _path: &str) -> Option<PathBuf> { None }
+}
+
+pub struct DirectoryMount {
+    root: PathBuf
+}
+
+impl DirectoryMount {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self { root: root.as_ref().to_owned() }
+    }
+
+    // Mounts can be used without a Vfs, so paths are kept inside the root here too
+    fn file_path(&self, path: &str) -> Option<PathBuf> {
+        let path = self.root.join(normalize_path(path)?);
+
+        if path.is_file() {
+            Some(path)
+        } else {
+            None
+        }
+    }
+}
+
+impl Mount for DirectoryMount {
+    fn read(&self, path: &str) -> Option<io::Result<Vec<u8>>> {
+        self.file_path(path).map(fs::read)
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.file_path(path).is_some()
+    }
+
+    fn real_path(&self, path: &str) -> Option<PathBuf> {
+        self.file_path(path)
+    }
+}
+
+// Pak files are a header, an index of every file's path, offset and length, then
+// the files' data one after another. Numbers are little endian.
+//   "CSPAK\0", version: u32, file count: u32
+//   For each file: path length: u32, path: utf-8, offset: u64, length: u64
+//   File data
+// The file is opened on every read, so it can be read from any thread. The index
+// is checked against the file's length when opened, so a damaged pak can't make
+// reads allocate more than the file holds.
+pub struct PakMount {
+    path: PathBuf,
+    entries: HashMap<String, (u64, u64)>
+}
+
+impl PakMount {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, EngineError> {
+        let path = path.as_ref().to_owned();
+        let file = File::open(&path)?;
+        let file_length = file.metadata()?.len();
+        let mut file = io::BufReader::new(file);
+        let damaged = || EngineError::VfsError(format!("'{}' is damaged, its index doesn't fit in the file!", path.display()));
+
+        let mut magic = [0; 6];
+        file.read_exact(&mut magic)?;
+        if &magic != PAK_MAGIC {
+            return Err(EngineError::VfsError(format!("'{}' is not a pak file!", path.display())));
+        }
+
+        let version = read_u32(&mut file)?;
+        if version != PAK_VERSION {
+            return Err(EngineError::VfsError(format!("'{}' is pak version {}, expected {}!", path.display(), version, PAK_VERSION)));
+        }
+
+        // Every entry takes at least 20 bytes, even with an empty path
+        let count = read_u32(&mut file)?;
+        let mut remaining = file_length.saturating_sub((PAK_MAGIC.len() + 8) as u64);
+        if count as u64 * 20 > remaining {
+            return Err(damaged());
+        }
+
+        let mut entries = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let length = read_u32(&mut file)?;
+            remaining = remaining.checked_sub(20 + length as u64).ok_or_else(damaged)?;
+
+            let mut name = vec![0; length as usize];
+            file.read_exact(&mut name)?;
+            let name = String::from_utf8(name)
+                .map_err(|_| EngineError::VfsError(format!("'{}' has a path that isn't valid utf-8!", path.display())))?;
+
+            let offset = read_u64(&mut file)?;
+            let length = read_u64(&mut file)?;
+            if offset.checked_add(length).is_none_or(|end| end > file_length) {
+                return Err(damaged());
+            }
+
+            entries.insert(name, (offset, length));
+        }
+
+        Ok(Self { path, entries })
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &String> {
+        self.entries.keys()
+    }
+}
+
+impl Mount for PakMount {
+    fn read(&self, path: &str) -> Option<io::Result<Vec<u8>>> {
+        let (offset, length) = *self.entries.get(path)?;
+
+        let read = || {
+            let mut file = File::open(&self.path)?;
+            file.seek(SeekFrom::Start(offset))?;
+
+            let mut bytes = vec![0; length as usize];
+            file.read_exact(&mut bytes)?;
+
+            Ok(bytes)
+        };
+
+        Some(read())
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.entries.contains_key(path)
+    }
+}
+
+// Every path the resource manager loads goes through here. Mounts are searched
+// from the last mounted to the first, so mods and patches mounted later override
+// the base game's files. Cloning is cheap, as mounts are shared.
+#[derive(Clone, Default)]
+pub struct Vfs {
+    mounts: Vec<Arc<dyn Mount>>
+}
+
+impl Vfs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mount(&mut self, mount: Arc<dyn Mount>) {
+        self.mounts.push(mount);
+    }
+
+    pub fn mount_dir(&mut self, root: impl AsRef<Path>) {
+        self.mount(Arc::new(DirectoryMount::new(root)));
+    }
+
+    pub fn mount_pak(&mut self, path: impl AsRef<Path>) -> Result<(), EngineError> {
+        self.mount(Arc::new(PakMount::open(path)?));
+
+        Ok(())
+    }
+
+    pub fn unmount_all(&mut self) {
+        self.mounts.clear();
+    }
+
+    fn find(&self, path: &str) -> Option<&Arc<dyn Mount>> {
+        self.mounts.iter().rev().find(|mount| mount.contains(path))
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        normalize_path(path).is_some_and(|path| self.find(&path).is_some())
+    }
+
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, EngineError> {
+        let normalized = normalize_path(path)
+            .ok_or_else(|| EngineError::VfsError(format!("'{}' is outside of the mounts!", path)))?;
+
+        match self.find(&normalized).and_then(|mount| mount.read(&normalized)) {
+            Some(bytes) => Ok(bytes?),
+            None => Err(EngineError::VfsError(format!("'{}' was not found in any mount!", path)))
+        }
+    }
+
+    pub fn read_to_string(&self, path: &str) -> Result<String, EngineError> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|_| EngineError::VfsError(format!("'{}' is not valid utf-8!", path)))
+    }
+
+    // Where the file that would be read is on disk, None if it is in an archive
+    pub fn real_path(&self, path: &str) -> Option<PathBuf> {
+        let normalized = normalize_path(path)?;
+
+        self.find(&normalized).and_then(|mount| mount.real_path(&normalized))
+    }
+}
+
+// Joins a file name onto the directory of another file, e.g. a texture onto a model's directory
+pub fn join_path(directory: &str, name: &str) -> String {
+    if directory.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}/{}", directory.trim_end_matches('/'), name)
+    }
+}
+
+// Paths are relative to the mounts, so "." and ".." are resolved and anything
+// absolute or leading out of the mounts is None, otherwise a directory mount
+// could read any file on disk
+fn normalize_path(path: &str) -> Option<String> {
+    let path = path.replace('\\', "/");
+    if path.starts_with('/') || path.contains(':') {
+        return None;
+    }
+
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "." | "" => (),
+            ".." => { parts.pop()?; },
+            _ => parts.push(part)
+        }
+    }
+
+    Some(parts.join("/"))
+}
+
+// Packs every file under the directory into a pak file, with paths relative to it
+pub fn build_pak(directory: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<usize, EngineError> {
+    let directory = directory.as_ref();
+
+    let mut files = Vec::new();
+    collect_files(directory, directory, &mut files)?;
+    files.sort();
+
+    let mut header_length = (PAK_MAGIC.len() + 8) as u64;
+    for (name, _) in files.iter() {
+        header_length += 4 + name.len() as u64 + 16;
+    }
+
+    let mut out = io::BufWriter::new(File::create(output)?);
+    out.write_all(PAK_MAGIC)?;
+    out.write_all(&PAK_VERSION.to_le_bytes())?;
+    out.write_all(&(files.len() as u32).to_le_bytes())?;
+
+    let mut offset = header_length;
+    for (name, path) in files.iter() {
+        let length = fs::metadata(path)?.len();
+
+        out.write_all(&(name.len() as u32).to_le_bytes())?;
+        out.write_all(name.as_bytes())?;
+        out.write_all(&offset.to_le_bytes())?;
+        out.write_all(&length.to_le_bytes())?;
+
+        offset += length;
+    }
+
+    for (_, path) in files.iter() {
+        io::copy(&mut File::open(path)?, &mut out)?;
+    }
+    out.flush()?;
+
+    Ok(files.len())
+}
+
+fn collect_files(root: &Path, directory: &Path, files: &mut Vec<(String, PathBuf)>) -> Result<(), EngineError> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else {
+            let name = path
+                .strip_prefix(root)
+                .ok()
+                .and_then(|name| name.to_str())
+                .ok_or(EngineError::VfsError(format!("'{}' has a path that isn't valid utf-8!", path.display())))?;
+
+            let name = normalize_path(name)
+                .ok_or_else(|| EngineError::VfsError(format!("'{}' can't be stored in a pak file!", path.display())))?;
+            files.push((name, path));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system's temp directory for each test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cs_engine_vfs_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn normalize_path_resolves_dots() {
+        assert_eq!(normalize_path("./models/cube.obj").as_deref(), Some("models/cube.obj"));
+        assert_eq!(normalize_path("models\\textures\\wood.png").as_deref(), Some("models/textures/wood.png"));
+        assert_eq!(normalize_path("models/../textures//./wood.png").as_deref(), Some("textures/wood.png"));
+    }
+
+    #[test]
+    fn normalize_path_rejects_escaping_paths() {
+        assert_eq!(normalize_path("../secret.txt"), None);
+        assert_eq!(normalize_path("models/../../secret.txt"), None);
+        assert_eq!(normalize_path("/etc/passwd"), None);
+        assert_eq!(normalize_path("\\etc\\passwd"), None);
+        assert_eq!(normalize_path("C:/Windows/win.ini"), None);
+    }
+
+    #[test]
+    fn join_path_joins_onto_directory() {
+        assert_eq!(join_path("", "wood.png"), "wood.png");
+        assert_eq!(join_path("models", "wood.png"), "models/wood.png");
+        assert_eq!(join_path("models/", "wood.png"), "models/wood.png");
+    }
+
+    #[test]
+    fn directory_mount_stays_in_root() {
+        let dir = temp_dir("directory");
+        fs::create_dir_all(dir.join("root")).unwrap();
+        fs::write(dir.join("root/inside.txt"), b"inside").unwrap();
+        fs::write(dir.join("outside.txt"), b"outside").unwrap();
+
+        let mut vfs = Vfs::new();
+        vfs.mount_dir(dir.join("root"));
+
+        assert_eq!(vfs.read("./inside.txt").unwrap(), b"inside");
+        assert!(!vfs.exists("../outside.txt"));
+        assert!(vfs.read("../outside.txt").is_err());
+        assert!(!DirectoryMount::new(dir.join("root")).contains("../outside.txt"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn pak_round_trip() {
+        let dir = temp_dir("pak");
+        fs::create_dir_all(dir.join("assets/textures")).unwrap();
+        fs::write(dir.join("assets/a.txt"), b"first").unwrap();
+        fs::write(dir.join("assets/textures/b.txt"), b"second file").unwrap();
+        fs::write(dir.join("assets/empty.txt"), b"").unwrap();
+
+        let pak = dir.join("assets.pak");
+        assert_eq!(build_pak(dir.join("assets"), &pak).unwrap(), 3);
+
+        let mut vfs = Vfs::new();
+        vfs.mount_pak(&pak).unwrap();
+
+        assert_eq!(vfs.read("a.txt").unwrap(), b"first");
+        assert_eq!(vfs.read("textures/b.txt").unwrap(), b"second file");
+        assert_eq!(vfs.read("textures/../empty.txt").unwrap(), b"");
+        assert!(vfs.real_path("a.txt").is_none());
+        assert!(vfs.read("missing.txt").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn pak_rejects_index_larger_than_file() {
+        let dir = temp_dir("damaged");
+
+        // Claims four billion files
+        let mut bytes = PAK_MAGIC.to_vec();
+        bytes.extend_from_slice(&PAK_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(dir.join("count.pak"), &bytes).unwrap();
+        assert!(matches!(PakMount::open(dir.join("count.pak")), Err(EngineError::VfsError(_))));
+
+        // One file with a path far longer than the pak
+        let mut bytes = PAK_MAGIC.to_vec();
+        bytes.extend_from_slice(&PAK_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+        fs::write(dir.join("name.pak"), &bytes).unwrap();
+        assert!(matches!(PakMount::open(dir.join("name.pak")), Err(EngineError::VfsError(_))));
+
+        // One file whose data runs past the end of the pak
+        let mut bytes = PAK_MAGIC.to_vec();
+        bytes.extend_from_slice(&PAK_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.push(b'a');
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(dir.join("data.pak"), &bytes).unwrap();
+        assert!(matches!(PakMount::open(dir.join("data.pak")), Err(EngineError::VfsError(_))));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,457 @@
+use std::{rc::Rc, io::{self, Read, Seek, SeekFrom}, sync::mpsc::{self, Sender, Receiver, SyncSender, TryRecvError}, thread::{self, JoinHandle}};
+use image::ImageFormat;
+use silver_gl::{Texture, GlImage, gl};
+use crate::{EngineError, TextureOptions, TextureFilter, TextureWrap, create_texture_2d, upload_pixels_without_mipmaps};
+
+// Decoded frames waiting to be shown, the decoder waits when they aren't being used
+const FRAME_QUEUE_LENGTH: usize = 8;
+// Widest or tallest video that can be opened, 8K, as a frame is allocated up front
+const MAX_VIDEO_SIZE: i32 = 8192;
+
+// Where a video's bytes are read from, a file on disk or the whole file in memory
+pub(crate) trait VideoSource: Read + Seek + Send {}
+impl<T: Read + Seek + Send> VideoSource for T {}
+
+// Where each frame's JPEG is in a Motion JPEG AVI file, along with the video's
+// size and frame rate
+pub(crate) struct AviIndex {
+    pub width: i32,
+    pub height: i32,
+    pub frame_rate: f64,
+    pub frames: Vec<(u64, usize)> // Offset and length
+}
+
+// AVI files are RIFF chunks, with the stream headers in the hdrl list and the
+// frames as chunks named after their stream, e.g. "00dc", in the movi list. The
+// idx1 index is optional, so the movi list is walked instead, reading only the
+// chunk headers.
+pub(crate) fn parse_avi(path: &str, source: &mut dyn VideoSource) -> Result<AviIndex, EngineError> {
+    let length = source.seek(SeekFrom::End(0))?;
+    let header = if length >= 12 { read_at(source, 0, 12)? } else { Vec::new() };
+    if header.len() < 12 || &header[0..4] != b"RIFF" || &header[8..12] != b"AVI " {
+        return Err(EngineError::ResourceManagerError(format!("'{}' is not an AVI file!", path)));
+    }
+
+    let mut parser = AviParser::default();
+    parser.parse_chunks(source, 12, length)?;
+
+    let (width, height) = parser.size
+        .ok_or_else(|| EngineError::ResourceManagerError(format!("'{}' doesn't have a video stream!", path)))?;
+    // Negative heights are stored top-down, which JPEG frames are anyway
+    if width <= 0 || width > MAX_VIDEO_SIZE || height == 0 || height.unsigned_abs() > MAX_VIDEO_SIZE as u32 {
+        return Err(
+            EngineError::ResourceManagerError(
+                format!("'{}' is {}x{}, videos have to be between 1x1 and {}x{}!", path, width, height, MAX_VIDEO_SIZE, MAX_VIDEO_SIZE)
+            )
+        );
+    }
+    if !parser.compression.eq_ignore_ascii_case(b"MJPG") {
+        return Err(
+            EngineError::ResourceManagerError(
+                format!("'{}' is compressed with {}, only Motion JPEG (MJPG) is supported!", path, String::from_utf8_lossy(&parser.compression))
+            )
+        );
+    }
+    if parser.frames.is_empty() {
+        return Err(EngineError::ResourceManagerError(format!("'{}' doesn't have any frames!", path)));
+    }
+
+    let frame_rate = match (parser.rate, parser.scale, parser.frame_period) {
+        (rate, scale, _) if rate > 0 && scale > 0 => rate as f64 / scale as f64,
+        (_, _, period) if period > 0 => 1_000_000.0 / period as f64,
+        _ => return Err(EngineError::ResourceManagerError(format!("'{}' doesn't have a frame rate!", path)))
+    };
+
+    Ok(AviIndex { width, height: height.abs(), frame_rate, frames: parser.frames })
+}
+
+#[derive(Default)]
+struct AviParser {
+    stream_count: usize,
+    video_stream: Option<usize>,
+    compression: [u8; 4],
+    size: Option<(i32, i32)>,
+    rate: u32,
+    scale: u32,
+    frame_period: u32, // Microseconds, from the main header
+    frames: Vec<(u64, usize)>
+}
+
+impl AviParser {
+    fn parse_chunks(&mut self, source: &mut dyn VideoSource, start: u64, end: u64) -> io::Result<()> {
+        let mut position = start;
+
+        while position + 8 <= end {
+            let header = read_at(source, position, 8)?;
+            let id = &header[0..4];
+            let size = read_u32(&header, 4) as u64;
+            let data = position + 8;
+            // Files cut short still play the frames they have
+            let data_end = (data + size).min(end);
+            let length = data_end.saturating_sub(data) as usize;
+
+            match id {
+                b"LIST" if length >= 4 => self.parse_chunks(source, data + 4, data_end)?,
+                b"avih" if length >= 4 => self.frame_period = read_u32(&read_at(source, data, 4)?, 0),
+                b"strh" if length >= 28 => {
+                    let strh = read_at(source, data, 28)?;
+                    if &strh[0..4] == b"vids" && self.video_stream.is_none() {
+                        self.video_stream = Some(self.stream_count);
+                        self.scale = read_u32(&strh, 20);
+                        self.rate = read_u32(&strh, 24);
+                    }
+                    self.stream_count += 1;
+                },
+                // Follows its stream's strh, and is a BITMAPINFOHEADER for video
+                b"strf" if length >= 20 && self.video_stream == Some(self.stream_count.wrapping_sub(1)) => {
+                    let strf = read_at(source, data, 20)?;
+                    let width = read_u32(&strf, 4) as i32;
+                    let height = read_u32(&strf, 8) as i32;
+                    self.size = Some((width, height));
+                    self.compression.copy_from_slice(&strf[16..20]);
+                },
+                _ if &id[2..4] == b"dc" || &id[2..4] == b"db" => {
+                    let stream = std::str::from_utf8(&id[0..2]).ok().and_then(|stream| stream.parse::<usize>().ok());
+
+                    if stream.is_some() && stream == self.video_stream {
+                        if length > 0 {
+                            self.frames.push((data, length));
+                        } else if let Some(previous) = self.frames.last().copied() {
+                            // Empty chunks repeat the previous frame
+                            self.frames.push(previous);
+                        }
+                    }
+                },
+                _ => ()
+            }
+
+            // Chunks are padded to an even length
+            position = data + size + (size & 1);
+        }
+
+        Ok(())
+    }
+}
+
+fn read_u32(bytes: &[u8], position: usize) -> u32 {
+    u32::from_le_bytes(bytes[position..position + 4].try_into().unwrap())
+}
+
+fn read_at(source: &mut dyn VideoSource, position: u64, length: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0; length];
+    source.seek(SeekFrom::Start(position))?;
+    source.read_exact(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+enum VideoCommand {
+    Seek { generation: u64, frame: u64 }
+}
+
+struct DecodedFrame {
+    generation: u64, // Which seek the frame was decoded after, older frames are dropped
+    position: u64, // Frames since the start, counting every loop
+    image: Result<GlImage, String>
+}
+
+// A video being played, see ResourceManager::open_video(). Frames are decoded in
+// order on a thread of the video's own, looping back to the start, and the frame
+// for the current time is uploaded into the same texture during update(). If
+// decoding falls behind, the latest decoded frame stays up.
+pub struct VideoStream {
+    pub looping: bool,
+    pub speed: f64,
+    texture: Rc<Texture>,
+    width: i32,
+    height: i32,
+    frame_rate: f64,
+    frame_count: usize,
+    time: f64, // Seconds since the start, counting every loop
+    playing: bool,
+    finished: bool,
+    generation: u64,
+    next_frame: Option<DecodedFrame>,
+    error: Option<String>,
+    commands: Option<Sender<VideoCommand>>,
+    frames: Option<Receiver<DecodedFrame>>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl VideoStream {
+    // Has to be called on the GL thread, as the texture is created straight away
+    pub(crate) fn new(source: Box<dyn VideoSource>, index: AviIndex) -> Result<Self, EngineError> {
+        let blank = GlImage {
+            bytes: vec![0; index.width as usize * index.height as usize * 3],
+            internal_format: gl::SRGB8,
+            data_format: gl::RGB,
+            width: index.width,
+            height: index.height
+        };
+        let texture = Rc::new(create_texture_2d(blank));
+        TextureOptions {
+            mipmaps: false,
+            wrap: TextureWrap::ClampToEdge,
+            filter: TextureFilter::Linear,
+            ..Default::default()
+        }.apply(&texture);
+
+        let (command_sender, command_receiver) = mpsc::channel();
+        let (frame_sender, frame_receiver) = mpsc::sync_channel(FRAME_QUEUE_LENGTH);
+        let frame_count = index.frames.len();
+        let (width, height, frame_rate) = (index.width, index.height, index.frame_rate);
+
+        let thread = thread::Builder::new()
+            .name(String::from("video_decoder"))
+            .spawn(move || decode_frames(source, index, command_receiver, frame_sender))?;
+
+        Ok(
+            Self {
+                looping: false,
+                speed: 1.0,
+                texture,
+                width,
+                height,
+                frame_rate,
+                frame_count,
+                time: 0.0,
+                playing: true,
+                finished: false,
+                generation: 0,
+                next_frame: None,
+                error: None,
+                commands: Some(command_sender),
+                frames: Some(frame_receiver),
+                thread: Some(thread)
+            }
+        )
+    }
+
+    pub fn get_texture(&self) -> &Rc<Texture> { &self.texture }
+    pub fn get_size(&self) -> (i32, i32) { (self.width, self.height) }
+    pub fn get_frame_rate(&self) -> f64 { self.frame_rate }
+    pub fn get_frame_count(&self) -> usize { self.frame_count }
+    // Seconds
+    pub fn get_duration(&self) -> f64 { self.frame_count as f64 / self.frame_rate }
+
+    // Seconds into the video, use this to keep text and audio in sync
+    pub fn get_time(&self) -> f64 {
+        if self.finished { self.get_duration() } else { self.time % self.get_duration() }
+    }
+
+    pub fn play(&mut self) {
+        if self.finished {
+            self.seek(0.0);
+        }
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) { self.playing = false }
+    pub fn is_playing(&self) -> bool { self.playing }
+
+    // Only videos that aren't looping finish
+    pub fn is_finished(&self) -> bool { self.finished }
+
+    // The last frame that failed to decode, which was skipped
+    pub fn get_error(&self) -> Option<&str> { self.error.as_deref() }
+
+    pub fn seek(&mut self, time: f64) {
+        self.time = time.clamp(0.0, self.get_duration());
+        self.finished = false;
+        self.generation += 1;
+        self.next_frame = None;
+
+        let frame = ((self.time * self.frame_rate) as u64).min(self.frame_count as u64 - 1);
+        if let Some(commands) = &self.commands {
+            commands.send(VideoCommand::Seek { generation: self.generation, frame }).ok();
+        }
+    }
+
+    // Advances the time and shows the frame for it, returning whether the video
+    // finished during this update. Called by VideoWidget::update().
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        let mut finished = false;
+
+        if self.playing {
+            // Frames are only decoded forwards, so negative speeds hold at the start
+            self.time = (self.time + delta_time as f64 * self.speed).max(0.0);
+
+            if !self.looping && self.time >= self.get_duration() {
+                self.time = self.get_duration();
+                self.playing = false;
+                self.finished = true;
+                finished = true;
+            }
+        }
+
+        let target = ((self.time * self.frame_rate) as u64).min(if self.looping { u64::MAX } else { self.frame_count as u64 - 1 });
+        let mut latest = None;
+
+        while let Some(frame) = self.next_frame.take().or_else(|| self.frames.as_ref().and_then(|frames| frames.try_recv().ok())) {
+            if frame.generation != self.generation {
+                continue;
+            }
+            if frame.position > target {
+                self.next_frame = Some(frame);
+                break;
+            }
+
+            latest = Some(frame);
+        }
+
+        match latest.map(|frame| (frame.position, frame.image)) {
+            Some((_, Ok(image))) if (image.width, image.height) == (self.width, self.height) => upload_pixels_without_mipmaps(&self.texture, &image),
+            Some((position, Ok(image))) => {
+                self.error = Some(
+                    format!(
+                        "Frame {} is {}x{}, but the video is {}x{}!",
+                        position % self.frame_count as u64, image.width, image.height, self.width, self.height
+                    )
+                );
+            },
+            Some((_, Err(err))) => self.error = Some(err),
+            None => ()
+        }
+
+        finished
+    }
+}
+
+// Stops the decoding thread, which finishes when it next tries to send a frame
+impl Drop for VideoStream {
+    fn drop(&mut self) {
+        self.commands = None;
+        self.frames = None;
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+fn decode_frames(mut source: Box<dyn VideoSource>, index: AviIndex, commands: Receiver<VideoCommand>, frames: SyncSender<DecodedFrame>) {
+    let (mut generation, mut position) = (0, 0);
+
+    loop {
+        // Only the latest seek matters
+        loop {
+            match commands.try_recv() {
+                Ok(VideoCommand::Seek { generation: seek_generation, frame }) => {
+                    generation = seek_generation;
+                    position = frame;
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return
+            }
+        }
+
+        let (offset, length) = index.frames[(position % index.frames.len() as u64) as usize];
+        let image = read_at(source.as_mut(), offset, length)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| image::load_from_memory_with_format(&bytes, ImageFormat::Jpeg).map_err(|err| err.to_string()))
+            .map(|image| {
+                let image = image.to_rgb8();
+
+                GlImage {
+                    width: image.width() as i32,
+                    height: image.height() as i32,
+                    bytes: image.into_raw(),
+                    internal_format: gl::SRGB8,
+                    data_format: gl::RGB
+                }
+            })
+            .map_err(|err| format!("Frame {} failed to decode: {}", position % index.frames.len() as u64, err));
+
+        // The stream was dropped
+        if frames.send(DecodedFrame { generation, position, image }).is_err() {
+            return;
+        }
+        position += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use crate::{CSEngine, CSEngineConfig, GraphicsLibrary};
+    use super::*;
+
+    fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes.extend(data);
+        if data.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    fn list(kind: &[u8], chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = kind.to_vec();
+        data.extend(chunks.concat());
+        chunk(b"LIST", &data)
+    }
+
+    fn avi(width: i32, height: i32, frames: &[&[u8]]) -> Vec<u8> {
+        let mut strh = b"vidsMJPG".to_vec();
+        strh.extend([0; 12]);
+        strh.extend(1u32.to_le_bytes()); // Scale
+        strh.extend(25u32.to_le_bytes()); // Rate
+
+        let mut strf = 40u32.to_le_bytes().to_vec();
+        strf.extend(width.to_le_bytes());
+        strf.extend(height.to_le_bytes());
+        strf.extend([1, 0, 24, 0]);
+        strf.extend(b"MJPG");
+
+        let hdrl = list(b"hdrl", &[list(b"strl", &[chunk(b"strh", &strh), chunk(b"strf", &strf)])]);
+        let movi = list(b"movi", &frames.iter().map(|frame| chunk(b"00dc", frame)).collect::<Vec<_>>());
+
+        let mut data = b"AVI ".to_vec();
+        data.extend(hdrl);
+        data.extend(movi);
+        chunk(b"RIFF", &data)
+    }
+
+    #[test]
+    fn parse_avi_finds_frames() {
+        let bytes = avi(4, -2, &[b"abc", b"", b"defg"]);
+        let index = parse_avi("clip.avi", &mut Cursor::new(bytes.clone())).unwrap();
+
+        assert_eq!((index.width, index.height), (4, 2));
+        assert_eq!(index.frame_rate, 25.0);
+        assert_eq!(index.frames.len(), 3);
+        assert_eq!(index.frames[0], index.frames[1]);
+
+        let (offset, length) = index.frames[2];
+        assert_eq!(&bytes[offset as usize..offset as usize + length], b"defg");
+    }
+
+    #[test]
+    fn parse_avi_rejects_invalid_sizes() {
+        for (width, height) in [(0, 2), (4, 0), (-4, 2), (MAX_VIDEO_SIZE + 1, 2), (4, i32::MIN)] {
+            assert!(parse_avi("clip.avi", &mut Cursor::new(avi(width, height, &[b"abc"]))).is_err());
+        }
+    }
+
+    #[test]
+    fn parse_avi_rejects_other_files() {
+        assert!(parse_avi("clip.avi", &mut Cursor::new(b"RIFF".to_vec())).is_err());
+    }
+
+    #[test]
+    fn negative_speed_holds_at_start() {
+        let _engine = CSEngine::new(CSEngineConfig {
+            gl: GraphicsLibrary::None,
+            ..Default::default()
+        });
+
+        let bytes = avi(4, 2, &[b"abc", b"def"]);
+        let index = parse_avi("clip.avi", &mut Cursor::new(bytes.clone())).unwrap();
+        let mut video = VideoStream::new(Box::new(Cursor::new(bytes)), index).unwrap();
+
+        video.speed = -1.0;
+        video.update(0.5);
+        assert_eq!(video.get_time(), 0.0);
+    }
+}
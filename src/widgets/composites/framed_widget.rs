@@ -1,5 +1,5 @@
 use cgmath::{Vector4, Matrix4, vec3};
-use crate::{primitives::{BackgroundWidget, BorderWidget}, Widget, EngineError};
+use crate::{primitives::{BackgroundWidget, BorderWidget}, Widget, EngineError, DrawRecorder};
 
 // TODO: add from funcs
 pub trait FramedWidget: Widget {
@@ -39,7 +39,7 @@ pub trait FramedWidget: Widget {
     fn set_padding(&mut self, widths: Vector4<f32>);
 
     // Reduces vec space of inner widget by padding and borders
-    fn draw_children(&mut self, vec_space: &Matrix4<f32>) -> Result<(), EngineError> {
+    fn get_inner_vec_space(&self, vec_space: &Matrix4<f32>) -> Matrix4<f32> {
         let padding = self.get_padding();
         let border_widths = self.get_border_widths();
 
@@ -51,8 +51,12 @@ pub trait FramedWidget: Widget {
 
         let mut inner_vec_space = Matrix4::<f32>::from_translation(vec3(x, y, 0.0));
         inner_vec_space = inner_vec_space * Matrix4::<f32>::from_nonuniform_scale(width, height, 1.0);
-        inner_vec_space = vec_space * inner_vec_space;
 
+        vec_space * inner_vec_space
+    }
+
+    fn draw_children(&mut self, vec_space: &Matrix4<f32>) -> Result<(), EngineError> {
+        let inner_vec_space = self.get_inner_vec_space(vec_space);
         let children = self.get_children_mut();
 
         children[0].draw(vec_space)?;
@@ -61,4 +65,13 @@ pub trait FramedWidget: Widget {
 
         Ok(())
     }
+
+    fn record_children(&mut self, vec_space: &Matrix4<f32>, recorder: &mut DrawRecorder) {
+        let inner_vec_space = self.get_inner_vec_space(vec_space);
+        let children = self.get_children_mut();
+
+        children[0].record(vec_space, recorder);
+        children[1].record(&inner_vec_space, recorder);
+        children[2].record(vec_space, recorder);
+    }
 }
\ No newline at end of file
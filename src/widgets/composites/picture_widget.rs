@@ -1,7 +1,7 @@
 use std::rc::Rc;
 use cgmath::{Vector4, Quaternion, Vector2, Matrix4, vec2, SquareMatrix};
 use silver_gl::{ShaderProgram, MultiBindModel, Texture};
-use crate::{Widget, EngineError, primitives::{TextureWidget, BackgroundWidget, BorderWidget}, FramedWidget, create_wquad};
+use crate::{Widget, EngineError, primitives::{TextureWidget, BackgroundWidget, BorderWidget}, FramedWidget, create_wquad, DrawRecorder, ResourceManager, ShaderPathBundle};
 
 pub struct PictureWidget {
     pub position: Vector2<f32>,
@@ -44,6 +44,30 @@ impl PictureWidget {
             padding
         }
     }
+
+    // The picture itself is only drawn through its children
+    pub fn with_default_shaders(
+        resource_manager: &mut ResourceManager,
+        padding: Vector4<f32>,
+        background_colour: Vector4<f32>,
+        texture: Rc<Texture>,
+        border_colour: Vector4<f32>,
+        border_widths: Vector4<f32>
+    ) -> Result<Self, EngineError> {
+        Ok(
+            Self::new(
+                resource_manager.load_shader_program(ShaderPathBundle::container())?,
+                resource_manager.load_shader_program(ShaderPathBundle::background())?,
+                resource_manager.load_shader_program(ShaderPathBundle::texture())?,
+                resource_manager.load_shader_program(ShaderPathBundle::border())?,
+                padding,
+                background_colour,
+                texture,
+                border_colour,
+                border_widths
+            )
+        )
+    }
 }
 
 impl FramedWidget for PictureWidget {
@@ -82,4 +106,7 @@ impl Widget for PictureWidget {
     fn draw_children(&mut self, vec_space: &Matrix4<f32>) -> Result<(), EngineError> {
         FramedWidget::draw_children(self, vec_space)
     }
+    fn record_children(&mut self, vec_space: &Matrix4<f32>, recorder: &mut DrawRecorder) {
+        FramedWidget::record_children(self, vec_space, recorder)
+    }
 }
\ No newline at end of file
@@ -2,9 +2,11 @@ mod widget;
 mod widget_quad;
 mod composites;
 mod border_widths;
+mod text_layout;
 pub mod primitives;
 
 pub use widget::*;
 pub use widget_quad::*;
 pub use composites::*;
-pub use border_widths::*;
\ No newline at end of file
+pub use border_widths::*;
+pub use text_layout::*;
\ No newline at end of file
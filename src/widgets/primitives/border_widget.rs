@@ -1,7 +1,7 @@
 use std::rc::Rc;
 use cgmath::{Vector4, Quaternion, Vector2, Matrix4, vec2, SquareMatrix};
 use silver_gl::{ShaderProgram, MultiBindModel};
-use crate::{Widget, EngineError, create_wquad};
+use crate::{Widget, EngineError, create_wquad, ResourceManager, ShaderPathBundle};
 
 pub struct BorderWidget {
     pub position: Vector2<f32>,
@@ -40,6 +40,14 @@ impl BorderWidget {
             border_widths
         }
     }
+
+    pub fn with_default_shader(
+        resource_manager: &mut ResourceManager,
+        colour: Vector4<f32>,
+        border_widths: Vector4<f32>
+    ) -> Result<Self, EngineError> {
+        Ok(Self::new(resource_manager.load_shader_program(ShaderPathBundle::border())?, colour, border_widths))
+    }
 }
 
 impl Widget for BorderWidget {
@@ -2,8 +2,14 @@ mod background_widget;
 mod texture_widget;
 mod border_widget;
 mod container_widget;
+mod viewport_widget;
+mod text_widget;
+mod video_widget;
 
 pub use background_widget::*;
 pub use texture_widget::*;
 pub use border_widget::*;
-pub use container_widget::*;
\ No newline at end of file
+pub use container_widget::*;
+pub use viewport_widget::*;
+pub use text_widget::*;
+pub use video_widget::*;
\ No newline at end of file
@@ -0,0 +1,305 @@
+use std::{rc::Rc, collections::HashMap};
+use cgmath::{Quaternion, Vector2, Vector4, Matrix4, vec2, vec3, vec4, SquareMatrix};
+use silver_gl::{ShaderProgram, MultiBindModel, ModelCreateTrait, Vertex, Mesh, Texture};
+use crate::{Widget, EngineError, create_wquad, ResourceManager, GlyphMetaDeta, GlyphData, TextWrap, TextAlignment, wrap_text, FontRenderMode};
+
+#[derive(Debug, Clone)]
+pub struct TextStyle {
+    pub font_family: String,
+    pub font_size: u32,
+    pub colour: Vector4<f32>,
+    pub width: usize, // In characters
+    pub height: usize, // In lines
+    pub wrap: TextWrap,
+    pub alignment: TextAlignment,
+    // Only drawn for fonts using FontRenderMode::Sdf, widths are in pixels at the
+    // font size and are limited by the font's spread
+    pub outline_colour: Vector4<f32>,
+    pub outline_width: f32,
+    pub glow_colour: Vector4<f32>,
+    pub glow_width: f32
+}
+
+impl TextStyle {
+    pub fn new(font_family: &str, font_size: u32) -> Self {
+        Self {
+            font_family: font_family.to_owned(),
+            font_size,
+            colour: vec4(1.0, 1.0, 1.0, 1.0),
+            width: 40,
+            height: 3,
+            wrap: TextWrap::default(),
+            alignment: TextAlignment::default(),
+            outline_colour: vec4(0.0, 0.0, 0.0, 1.0),
+            outline_width: 0.0,
+            glow_colour: vec4(0.0, 0.0, 0.0, 0.5),
+            glow_width: 0.0
+        }
+    }
+}
+
+// Lays out text inside a box that is style.width characters wide and style.height
+// lines tall, which is stretched to fill the widget. Characters are measured with
+// the advance of 'M', use get_pixel_size() to find the size the text looks best at.
+// Glyphs are grouped by the glyph atlas page they are on, so usually the whole
+// text is one mesh drawn in a single call. If the glyphs are dropped by the
+// resource manager, the text is laid out again before the next frame is drawn.
+// Fonts using FontRenderMode::Sdf need to be drawn with the SDF text shader, which
+// is given the outline and glow from the style.
+pub struct TextWidget {
+    pub position: Vector2<f32>,
+    pub rotation: Quaternion<f32>,
+    pub width: f32,
+    pub height: f32,
+    pub children: Vec<Box<dyn Widget>>,
+    pub shader_program: Rc<ShaderProgram>,
+    pub model: MultiBindModel,
+    pub vec_space: Matrix4<f32>,
+
+    // Call layout() after changing the style directly
+    pub style: TextStyle,
+    text: String,
+    lines: Vec<String>,
+    pixel_size: (f32, f32),
+    glyph_generation: u64,
+    sdf_params: Option<Vector4<f32>>
+}
+
+impl TextWidget {
+    pub fn new(
+        resource_manager: &mut ResourceManager,
+        shader_program: Rc<ShaderProgram>,
+        text: &str,
+        style: TextStyle
+    ) -> Result<Self, EngineError> {
+        let mut widget = Self {
+            position: vec2(0.0, 0.0),
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            width: 1.0,
+            height: 1.0,
+            children: Vec::new(),
+            shader_program,
+            model: create_wquad(),
+            vec_space: Matrix4::identity(),
+            style,
+            text: text.to_owned(),
+            lines: Vec::new(),
+            pixel_size: (0.0, 0.0),
+            glyph_generation: 0,
+            sdf_params: None
+        };
+
+        widget.layout(resource_manager)?;
+
+        Ok(widget)
+    }
+
+    pub fn get_text(&self) -> &str { &self.text }
+    pub fn set_text(&mut self, resource_manager: &mut ResourceManager, text: &str) -> Result<(), EngineError> {
+        self.text = text.to_owned();
+        self.layout(resource_manager)
+    }
+
+    pub fn set_style(&mut self, resource_manager: &mut ResourceManager, style: TextStyle) -> Result<(), EngineError> {
+        self.style = style;
+        self.layout(resource_manager)
+    }
+
+    // Text after wrapping and cropping
+    pub fn get_lines(&self) -> &Vec<String> { &self.lines }
+
+    // Size of the text box in pixels at the style's font size and the resource
+    // manager's content scale
+    pub fn get_pixel_size(&self) -> (f32, f32) { self.pixel_size }
+
+    // True when glyphs this text uses have been dropped by the resource manager,
+    // e.g. removed from the glyph atlas or rasterized for another content scale
+    pub fn needs_layout(&self, resource_manager: &ResourceManager) -> bool {
+        self.glyph_generation != resource_manager.get_glyph_generation()
+    }
+
+    fn load_glyph(&self, resource_manager: &mut ResourceManager, glyph: char) -> Result<Rc<GlyphData>, EngineError> {
+        resource_manager.load_glyph(GlyphMetaDeta {
+            font_family: self.style.font_family.clone(),
+            font_size: self.style.font_size,
+            glyph
+        })
+    }
+
+    // Wraps the text and rebuilds the model from its glyphs
+    pub fn layout(&mut self, resource_manager: &mut ResourceManager) -> Result<(), EngineError> {
+        resource_manager.pin_glyphs();
+        let result = self.layout_glyphs(resource_manager);
+        resource_manager.unpin_glyphs();
+
+        result
+    }
+
+    fn layout_glyphs(&mut self, resource_manager: &mut ResourceManager) -> Result<(), EngineError> {
+        self.lines = wrap_text(&self.text, self.style.width, self.style.height, &self.style.wrap);
+
+        let metrics = resource_manager.load_face_metrics(&self.style.font_family, self.style.font_size)?;
+        let line_height = metrics.line_height;
+
+        // Advance is stored in 1/64ths of a pixel
+        let cell_width = (self.load_glyph(resource_manager, 'M')?.advance >> 6) as f32;
+
+        let box_width = cell_width * self.style.width as f32;
+        let box_height = line_height * self.style.height as f32;
+        self.pixel_size = (box_width, box_height);
+
+        // Quads (x, y, width, height) in pixels with their UVs, grouped by atlas page
+        let mut groups: Vec<(Rc<Texture>, Vec<(Vector4<f32>, Vector4<f32>)>)> = Vec::new();
+        let mut group_indices: HashMap<usize, usize> = HashMap::new();
+
+        for (line_index, line) in self.lines.iter().enumerate() {
+            // Each glyph is stored with how far the pen moves after it, including kerning
+            let mut glyphs: Vec<(Rc<GlyphData>, f32)> = Vec::new();
+            let chars: Vec<char> = line.chars().collect();
+            for (i, c) in chars.iter().enumerate() {
+                let glyph = self.load_glyph(resource_manager, *c)?;
+                let mut advance = (glyph.advance >> 6) as f32;
+                if let Some(next) = chars.get(i + 1) {
+                    advance += resource_manager.get_kerning(&self.style.font_family, self.style.font_size, *c, *next)?;
+                }
+
+                glyphs.push((glyph, advance));
+            }
+
+            let line_width: f32 = glyphs.iter().map(|(_, advance)| advance).sum();
+            let mut pen_x = self.style.alignment.line_offset(box_width, line_width);
+            let baseline = line_index as f32 * line_height + metrics.ascender;
+
+            for (glyph, advance) in glyphs {
+                // Whitespace has no bitmap, only an advance
+                if glyph.size.x > 0 && glyph.size.y > 0 {
+                    let quad = vec4(
+                        pen_x + glyph.bearing.x as f32,
+                        baseline - glyph.bearing.y as f32,
+                        glyph.size.x as f32,
+                        glyph.size.y as f32
+                    );
+
+                    let index = *group_indices.entry(glyph.page).or_insert_with(|| {
+                        groups.push((Rc::clone(&glyph.texture), Vec::new()));
+                        groups.len() - 1
+                    });
+                    groups[index].1.push((quad, glyph.uv));
+                }
+
+                pen_x += advance;
+            }
+        }
+
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        let mut meshes: Vec<Mesh> = Vec::new();
+
+        for (texture, quads) in groups {
+            let offset = indices.len();
+
+            for (quad, uv) in quads.iter() {
+                // Convert to the widget's [0,1] space
+                let quad = vec4(quad.x / box_width, quad.y / box_height, quad.z / box_width, quad.w / box_height);
+
+                push_quad(&mut vertices, &mut indices, quad, *uv);
+            }
+
+            let mut mesh = Mesh::new(offset, (quads.len() * 6) as i32);
+            mesh.diffuse_textures.push(texture);
+            meshes.push(mesh);
+        }
+
+        self.model = MultiBindModel::new(vertices, indices, Vec::new(), meshes);
+        // Glyphs were pinned, so evictions while loading them didn't affect this text
+        self.glyph_generation = resource_manager.get_glyph_generation();
+        self.sdf_params = self.get_sdf_params(resource_manager);
+
+        Ok(())
+    }
+
+    // Field values of the glyph's edge, the outside of the outline, and the outside of
+    // the glow, in the same space as the SDF stored in the atlas
+    fn get_sdf_params(&self, resource_manager: &ResourceManager) -> Option<Vector4<f32>> {
+        match resource_manager.get_font_render_mode(&self.style.font_family) {
+            FontRenderMode::Sdf { spread } => {
+                let scale = resource_manager.get_content_scale() / (2.0 * spread.max(1) as f32);
+                let outline_edge = (0.5 - self.style.outline_width * scale).max(0.0);
+                let glow_edge = (outline_edge - self.style.glow_width * scale).max(0.0);
+
+                Some(vec4(0.5, outline_edge, glow_edge, 0.0))
+            },
+            FontRenderMode::Bitmap => None
+        }
+    }
+}
+
+// Same layout as create_wquad(), placed at quad (x, y, width, height) and textured
+// with uv (left, top, right, bottom)
+fn push_quad(vertices: &mut Vec<Vertex>, indices: &mut Vec<u32>, quad: Vector4<f32>, uv: Vector4<f32>) {
+    let base = vertices.len() as u32;
+    let corners = [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)];
+
+    for (u, v) in corners {
+        vertices.push(
+            Vertex {
+                position: vec3(quad.x + u * quad.z, quad.y + v * quad.w, 0.0),
+                normal: vec3(0.0, 0.0, 1.0),
+                tex_coord: vec2(uv.x + u * (uv.z - uv.x), uv.y + v * (uv.w - uv.y)),
+                ..Vertex::default()
+            }
+        );
+    }
+
+    indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+}
+
+impl Widget for TextWidget {
+    fn get_position(&self) -> Vector2<f32> { self.position }
+    fn set_position(&mut self, pos: Vector2<f32>) { self.position = pos }
+
+    fn get_rotation(&self) -> Quaternion<f32> { self.rotation }
+    fn set_rotation(&mut self, rot: Quaternion<f32>) { self.rotation = rot }
+
+    fn get_size(&self) -> (f32, f32) { (self.width, self.height) }
+    fn set_size(&mut self, width: f32, height: f32) { self.width = width; self.height = height }
+
+    fn get_children(&self) -> &Vec<Box<dyn Widget>> { &self.children }
+    fn get_children_mut(&mut self) -> &mut Vec<Box<dyn Widget>> { &mut self.children }
+
+    fn get_shader_program(&self) -> &Rc<ShaderProgram> { &self.shader_program }
+    fn set_shader_program(&mut self, shader_program: Rc<ShaderProgram>) { self.shader_program = shader_program }
+
+    fn get_model(&self) -> &MultiBindModel { &self.model }
+    fn get_model_mut(&mut self) -> &mut MultiBindModel { &mut self.model }
+    fn set_model(&mut self, model: MultiBindModel) { self.model = model }
+
+    fn get_vec_space(&self) -> Matrix4<f32> { self.vec_space }
+    fn set_vec_space(&mut self, vec_space: Matrix4<f32>) { self.vec_space = vec_space }
+
+    fn refresh_layout(&mut self, resource_manager: &mut ResourceManager) -> Result<(), EngineError> {
+        for widget in &mut self.children {
+            widget.refresh_layout(resource_manager)?;
+        }
+
+        if self.needs_layout(resource_manager) {
+            self.layout(resource_manager)?;
+        }
+
+        Ok(())
+    }
+
+    fn update_shader_program(&self) -> Result<(), EngineError> {
+        unsafe {
+            self.shader_program.set_vector_4_unsafe("colour", &self.style.colour)?;
+
+            if let Some(sdf_params) = &self.sdf_params {
+                self.shader_program.set_vector_4_unsafe("outline_colour", &self.style.outline_colour)?;
+                self.shader_program.set_vector_4_unsafe("glow_colour", &self.style.glow_colour)?;
+                self.shader_program.set_vector_4_unsafe("sdf_params", sdf_params)?;
+            }
+        }
+
+        Ok(())
+    }
+}
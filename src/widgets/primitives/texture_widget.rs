@@ -1,7 +1,7 @@
 use std::rc::Rc;
 use cgmath::{Quaternion, Vector2, Matrix4, vec2, SquareMatrix};
 use silver_gl::{ShaderProgram, MultiBindModel, Texture, Mesh};
-use crate::{Widget, EngineError, create_wquad};
+use crate::{Widget, EngineError, create_wquad, ResourceManager, ShaderPathBundle, AnimatedTexture, AnimationPlayer, LoopMode};
 
 pub struct TextureWidget {
     pub position: Vector2<f32>,
@@ -12,6 +12,8 @@ pub struct TextureWidget {
     pub shader_program: Rc<ShaderProgram>,
     pub model: MultiBindModel,
     pub vec_space: Matrix4<f32>,
+
+    pub animation: Option<AnimationPlayer>, // Sets the texture to its current frame each update
 }
 
 impl TextureWidget {
@@ -24,13 +26,50 @@ impl TextureWidget {
             children: Vec::new(),
             shader_program,
             model: create_wquad(),
-            vec_space: Matrix4::identity()
+            vec_space: Matrix4::identity(),
+            animation: None
         };
 
         widget.model.meshes[0].diffuse_textures.push(texture);
 
         widget
     }
+
+    pub fn with_default_shader(resource_manager: &mut ResourceManager, texture: Rc<Texture>) -> Result<Self, EngineError> {
+        Ok(Self::new(resource_manager.load_shader_program(ShaderPathBundle::texture())?, texture))
+    }
+
+    // Starts playing from the first frame, errors if the animation has no frames
+    pub fn new_animated(shader_program: Rc<ShaderProgram>, animation: Rc<AnimatedTexture>, loop_mode: LoopMode) -> Result<Self, EngineError> {
+        let first_frame = animation.frames.first().cloned().ok_or_else(|| {
+            EngineError::ResourceManagerError("Can't create an animated texture widget from an animation without any frames!".to_string())
+        })?;
+
+        let mut widget = Self::new(shader_program, first_frame);
+        widget.animation = Some(AnimationPlayer::new(animation, loop_mode));
+
+        Ok(widget)
+    }
+
+    // Advances the animation and shows its current frame, returning whether it
+    // finished during this update. Called by update(), so only needs calling
+    // directly for widgets outside of a scene.
+    pub fn update_animation(&mut self, delta_time: f32) -> bool {
+        let (finished, texture) = match &mut self.animation {
+            Some(animation) => (animation.advance(delta_time), animation.get_texture().cloned()),
+            None => return false
+        };
+
+        if let Some(texture) = texture {
+            let current = self.get_texture().map_or(false, |current| Rc::ptr_eq(current, &texture));
+            if !current {
+                // Can't fail, texture widgets always take a texture
+                let _ = self.set_texture(texture);
+            }
+        }
+
+        finished
+    }
 }
 
 impl Widget for TextureWidget {
@@ -82,6 +121,14 @@ impl Widget for TextureWidget {
         Ok(())
     }
 
+    fn update(&mut self, delta_time: f32) {
+        for widget in self.get_children_mut() {
+            widget.update(delta_time);
+        }
+
+        self.update_animation(delta_time);
+    }
+
     // No need to update SP
     fn update_shader_program(&self) -> Result<(), EngineError> { Ok(()) }
 }
\ No newline at end of file
@@ -0,0 +1,82 @@
+use std::rc::Rc;
+use cgmath::{Quaternion, Vector2, Matrix4, vec2, SquareMatrix};
+use silver_gl::{ShaderProgram, MultiBindModel, Texture};
+use crate::{Widget, EngineError, create_wquad, ResourceManager, ShaderPathBundle, VideoStream};
+
+// Plays a video in the widget, using the same shaders as TextureWidget. Playback
+// is controlled through the video, e.g. widget.video.seek(), and its time is kept
+// by the widget's update, so text and audio can follow video.get_time().
+pub struct VideoWidget {
+    pub position: Vector2<f32>,
+    pub rotation: Quaternion<f32>,
+    pub width: f32,
+    pub height: f32,
+    pub children: Vec<Box<dyn Widget>>,
+    pub shader_program: Rc<ShaderProgram>,
+    pub model: MultiBindModel,
+    pub vec_space: Matrix4<f32>,
+
+    pub video: VideoStream,
+}
+
+impl VideoWidget {
+    pub fn new(shader_program: Rc<ShaderProgram>, video: VideoStream) -> Self {
+        let mut widget = Self {
+            position: vec2(0.0, 0.0),
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            width: 1.0,
+            height: 1.0,
+            children: Vec::new(),
+            shader_program,
+            model: create_wquad(),
+            vec_space: Matrix4::identity(),
+            video
+        };
+
+        widget.model.meshes[0].diffuse_textures.push(Rc::clone(widget.video.get_texture()));
+
+        widget
+    }
+
+    pub fn with_default_shader(resource_manager: &mut ResourceManager, video: VideoStream) -> Result<Self, EngineError> {
+        Ok(Self::new(resource_manager.load_shader_program(ShaderPathBundle::texture())?, video))
+    }
+}
+
+impl Widget for VideoWidget {
+    fn get_position(&self) -> Vector2<f32> { self.position }
+    fn set_position(&mut self, pos: Vector2<f32>) { self.position = pos }
+
+    fn get_rotation(&self) -> Quaternion<f32> { self.rotation }
+    fn set_rotation(&mut self, rot: Quaternion<f32>) { self.rotation = rot }
+
+    fn get_size(&self) -> (f32, f32) { (self.width, self.height) }
+    fn set_size(&mut self, width: f32, height: f32) { self.width = width; self.height = height }
+
+    fn get_children(&self) -> &Vec<Box<dyn Widget>> { &self.children }
+    fn get_children_mut(&mut self) -> &mut Vec<Box<dyn Widget>> { &mut self.children }
+
+    fn get_shader_program(&self) -> &Rc<ShaderProgram> { &self.shader_program }
+    fn set_shader_program(&mut self, shader_program: Rc<ShaderProgram>) { self.shader_program = shader_program }
+
+    fn get_model(&self) -> &MultiBindModel { &self.model }
+    fn get_model_mut(&mut self) -> &mut MultiBindModel { &mut self.model }
+    fn set_model(&mut self, model: MultiBindModel) { self.model = model }
+
+    fn get_vec_space(&self) -> Matrix4<f32> { self.vec_space }
+    fn set_vec_space(&mut self, vec_space: Matrix4<f32>) { self.vec_space = vec_space }
+
+    // The video's frames are always uploaded into the same texture
+    fn get_texture(&self) -> Option<&Rc<Texture>> { Some(self.video.get_texture()) }
+
+    fn update(&mut self, delta_time: f32) {
+        for widget in self.get_children_mut() {
+            widget.update(delta_time);
+        }
+
+        self.video.update(delta_time);
+    }
+
+    // No need to update SP
+    fn update_shader_program(&self) -> Result<(), EngineError> { Ok(()) }
+}
@@ -0,0 +1,111 @@
+use std::rc::Rc;
+use cgmath::{Quaternion, Vector2, Matrix4, vec2, SquareMatrix};
+use silver_gl::{ShaderProgram, MultiBindModel, Texture};
+use crate::{Widget, EngineError, create_flipped_wquad, View3DScene, Scene, DrawRecorder};
+
+// Displays a 3D scene, which is drawn to its own render pipeline at the widget's
+// pixel size every frame. Like the compositor, every texture from the scene's
+// get_link() is passed to the shader as a diffuse texture, which for
+// View3DRenderPipeline is the lighting output followed by the bloom blur and depth.
+pub struct ViewportWidget {
+    pub position: Vector2<f32>,
+    pub rotation: Quaternion<f32>,
+    pub width: f32,
+    pub height: f32,
+    pub children: Vec<Box<dyn Widget>>,
+    pub shader_program: Rc<ShaderProgram>,
+    pub model: MultiBindModel,
+    pub vec_space: Matrix4<f32>,
+
+    pub scene: View3DScene,
+}
+
+impl ViewportWidget {
+    pub fn new(shader_program: Rc<ShaderProgram>, scene: View3DScene) -> Self {
+        Self {
+            position: vec2(0.0, 0.0),
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            width: 1.0,
+            height: 1.0,
+            children: Vec::new(),
+            shader_program,
+            model: create_flipped_wquad(),
+            vec_space: Matrix4::identity(),
+            scene
+        }
+    }
+}
+
+impl Widget for ViewportWidget {
+    fn get_position(&self) -> Vector2<f32> { self.position }
+    fn set_position(&mut self, pos: Vector2<f32>) { self.position = pos }
+
+    fn get_rotation(&self) -> Quaternion<f32> { self.rotation }
+    fn set_rotation(&mut self, rot: Quaternion<f32>) { self.rotation = rot }
+    
+    fn get_size(&self) -> (f32, f32) { (self.width, self.height) }
+    fn set_size(&mut self, width: f32, height: f32) { self.width = width; self.height = height }
+
+    fn get_children(&self) -> &Vec<Box<dyn Widget>> { &self.children }
+    fn get_children_mut(&mut self) -> &mut Vec<Box<dyn Widget>> { &mut self.children }
+
+    fn get_shader_program(&self) -> &Rc<ShaderProgram> { &self.shader_program }
+    fn set_shader_program(&mut self, shader_program: Rc<ShaderProgram>) { self.shader_program = shader_program }
+
+    fn get_model(&self) -> &MultiBindModel { &self.model }
+    fn get_model_mut(&mut self) -> &mut MultiBindModel { &mut self.model }
+    fn set_model(&mut self, model: MultiBindModel) { self.model = model }
+
+    fn get_vec_space(&self) -> Matrix4<f32> { self.vec_space }
+    fn set_vec_space(&mut self, vec_space: Matrix4<f32>) { self.vec_space = vec_space }
+
+    // Texture is owned by the scene's render pipeline, so it can't be set
+    fn get_texture(&self) -> Option<&Rc<Texture>> {
+        self.model.meshes.get(0)?.diffuse_textures.get(0)
+    }
+
+    // Vec space is the one from the previous frame, so resizes lag by a frame
+    fn pre_draw(&mut self, screen_width: i32, screen_height: i32) -> Result<(), EngineError> {
+        for widget in &mut self.children {
+            widget.pre_draw(screen_width, screen_height)?;
+        }
+
+        let (width, height) = self.get_size_in_pixels(screen_width, screen_height);
+        let (width, height) = (width.round() as i32, height.round() as i32);
+
+        if width > 0 && height > 0 && (width, height) != self.scene.get_size() {
+            self.scene.set_size(width, height)?;
+        }
+
+        self.scene.draw()?;
+
+        // Resizing can recreate the pipeline's textures, so always get the latest
+        self.model.meshes[0].diffuse_textures = self.scene.get_render_pipeline().get_link()?;
+
+        Ok(())
+    }
+
+    fn update(&mut self, delta_time: f32) {
+        self.scene.update(delta_time);
+
+        for widget in &mut self.children {
+            widget.update(delta_time);
+        }
+    }
+
+    // The scene is drawn in pre_draw, so it is recorded before the children here
+    // and nests inside this widget's records
+    fn record_children(&mut self, vec_space: &Matrix4<f32>, recorder: &mut DrawRecorder) {
+        self.scene.record(recorder);
+
+        for widget in &mut self.children {
+            widget.record(vec_space, recorder);
+        }
+    }
+
+    fn update_shader_program(&self) -> Result<(), EngineError> {
+        self.shader_program.set_bool("has_bloom", self.model.meshes[0].diffuse_textures.len() > 1)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+// Where lines of text are allowed to break when they reach the width limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakRules {
+    // Characters that can be broken on, and are removed from the start and end of lines
+    pub break_on: Vec<char>,
+    // Characters that can be broken after, which are kept at the end of the line
+    pub break_after: Vec<char>
+}
+
+impl Default for BreakRules {
+    fn default() -> Self {
+        Self {
+            break_on: vec![' ', '\t'],
+            break_after: vec!['-']
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextWrap {
+    // Breaks lines between words, words longer than a line are broken anywhere
+    Word(BreakRules),
+    // Breaks lines at whichever character reaches the limit
+    Character,
+    // Only breaks on newlines, anything past the limit is cropped
+    None
+}
+
+impl Default for TextWrap {
+    fn default() -> Self {
+        TextWrap::Word(BreakRules::default())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextAlignment {
+    Left,
+    Centre,
+    Right
+}
+
+impl Default for TextAlignment {
+    fn default() -> Self {
+        TextAlignment::Left
+    }
+}
+
+impl TextAlignment {
+    // Where a line starts from the left of the box it is aligned in
+    pub fn line_offset(&self, box_width: f32, line_width: f32) -> f32 {
+        match self {
+            TextAlignment::Left => 0.0,
+            TextAlignment::Centre => (box_width - line_width) / 2.0,
+            TextAlignment::Right => box_width - line_width,
+        }
+    }
+}
+
+// Splits text into lines of at most max_chars characters following the wrap mode,
+// cropping any lines past max_lines. Newlines always start a new line.
+pub fn wrap_text(text: &str, max_chars: usize, max_lines: usize, wrap: &TextWrap) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    if max_chars == 0 {
+        return lines;
+    }
+
+    for paragraph in text.split('\n') {
+        let chars: Vec<char> = paragraph.chars().collect();
+
+        match wrap {
+            TextWrap::Word(rules) => wrap_words(&chars, max_chars, rules, &mut lines),
+            TextWrap::Character => {
+                if chars.is_empty() {
+                    lines.push(String::new());
+                }
+
+                for line in chars.chunks(max_chars) {
+                    lines.push(line.iter().collect());
+                }
+            },
+            TextWrap::None => lines.push(chars.iter().take(max_chars).collect()),
+        }
+
+        if lines.len() >= max_lines {
+            break;
+        }
+    }
+
+    lines.truncate(max_lines);
+    lines
+}
+
+fn wrap_words(chars: &[char], max_chars: usize, rules: &BreakRules, lines: &mut Vec<String>) {
+    let is_break_on = |c: &char| rules.break_on.contains(c);
+    let mut start = 0;
+
+    loop {
+        // Lines never start with characters that would be removed at a break
+        while start < chars.len() && start > 0 && is_break_on(&chars[start]) {
+            start += 1;
+        }
+
+        let remaining = &chars[start..];
+
+        // Only trailing whitespace was left after the last break
+        if remaining.is_empty() && start > 0 {
+            return;
+        }
+
+        if remaining.len() <= max_chars {
+            let end = remaining.len() - remaining.iter().rev().take_while(|c| is_break_on(c)).count();
+            lines.push(remaining[..end].iter().collect());
+
+            return;
+        }
+
+        // Find the last place the line can break, checking one past the limit since
+        // a removed character there still leaves a full line
+        let mut line_end = None;
+        let mut next_start = None;
+        let indent = remaining.iter().take_while(|c| is_break_on(c)).count();
+
+        for i in (indent + 1..=max_chars).rev() {
+            if is_break_on(&remaining[i]) {
+                line_end = Some(i);
+                next_start = Some(i + 1);
+                break;
+            }
+            if rules.break_after.contains(&remaining[i - 1]) {
+                line_end = Some(i);
+                next_start = Some(i);
+                break;
+            }
+        }
+
+        // No break found, so the word is too long and has to be broken anywhere
+        let line_end = line_end.unwrap_or(max_chars);
+        let next_start = next_start.unwrap_or(max_chars);
+
+        let line = &remaining[..line_end];
+        let end = line.len() - line.iter().rev().take_while(|c| is_break_on(c)).count();
+        lines.push(line[..end].iter().collect());
+
+        start += next_start;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word_wrap(text: &str, max_chars: usize) -> Vec<String> {
+        wrap_text(text, max_chars, usize::MAX, &TextWrap::default())
+    }
+
+    #[test]
+    fn short_text_is_one_line() {
+        assert_eq!(word_wrap("hello world", 20), vec!["hello world"]);
+    }
+
+    #[test]
+    fn words_wrap_at_spaces() {
+        assert_eq!(word_wrap("the quick brown fox", 10), vec!["the quick", "brown fox"]);
+    }
+
+    #[test]
+    fn space_at_limit_still_fills_line() {
+        assert_eq!(word_wrap("abcde fgh", 5), vec!["abcde", "fgh"]);
+    }
+
+    #[test]
+    fn long_words_break_anywhere() {
+        assert_eq!(word_wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn break_after_keeps_character() {
+        assert_eq!(word_wrap("well-known", 7), vec!["well-", "known"]);
+    }
+
+    #[test]
+    fn breaks_are_trimmed_from_lines() {
+        assert_eq!(word_wrap("one   two  ", 4), vec!["one", "two"]);
+    }
+
+    #[test]
+    fn leading_indent_is_kept() {
+        assert_eq!(word_wrap("  indented text", 11), vec!["  indented", "text"]);
+    }
+
+    #[test]
+    fn custom_break_rules() {
+        let rules = BreakRules { break_on: vec![','], break_after: vec!['/'] };
+        let wrap = TextWrap::Word(rules);
+
+        assert_eq!(wrap_text("a,b,c", 3, usize::MAX, &wrap), vec!["a,b", "c"]);
+        assert_eq!(wrap_text("ab/cd/ef", 5, usize::MAX, &wrap), vec!["ab/", "cd/ef"]);
+        // Spaces aren't break characters with these rules
+        assert_eq!(wrap_text("ab cd", 3, usize::MAX, &wrap), vec!["ab ", "cd"]);
+    }
+
+    #[test]
+    fn newlines_always_break() {
+        assert_eq!(word_wrap("one\n\ntwo", 10), vec!["one", "", "two"]);
+        assert_eq!(wrap_text("ab\ncd", 10, usize::MAX, &TextWrap::Character), vec!["ab", "cd"]);
+        assert_eq!(wrap_text("ab\n", 10, usize::MAX, &TextWrap::None), vec!["ab", ""]);
+    }
+
+    #[test]
+    fn character_wrap_ignores_words() {
+        assert_eq!(wrap_text("hello world", 4, usize::MAX, &TextWrap::Character), vec!["hell", "o wo", "rld"]);
+    }
+
+    #[test]
+    fn no_wrap_crops_lines() {
+        assert_eq!(wrap_text("hello world", 5, usize::MAX, &TextWrap::None), vec!["hello"]);
+    }
+
+    #[test]
+    fn lines_past_max_are_cropped() {
+        assert_eq!(wrap_text("a b c d", 1, 2, &TextWrap::default()), vec!["a", "b"]);
+        assert_eq!(wrap_text("a\nb\nc", 10, 2, &TextWrap::None), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn zero_width_has_no_lines() {
+        assert!(word_wrap("hello", 0).is_empty());
+    }
+
+    #[test]
+    fn alignment_offsets() {
+        assert_eq!(TextAlignment::Left.line_offset(100.0, 40.0), 0.0);
+        assert_eq!(TextAlignment::Centre.line_offset(100.0, 40.0), 30.0);
+        assert_eq!(TextAlignment::Right.line_offset(100.0, 40.0), 60.0);
+        assert_eq!(TextAlignment::default(), TextAlignment::Left);
+    }
+}
@@ -2,7 +2,7 @@ use std::rc::Rc;
 use cgmath::{Quaternion, Matrix4, Vector2, vec3, vec4, SquareMatrix, vec2};
 use downcast_rs::{Downcast, impl_downcast};
 use silver_gl::{Texture, ShaderProgram, MultiBindModel, ModelTrait};
-use crate::EngineError;
+use crate::{EngineError, DrawRecorder, DrawRecord, ResourceManager};
 
 // TODO: To create a kind of crosshair widget that follows a point (to make animating in widgets cool),
 // TODO: have a widget (child of top-level widget, preferably on the bottom of the vec so it draws
@@ -119,6 +119,19 @@ pub trait Widget: Downcast {
 
         self.set_size(size_vec.x, size_vec.y);
     }
+    // Pixel size of the widget on a screen of the given size, for widgets that
+    // render at their drawn size. Unlike get_size_from_res, the size is treated
+    // as a direction, so translation is ignored, and [-1,1] spanning 2 units is
+    // accounted for. Always positive, even when the vec space flips an axis.
+    fn get_size_in_pixels(&self, screen_width: i32, screen_height: i32) -> (f32, f32) {
+        let (width, height) = self.get_size();
+        let size_vec = self.get_vec_space() * vec4(width, height, 0.0, 0.0);
+
+        (
+            (size_vec.x * screen_width as f32 / 2.0).abs(),
+            (size_vec.y * screen_height as f32 / 2.0).abs()
+        )
+    }
     fn get_position_from_res(&self, screen_width: i32, screen_height: i32) -> Vector2<f32> {
         let pos = self.get_position();
         let mut pos_vec = vec4(pos.x, pos.y, 0.0, 1.0);
@@ -151,6 +164,35 @@ pub trait Widget: Downcast {
     // Only unique props need to be set here
     fn update_shader_program(&self) -> Result<(), EngineError>;
 
+    // Called on the whole tree before the parent scene binds its render pipeline,
+    // for widgets that need to render into their own framebuffers first.
+    // Screen size is that of the parent scene, so pixel sizes can be found.
+    fn pre_draw(&mut self, screen_width: i32, screen_height: i32) -> Result<(), EngineError> {
+        for widget in self.get_children_mut() {
+            widget.pre_draw(screen_width, screen_height)?;
+        }
+
+        Ok(())
+    }
+
+    // Called on the whole tree once a frame before drawing, with the seconds since
+    // the last frame, for widgets that animate themselves
+    fn update(&mut self, delta_time: f32) {
+        for widget in self.get_children_mut() {
+            widget.update(delta_time);
+        }
+    }
+
+    // Called on the whole tree once a frame before drawing, for widgets built from
+    // resources that can be dropped, e.g. text whose glyphs were evicted
+    fn refresh_layout(&mut self, resource_manager: &mut ResourceManager) -> Result<(), EngineError> {
+        for widget in self.get_children_mut() {
+            widget.refresh_layout(resource_manager)?;
+        }
+
+        Ok(())
+    }
+
     // Overridable function for drawing children
     // This exists for composite widgets that may want to alter the vector space of its
     // children, for example padding in a framed widget, which only effects the inner
@@ -183,9 +225,68 @@ pub trait Widget: Downcast {
 
         Ok(())
     }
+
+    // Headless counterpart to draw_children, so must be overridden alongside it
+    fn record_children(&mut self, vec_space: &Matrix4<f32>, recorder: &mut DrawRecorder) {
+        for widget in self.get_children_mut() {
+            widget.record(&vec_space, recorder);
+        }
+    }
+
+    // Walks the tree in the same order as draw() without touching the graphics
+    // library, recording what would have been drawn instead
+    fn record(&mut self, vec_space: &Matrix4<f32>, recorder: &mut DrawRecorder) {
+        let transform_matrix = vec_space * self.transform_matrix();
+        self.set_vec_space(*vec_space);
+
+        recorder.push_depth();
+        self.record_children(&transform_matrix, recorder);
+        recorder.pop_depth();
+
+        recorder.push(DrawRecord::Widget {
+            name: std::any::type_name::<Self>(),
+            depth: recorder.get_depth(),
+            transform: transform_matrix,
+            texture: self.get_texture().map(|texture| texture.get_id())
+        });
+    }
 }
 
 // Instead of using an enum to remove downcasting, this allows for a new widget
 // to be created by anyone who uses the engine.
 // A third party crate is used here for more flexibility with downcasting
-impl_downcast!(Widget);
\ No newline at end of file
+impl_downcast!(Widget);
+
+#[cfg(test)]
+mod tests {
+    use crate::{CSEngine, CSEngineConfig, GraphicsLibrary, ShaderPathBundle, primitives::ContainerWidget};
+    use super::*;
+
+    fn container() -> ContainerWidget {
+        let mut engine = CSEngine::new(CSEngineConfig { gl: GraphicsLibrary::None, ..Default::default() });
+        ContainerWidget::new(engine.resource_manager.load_shader_program(ShaderPathBundle::container()).unwrap())
+    }
+
+    #[test]
+    fn size_in_pixels_ignores_translation_and_flips() {
+        let mut widget = container();
+        widget.set_size(0.5, 0.25);
+        // Scene vec space, which flips y and translates
+        widget.set_vec_space(cgmath::ortho(0.0, 1.0, 1.0, 0.0, -1.0, 1.0));
+
+        let (width, height) = widget.get_size_in_pixels(800, 600);
+        assert!((width - 400.0).abs() < 1e-3);
+        assert!((height - 150.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn size_from_res_round_trips() {
+        let mut widget = container();
+        widget.set_vec_space(Matrix4::from_scale(2.0));
+        widget.set_size_from_res(200.0, 100.0, 800, 600);
+
+        let (width, height) = widget.get_size_from_res(800, 600);
+        assert!((width - 200.0).abs() < 1e-3);
+        assert!((height - 100.0).abs() < 1e-3);
+    }
+}
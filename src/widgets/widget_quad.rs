@@ -2,6 +2,16 @@ use cgmath::{Vector3, Vector2};
 use silver_gl::{Vertex, MultiBindModel, ModelCreateTrait, Mesh};
 
 pub fn create_wquad() -> MultiBindModel {
+    create_wquad_with_tex_coords(|tex_coord| tex_coord)
+}
+
+// Framebuffer textures start at the bottom left, so for displaying them in widgets
+// the texture coordinates are flipped vertically
+pub fn create_flipped_wquad() -> MultiBindModel {
+    create_wquad_with_tex_coords(|tex_coord| Vector2::new(tex_coord.x, 1.0 - tex_coord.y))
+}
+
+fn create_wquad_with_tex_coords(tex_coord: impl Fn(Vector2<f32>) -> Vector2<f32>) -> MultiBindModel {
     // Flat panel definition
     // Starts at (0.0, 0.0) for a default top-left
     // anchor
@@ -9,25 +19,25 @@ pub fn create_wquad() -> MultiBindModel {
         Vertex {
             position: Vector3::new(0.0, 0.0, 0.0),
             normal: Vector3::new(0.0, 0.0, 1.0),
-            tex_coord: Vector2::new(0.0, 0.0),
+            tex_coord: tex_coord(Vector2::new(0.0, 0.0)),
             ..Vertex::default()
         },
         Vertex {
             position: Vector3::new(0.0, 1.0, 0.0),
             normal: Vector3::new(0.0, 0.0, 1.0),
-            tex_coord: Vector2::new(0.0, 1.0),
+            tex_coord: tex_coord(Vector2::new(0.0, 1.0)),
             ..Vertex::default()
         },
         Vertex {
             position: Vector3::new(1.0, 1.0, 0.0),
             normal: Vector3::new(0.0, 0.0, 1.0),
-            tex_coord: Vector2::new(1.0, 1.0),
+            tex_coord: tex_coord(Vector2::new(1.0, 1.0)),
             ..Vertex::default()
         },
         Vertex {
             position: Vector3::new(1.0, 0.0, 0.0),
             normal: Vector3::new(0.0, 0.0, 1.0),
-            tex_coord: Vector2::new(1.0, 0.0),
+            tex_coord: tex_coord(Vector2::new(1.0, 0.0)),
             ..Vertex::default()
         }
     ];